@@ -0,0 +1,81 @@
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use nom::number::Endianness;
+
+const LEN: usize = 1 << 20;
+
+/// A numeric subelement holding doubles, as stored for the real part of a
+/// double array.
+fn double_subelement(values: &[f64], endianness: Endianness) -> Vec<u8> {
+    let big_endian = endianness == Endianness::Big;
+    let u32_bytes = |v: u32| {
+        if big_endian {
+            v.to_be_bytes()
+        } else {
+            v.to_le_bytes()
+        }
+    };
+    let mut element = Vec::with_capacity(8 + values.len() * 8);
+    element.extend(u32_bytes(9));
+    element.extend(u32_bytes(values.len() as u32 * 8));
+    for v in values {
+        if big_endian {
+            element.extend(v.to_be_bytes());
+        } else {
+            element.extend(v.to_le_bytes());
+        }
+    }
+    element
+}
+
+/// The previous decoder of numeric subelements, which decoded one number at
+/// a time. Only doubles are handled here.
+fn parse_double_subelement_per_number(
+    endianness: Endianness,
+    input: &[u8],
+) -> nom::IResult<&[u8], Vec<f64>> {
+    let (i, _data_type) = nom::number::complete::u32(endianness)(input)?;
+    let (i, byte_size) = nom::number::complete::u32(endianness)(i)?;
+    let padding = (8 - byte_size as usize % 8) % 8;
+    let (i, values) = nom::multi::count(
+        nom::number::complete::f64(endianness),
+        byte_size as usize / 8,
+    )(i)?;
+    let (i, _) = nom::bytes::complete::take(padding)(i)?;
+    Ok((i, values))
+}
+
+fn decode_double(c: &mut Criterion) {
+    let values: Vec<f64> = (0..LEN).map(|i| i as f64 * 0.5).collect();
+    let mut group = c.benchmark_group("decode_double");
+    group.throughput(Throughput::Bytes(LEN as u64 * 8));
+    for (byte_order, endianness) in [
+        ("little_endian", Endianness::Little),
+        ("big_endian", Endianness::Big),
+    ] {
+        let element = double_subelement(&values, endianness);
+        group.bench_with_input(
+            BenchmarkId::new("per_number", byte_order),
+            &element,
+            |b, element| {
+                b.iter(|| {
+                    black_box(parse_double_subelement_per_number(endianness, element).unwrap())
+                })
+            },
+        );
+        group.bench_with_input(
+            BenchmarkId::new("bulk", byte_order),
+            &element,
+            |b, element| {
+                b.iter(|| {
+                    black_box(
+                        matfile::parse::parse_numeric_subelement(endianness)(element).unwrap(),
+                    )
+                })
+            },
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, decode_double);
+criterion_main!(benches);
@@ -0,0 +1,109 @@
+use crate::cell::linear_index;
+
+/// A `categorical` array.
+///
+/// Each element is stored as an integer code referring to one of the
+/// categories. Code `n` stands for the `n`-th category (counting from one),
+/// code 0 for an undefined element. Like numeric data, the codes are stored in
+/// column-major order.
+///
+/// ```rust
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let file = std::fs::File::open("tests/categorical.mat")?;
+/// let mat_file = matfile::MatFile::parse(file)?;
+/// if let Some(matfile::ArrayData::Categorical(levels)) = mat_file.find_by_name("levels").map(|a| a.data()) {
+///     println!("Categories: {:?}", levels.categories());
+///     for level in levels.iter() {
+///         println!("{}", level.unwrap_or("<undefined>"));
+///     }
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct CategoricalArray {
+    size: Vec<usize>,
+    codes: Vec<u32>,
+    categories: Vec<String>,
+    is_ordinal: bool,
+    is_protected: bool,
+}
+
+impl CategoricalArray {
+    pub(crate) fn new(
+        size: Vec<usize>,
+        codes: Vec<u32>,
+        categories: Vec<String>,
+        is_ordinal: bool,
+        is_protected: bool,
+    ) -> Self {
+        CategoricalArray {
+            size,
+            codes,
+            categories,
+            is_ordinal,
+            is_protected,
+        }
+    }
+
+    /// The size of this categorical array, see `Array::size`.
+    pub fn size(&self) -> &Vec<usize> {
+        &self.size
+    }
+
+    /// The number of elements.
+    pub fn len(&self) -> usize {
+        self.codes.len()
+    }
+
+    /// Whether this categorical array has no elements at all.
+    pub fn is_empty(&self) -> bool {
+        self.codes.is_empty()
+    }
+
+    /// The names of the categories.
+    pub fn categories(&self) -> &[String] {
+        &self.categories
+    }
+
+    /// The integer codes of all elements in column-major order, 0 for
+    /// undefined elements.
+    pub fn codes(&self) -> &[u32] {
+        &self.codes
+    }
+
+    /// Whether the categories have a mathematical ordering.
+    pub fn is_ordinal(&self) -> bool {
+        self.is_ordinal
+    }
+
+    /// Whether the set of categories is protected, i.e. whether only values
+    /// of the existing categories can be assigned.
+    pub fn is_protected(&self) -> bool {
+        self.is_protected
+    }
+
+    /// Returns the category of the element at the given linear
+    /// (column-major) index. Returns `None` if the index is out of bounds or
+    /// the element is undefined.
+    pub fn get(&self, index: usize) -> Option<&str> {
+        self.category(*self.codes.get(index)?)
+    }
+
+    /// Returns the category of the element at the given zero-based
+    /// subscripts, one per dimension. See `CellArray::get_by_subscripts`.
+    pub fn get_by_subscripts(&self, subscripts: &[usize]) -> Option<&str> {
+        self.get(linear_index(&self.size, subscripts)?)
+    }
+
+    /// Iterates over the categories of all elements in column-major order,
+    /// with `None` for undefined elements.
+    pub fn iter(&self) -> impl Iterator<Item = Option<&str>> {
+        self.codes.iter().map(move |&code| self.category(code))
+    }
+
+    fn category(&self, code: u32) -> Option<&str> {
+        let index = (code as usize).checked_sub(1)?;
+        self.categories.get(index).map(|category| category.as_str())
+    }
+}
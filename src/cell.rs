@@ -0,0 +1,94 @@
+use crate::Array;
+
+/// A cell array.
+///
+/// Each cell holds an array of its own (which can be a cell array again).
+/// The arrays stored in the cells have empty names. Like numeric data, the
+/// cells are stored in column-major order.
+///
+/// ```rust
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let file = std::fs::File::open("tests/cell.mat")?;
+/// let mat_file = matfile::MatFile::parse(file)?;
+/// if let Some(matfile::ArrayData::Cell(cells)) = mat_file.find_by_name("c").map(|a| a.data()) {
+///     for (index, cell) in cells.iter().enumerate() {
+///         println!("Cell {} has size {:?}", index, cell.size());
+///     }
+///     let second_row_first_column = cells.get_by_subscripts(&[1, 0]);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct CellArray {
+    size: Vec<usize>,
+    cells: Vec<Array>,
+}
+
+impl CellArray {
+    pub(crate) fn new(size: Vec<usize>, cells: Vec<Array>) -> Self {
+        CellArray { size, cells }
+    }
+
+    pub(crate) fn into_cells(self) -> Vec<Array> {
+        self.cells
+    }
+
+    /// The size of this cell array, see `Array::size`.
+    pub fn size(&self) -> &Vec<usize> {
+        &self.size
+    }
+
+    /// The total number of cells.
+    pub fn len(&self) -> usize {
+        self.cells.len()
+    }
+
+    /// Whether this cell array has no cells at all.
+    pub fn is_empty(&self) -> bool {
+        self.cells.is_empty()
+    }
+
+    /// Returns the cell at the given linear (column-major) index.
+    pub fn get(&self, index: usize) -> Option<&Array> {
+        self.cells.get(index)
+    }
+
+    /// Returns the cell at the given zero-based subscripts, one per
+    /// dimension. Returns `None` if the number of subscripts does not match
+    /// the number of dimensions or if any subscript is out of bounds.
+    pub fn get_by_subscripts(&self, subscripts: &[usize]) -> Option<&Array> {
+        self.get(linear_index(&self.size, subscripts)?)
+    }
+
+    /// Iterates over all cells in column-major order.
+    pub fn iter(&self) -> std::slice::Iter<'_, Array> {
+        self.cells.iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a CellArray {
+    type Item = &'a Array;
+    type IntoIter = std::slice::Iter<'a, Array>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// Converts zero-based subscripts into a column-major linear index.
+pub(crate) fn linear_index(size: &[usize], subscripts: &[usize]) -> Option<usize> {
+    if subscripts.len() != size.len() {
+        return None;
+    }
+    let mut index = 0;
+    let mut stride = 1;
+    for (&subscript, &dim) in subscripts.iter().zip(size) {
+        if subscript >= dim {
+            return None;
+        }
+        index += subscript * stride;
+        stride *= dim;
+    }
+    Some(index)
+}
@@ -0,0 +1,77 @@
+/// A character array.
+///
+/// Matlab stores characters as UTF-16 code units, so that is what this type
+/// holds as well. Like numeric data, the characters are stored in column-major
+/// order, which means that for a matrix of characters (as created by Matlab's
+/// `char` function with several arguments) each row is one string.
+///
+/// ```rust
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let file = std::fs::File::open("tests/character.mat")?;
+/// let mat_file = matfile::MatFile::parse(file)?;
+/// if let Some(matfile::ArrayData::Char(chars)) = mat_file.find_by_name("chr").map(|a| a.data()) {
+///     assert_eq!(chars.to_string(), "Hello, world!");
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct CharArray {
+    size: Vec<usize>,
+    data: Vec<u16>,
+}
+
+impl CharArray {
+    pub(crate) fn new(size: Vec<usize>, data: Vec<u16>) -> Self {
+        CharArray { size, data }
+    }
+
+    /// The size of this character array, see `Array::size`.
+    pub fn size(&self) -> &Vec<usize> {
+        &self.size
+    }
+
+    /// The raw UTF-16 code units in column-major order.
+    pub fn code_units(&self) -> &[u16] {
+        &self.data
+    }
+
+    /// The number of rows of this character array.
+    pub fn num_rows(&self) -> usize {
+        self.size.first().copied().unwrap_or(0)
+    }
+
+    /// Returns each row of the character array as a separate string.
+    ///
+    /// All dimensions after the first one are treated as columns, i.e. a
+    /// character array of size `[2, 3, 4]` results in two strings with twelve
+    /// characters each. Invalid UTF-16 is replaced with the replacement
+    /// character. Note that Matlab pads the rows of character matrices with
+    /// spaces, these are not removed.
+    pub fn rows(&self) -> Vec<String> {
+        let num_rows = self.num_rows();
+        if num_rows == 0 {
+            return Vec::new();
+        }
+        (0..num_rows)
+            .map(|row| {
+                let units: Vec<u16> = self
+                    .data
+                    .iter()
+                    .skip(row)
+                    .step_by(num_rows)
+                    .copied()
+                    .collect();
+                String::from_utf16_lossy(&units)
+            })
+            .collect()
+    }
+}
+
+/// Formats the character array as text. The rows of a character matrix are
+/// separated by line breaks.
+impl std::fmt::Display for CharArray {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.rows().join("\n"))
+    }
+}
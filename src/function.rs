@@ -0,0 +1,172 @@
+use crate::{mcos, parse, Array, ArrayData, Error};
+
+/// A function handle, such as `@sin` or `@(x) x.^2`.
+///
+/// Matlab does not store the code of the function, only a description of it:
+/// the name of the function (or the expression of an anonymous function), the
+/// file it was defined in and, for anonymous functions, the variables it
+/// captured.
+///
+/// ```rust
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let file = std::fs::File::open("tests/function.mat")?;
+/// let mat_file = matfile::MatFile::parse(file)?;
+/// if let Some(matfile::ArrayData::FunctionHandle(f)) = mat_file.find_by_name("square").map(|a| a.data()) {
+///     println!("{:?} function {}", f.kind(), f.function());
+///     for variable in f.captured_variables() {
+///         println!("Captured {}", variable.name());
+///     }
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct FunctionHandle {
+    kind: FunctionHandleKind,
+    function: String,
+    file: Option<String>,
+    captured_variables: Vec<Array>,
+}
+
+/// The kind of a function handle.
+#[derive(Clone, Debug, PartialEq)]
+pub enum FunctionHandleKind {
+    /// A handle to a named function, e.g. `@sin`
+    Simple,
+    /// An anonymous function, e.g. `@(x) x.^2`
+    Anonymous,
+    /// A handle to a nested function
+    Nested,
+    /// A handle to a local function of a file
+    Scoped,
+    /// Any other kind, with the name Matlab uses for it
+    Other(String),
+}
+
+impl FunctionHandle {
+    /// Converts the struct describing a function handle. Matlab wraps the
+    /// actual description in a field called `function_handle`.
+    ///
+    /// The captured variables are converted individually and the ones of
+    /// unsupported type are left out, so that a function handle can be loaded
+    /// even if not all of its workspace can.
+    pub(crate) fn from_data_element(
+        data: parse::DataElement,
+        context: mcos::Context,
+    ) -> Result<Option<Self>, Error> {
+        let mut fields = match struct_fields(data) {
+            Some(fields) => fields,
+            None => return Ok(None),
+        };
+        if let Some(index) = fields
+            .iter()
+            .position(|(name, _)| name == "function_handle")
+        {
+            fields = match struct_fields(fields.swap_remove(index).1) {
+                Some(fields) => fields,
+                None => return Ok(None),
+            };
+        }
+        let mut function = None;
+        let mut kind = None;
+        let mut file = None;
+        let mut captured_variables = Vec::new();
+        for (name, value) in fields {
+            match name.as_str() {
+                "function" => function = text(value, context)?,
+                "type" => kind = text(value, context)?,
+                "file" => file = text(value, context)?.filter(|file| !file.is_empty()),
+                "workspace" => captured_variables = workspace(value, context)?,
+                _ => {}
+            }
+        }
+        let function = match function {
+            Some(function) => function,
+            None => return Ok(None),
+        };
+        let kind = match kind.as_deref() {
+            Some("simple") => FunctionHandleKind::Simple,
+            Some("anonymous") => FunctionHandleKind::Anonymous,
+            Some("nested") => FunctionHandleKind::Nested,
+            Some("scopedfunction") => FunctionHandleKind::Scoped,
+            Some(other) => FunctionHandleKind::Other(other.to_owned()),
+            None => FunctionHandleKind::Simple,
+        };
+        Ok(Some(FunctionHandle {
+            kind,
+            function,
+            file,
+            captured_variables,
+        }))
+    }
+
+    /// The kind of this function handle.
+    pub fn kind(&self) -> &FunctionHandleKind {
+        &self.kind
+    }
+
+    /// The name of the function, or the expression of an anonymous function
+    /// (e.g. `@(x)x.^2`).
+    pub fn function(&self) -> &str {
+        &self.function
+    }
+
+    /// The file the function was defined in, if any.
+    pub fn file(&self) -> Option<&str> {
+        self.file.as_deref()
+    }
+
+    /// The variables captured by an anonymous function, named like the
+    /// variables.
+    pub fn captured_variables(&self) -> &[Array] {
+        &self.captured_variables
+    }
+
+    /// Returns the captured variable with the given name. Case sensitive.
+    pub fn captured_variable(&self, name: &str) -> Option<&Array> {
+        self.captured_variables
+            .iter()
+            .find(|variable| variable.name() == name)
+    }
+}
+
+/// Splits a 1x1 struct into pairs of field name and value.
+fn struct_fields(data: parse::DataElement) -> Option<Vec<(String, parse::DataElement)>> {
+    match data {
+        parse::DataElement::StructureMatrix(_, _, _, field_names, values)
+            if values.len() == field_names.len() =>
+        {
+            Some(field_names.into_iter().zip(values).collect())
+        }
+        _ => None,
+    }
+}
+
+fn text(data: parse::DataElement, context: mcos::Context) -> Result<Option<String>, Error> {
+    Ok(match Array::from_data_element(data, context)? {
+        Some(Array {
+            data: ArrayData::Char(chars),
+            ..
+        }) => Some(chars.to_string()),
+        _ => None,
+    })
+}
+
+/// Converts the workspace of an anonymous function: a struct whose fields are
+/// the captured variables, possibly wrapped in a cell array.
+fn workspace(data: parse::DataElement, context: mcos::Context) -> Result<Vec<Array>, Error> {
+    let data = match data {
+        parse::DataElement::CellMatrix(_, _, _, cells) => match cells.into_iter().next() {
+            Some(data) => data,
+            None => return Ok(Vec::new()),
+        },
+        data => data,
+    };
+    let mut variables = Vec::new();
+    for (name, value) in struct_fields(data).unwrap_or_default() {
+        if let Some(variable) = Array::from_data_element(value, context)? {
+            variables.push(variable.with_name(&name));
+        }
+    }
+    Ok(variables)
+}
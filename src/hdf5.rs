@@ -0,0 +1,1513 @@
+use crate::parse::{
+    fail, unsupported, valid_sparse_indices, ArrayFlags, ArrayType, DataElement, NumericData,
+};
+use crate::ParseErrorKind;
+use libflate::zlib::Decoder;
+use std::convert::{TryFrom, TryInto};
+use std::io::Read;
+
+// A minimal HDF5 reader, just enough to read the files Matlab writes for
+// version 7.3 MAT files.
+// https://docs.hdfgroup.org/hdf5/develop/_f_m_t3.html
+// https://www.mathworks.com/help/matlab/import_export/mat-file-versions.html
+
+type Failure<'a> = nom::Err<crate::parse::Error<&'a [u8]>>;
+type Parsed<'a, T> = Result<T, Failure<'a>>;
+
+const SIGNATURE: &[u8] = b"\x89HDF\r\n\x1a\n";
+const MAX_DEPTH: usize = 64;
+
+const MESSAGE_DATASPACE: u16 = 0x0001;
+const MESSAGE_LINK_INFO: u16 = 0x0002;
+const MESSAGE_DATATYPE: u16 = 0x0003;
+const MESSAGE_LINK: u16 = 0x0006;
+const MESSAGE_LAYOUT: u16 = 0x0008;
+const MESSAGE_FILTER_PIPELINE: u16 = 0x000B;
+const MESSAGE_ATTRIBUTE: u16 = 0x000C;
+const MESSAGE_CONTINUATION: u16 = 0x0010;
+const MESSAGE_SYMBOL_TABLE: u16 = 0x0011;
+
+fn failure<'a>(i: &'a [u8], message: &'static str) -> Failure<'a> {
+    fail(i, ParseErrorKind::Invalid(message))
+}
+
+/// The superblock is located at offset 0, 512, 1024, 2048 and so on. Matlab
+/// puts it right after the 512 byte user block holding the MAT file header.
+fn superblock_position(input: &[u8]) -> Option<usize> {
+    std::iter::once(0)
+        .chain((9..64).map(|shift| 1usize << shift))
+        .take_while(|&position| position < input.len())
+        .find(|&position| input[position..].starts_with(SIGNATURE))
+}
+
+/// Parses all variables of a version 7.3 MAT file.
+///
+/// Every variable is an HDF5 dataset or group in the root group, with a
+/// `MATLAB_class` attribute telling the class of the variable. Variables of
+/// unsupported class are returned as `DataElement::UnsupportedMatrix`. Only
+/// the variables whose name is accepted by `select` are read.
+pub fn parse_all<'a>(
+    input: &'a [u8],
+    select: &(dyn Fn(&str) -> bool + Sync),
+) -> Parsed<'a, Vec<DataElement>> {
+    let file = File::open(input)?;
+    let root = file.object(file.root)?;
+    let mut data_elements = Vec::new();
+    for (name, address) in file.links(&root.messages)? {
+        // Cell contents and the subsystem data are stored in groups with
+        // special names, they are not variables themselves
+        if name.starts_with('#') || !select(&name) {
+            continue;
+        }
+        let in_variable = |err: Failure<'a>| err.map(|err| err.in_variable(|| Some(name.clone())));
+        let object = file.object(address).map_err(in_variable)?;
+        let data_element = file
+            .convert(name.clone(), &object, 0)
+            .map_err(in_variable)?;
+        data_elements.push(data_element);
+    }
+    Ok(data_elements)
+}
+
+/// A cursor over the bytes of the file. All HDF5 structures are stored in
+/// little endian byte order.
+#[derive(Clone, Copy)]
+struct Reader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Reader { data, position: 0 }
+    }
+
+    fn remaining(&self) -> &'a [u8] {
+        self.data.get(self.position..).unwrap_or(&[])
+    }
+
+    fn bytes(&mut self, len: usize) -> Parsed<'a, &'a [u8]> {
+        let remaining = self.remaining();
+        if len > remaining.len() {
+            return Err(fail(remaining, ParseErrorKind::Truncated));
+        }
+        self.position += len;
+        Ok(&remaining[..len])
+    }
+
+    fn skip(&mut self, len: usize) -> Parsed<'a, ()> {
+        self.bytes(len).map(|_| ())
+    }
+
+    fn tag(&mut self, tag: &[u8]) -> Parsed<'a, ()> {
+        let remaining = self.remaining();
+        if self.bytes(tag.len())? != tag {
+            return Err(failure(remaining, "invalid signature"));
+        }
+        Ok(())
+    }
+
+    fn uint(&mut self, size: usize) -> Parsed<'a, u64> {
+        let remaining = self.remaining();
+        if size > 8 {
+            return Err(failure(remaining, "integer too large"));
+        }
+        let mut bytes = [0; 8];
+        bytes[..size].copy_from_slice(self.bytes(size)?);
+        Ok(u64::from_le_bytes(bytes))
+    }
+
+    fn u8(&mut self) -> Parsed<'a, u8> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn u16(&mut self) -> Parsed<'a, u16> {
+        Ok(self.uint(2)? as u16)
+    }
+
+    fn u32(&mut self) -> Parsed<'a, u32> {
+        Ok(self.uint(4)? as u32)
+    }
+
+    fn usize(&mut self, size: usize) -> Parsed<'a, usize> {
+        let remaining = self.remaining();
+        let value = self.uint(size)?;
+        usize::try_from(value).map_err(|_| failure(remaining, "integer too large"))
+    }
+
+    /// Reads a NUL terminated string.
+    fn string(&mut self) -> Parsed<'a, String> {
+        let remaining = self.remaining();
+        let len = remaining
+            .iter()
+            .position(|&b| b == 0)
+            .ok_or_else(|| fail(remaining, ParseErrorKind::Truncated))?;
+        let string = String::from_utf8_lossy(&remaining[..len]).into_owned();
+        self.position += len + 1;
+        Ok(string)
+    }
+}
+
+struct File<'a> {
+    /// The file starting at the superblock. All addresses are relative to it.
+    data: &'a [u8],
+    offset_size: usize,
+    length_size: usize,
+    root: u64,
+}
+
+#[derive(Clone, Copy)]
+struct Message<'a> {
+    kind: u16,
+    data: &'a [u8],
+}
+
+/// The header messages and attributes of a dataset or group.
+struct Object<'a> {
+    /// The file from the start of the object header on
+    header: &'a [u8],
+    messages: Vec<Message<'a>>,
+    attributes: Vec<Attribute<'a>>,
+}
+
+struct Attribute<'a> {
+    name: String,
+    datatype: Datatype,
+    dims: Vec<u64>,
+    data: &'a [u8],
+}
+
+#[derive(Clone, Debug)]
+enum Datatype {
+    Integer {
+        size: usize,
+        signed: bool,
+        big_endian: bool,
+    },
+    Float {
+        size: usize,
+        big_endian: bool,
+    },
+    String {
+        size: usize,
+    },
+    Compound {
+        size: usize,
+        members: Vec<(String, usize, Datatype)>,
+    },
+    Reference {
+        size: usize,
+    },
+    VariableLength {
+        size: usize,
+    },
+    Other {
+        size: usize,
+    },
+}
+
+impl Datatype {
+    fn size(&self) -> usize {
+        match *self {
+            Datatype::Integer { size, .. }
+            | Datatype::Float { size, .. }
+            | Datatype::String { size }
+            | Datatype::Compound { size, .. }
+            | Datatype::Reference { size }
+            | Datatype::VariableLength { size }
+            | Datatype::Other { size } => size,
+        }
+    }
+
+    /// Decodes numeric data. Complex numbers are stored as a compound of a
+    /// real and an imaginary part.
+    fn numeric(&self, bytes: &[u8]) -> Option<(NumericData, Option<NumericData>)> {
+        if self.size() == 0 {
+            return None;
+        }
+        if let Datatype::Compound { size, members } = self {
+            let member = |name: &str| {
+                let (_, offset, datatype) = members.iter().find(|member| member.0 == name)?;
+                let member_size = datatype.size();
+                if offset + member_size > *size {
+                    return None;
+                }
+                let bytes: Vec<u8> = bytes
+                    .chunks_exact(*size)
+                    .flat_map(|element| &element[*offset..offset + member_size])
+                    .copied()
+                    .collect();
+                datatype.numeric(&bytes).map(|(real, _)| real)
+            };
+            return Some((member("real")?, Some(member("imag")?)));
+        }
+        macro_rules! decode {
+            ($t:ty, $variant:ident, $big_endian:expr) => {
+                NumericData::$variant(
+                    bytes
+                        .chunks_exact(std::mem::size_of::<$t>())
+                        .map(|chunk| {
+                            let mut value = [0; std::mem::size_of::<$t>()];
+                            value.copy_from_slice(chunk);
+                            if $big_endian {
+                                <$t>::from_be_bytes(value)
+                            } else {
+                                <$t>::from_le_bytes(value)
+                            }
+                        })
+                        .collect(),
+                )
+            };
+        }
+        let data = match *self {
+            Datatype::Integer {
+                size,
+                signed,
+                big_endian,
+            } => match (size, signed) {
+                (1, true) => decode!(i8, Int8, big_endian),
+                (1, false) => decode!(u8, UInt8, big_endian),
+                (2, true) => decode!(i16, Int16, big_endian),
+                (2, false) => decode!(u16, UInt16, big_endian),
+                (4, true) => decode!(i32, Int32, big_endian),
+                (4, false) => decode!(u32, UInt32, big_endian),
+                (8, true) => decode!(i64, Int64, big_endian),
+                (8, false) => decode!(u64, UInt64, big_endian),
+                _ => return None,
+            },
+            Datatype::Float { size, big_endian } => match size {
+                4 => decode!(f32, Single, big_endian),
+                8 => decode!(f64, Double, big_endian),
+                _ => return None,
+            },
+            _ => return None,
+        };
+        Some((data, None))
+    }
+}
+
+impl<'a> Attribute<'a> {
+    fn text(&self) -> Option<String> {
+        match self.datatype {
+            Datatype::String { .. } => {
+                let text = self.data.split(|&b| b == 0).next().unwrap_or(&[]);
+                Some(String::from_utf8_lossy(text).into_owned())
+            }
+            _ => None,
+        }
+    }
+
+    fn integer(&self) -> Option<u64> {
+        match self.datatype.numeric(self.data)?.0 {
+            NumericData::UInt8(values) => values.first().map(|&v| v as u64),
+            NumericData::UInt16(values) => values.first().map(|&v| v as u64),
+            NumericData::UInt32(values) => values.first().map(|&v| v as u64),
+            NumericData::UInt64(values) => values.first().copied(),
+            NumericData::Int8(values) => values.first().map(|&v| v.max(0) as u64),
+            NumericData::Int16(values) => values.first().map(|&v| v.max(0) as u64),
+            NumericData::Int32(values) => values.first().map(|&v| v.max(0) as u64),
+            NumericData::Int64(values) => values.first().map(|&v| v.max(0) as u64),
+            NumericData::Single(values) => values.first().map(|&v| v as u64),
+            NumericData::Double(values) => values.first().map(|&v| v as u64),
+        }
+    }
+}
+
+impl<'a> Object<'a> {
+    fn attribute(&self, name: &str) -> Option<&Attribute<'a>> {
+        self.attributes
+            .iter()
+            .find(|attribute| attribute.name == name)
+    }
+
+    fn message(&self, kind: u16) -> Option<&'a [u8]> {
+        self.messages
+            .iter()
+            .find(|message| message.kind == kind)
+            .map(|message| message.data)
+    }
+
+    fn is_dataset(&self) -> bool {
+        self.message(MESSAGE_LAYOUT).is_some()
+    }
+}
+
+enum Layout<'a> {
+    Compact(&'a [u8]),
+    Contiguous(Option<u64>),
+    Chunked {
+        btree: Option<u64>,
+        chunk_dims: Vec<usize>,
+    },
+}
+
+struct Filter {
+    id: u16,
+    client_data: Vec<u32>,
+}
+
+impl<'a> File<'a> {
+    fn open(input: &'a [u8]) -> Parsed<'a, Self> {
+        let position =
+            superblock_position(input).ok_or_else(|| failure(input, "no HDF5 superblock"))?;
+        let data = &input[position..];
+        let mut reader = Reader::new(data);
+        reader.tag(SIGNATURE)?;
+        let version = reader.u8()?;
+        let (offset_size, length_size, root) = match version {
+            0 | 1 => {
+                reader.skip(4)?;
+                let offset_size = reader.u8()? as usize;
+                let length_size = reader.u8()? as usize;
+                reader.skip(1 + 2 + 2 + 4)?;
+                if version == 1 {
+                    reader.skip(4)?;
+                }
+                // Base address, free space, end of file and driver addresses
+                reader.skip(4 * offset_size)?;
+                // Root group symbol table entry
+                reader.skip(offset_size)?;
+                let root = reader.uint(offset_size)?;
+                (offset_size, length_size, root)
+            }
+            2 | 3 => {
+                let offset_size = reader.u8()? as usize;
+                let length_size = reader.u8()? as usize;
+                reader.skip(1)?;
+                // Base address, superblock extension and end of file addresses
+                reader.skip(3 * offset_size)?;
+                let root = reader.uint(offset_size)?;
+                (offset_size, length_size, root)
+            }
+            _ => return Err(unsupported(data)),
+        };
+        if !(1..=8).contains(&offset_size) || !(1..=8).contains(&length_size) {
+            return Err(failure(data, "invalid superblock"));
+        }
+        Ok(File {
+            data,
+            offset_size,
+            length_size,
+            root,
+        })
+    }
+
+    fn reader_at(&self, address: u64) -> Parsed<'a, Reader<'a>> {
+        match usize::try_from(address) {
+            Ok(position) if position < self.data.len() => Ok(Reader {
+                data: self.data,
+                position,
+            }),
+            _ => Err(fail(
+                &self.data[self.data.len()..],
+                ParseErrorKind::Truncated,
+            )),
+        }
+    }
+
+    fn slice(&self, address: u64, len: usize) -> Parsed<'a, &'a [u8]> {
+        self.reader_at(address)?.bytes(len)
+    }
+
+    /// Reads an address, `None` if it is undefined (all bits set).
+    fn address(&self, reader: &mut Reader<'a>) -> Parsed<'a, Option<u64>> {
+        let address = reader.uint(self.offset_size)?;
+        let undefined = u64::MAX >> (64 - 8 * self.offset_size);
+        Ok(if address == undefined {
+            None
+        } else {
+            Some(address)
+        })
+    }
+
+    fn length(&self, reader: &mut Reader<'a>) -> Parsed<'a, usize> {
+        reader.usize(self.length_size)
+    }
+
+    fn object(&self, address: u64) -> Parsed<'a, Object<'a>> {
+        let header = self.reader_at(address)?.remaining();
+        let messages = self.messages(address)?;
+        let attributes = messages
+            .iter()
+            .filter(|message| message.kind == MESSAGE_ATTRIBUTE)
+            .map(|message| self.attribute(message.data))
+            .collect::<Result<_, _>>()?;
+        Ok(Object {
+            header,
+            messages,
+            attributes,
+        })
+    }
+
+    /// Reads all messages of an object header, following continuation
+    /// messages.
+    fn messages(&self, address: u64) -> Parsed<'a, Vec<Message<'a>>> {
+        let mut reader = self.reader_at(address)?;
+        let is_version2 = reader.remaining().starts_with(b"OHDR");
+        let mut blocks = Vec::new();
+        let mut creation_order = false;
+        if is_version2 {
+            reader.tag(b"OHDR")?;
+            if reader.u8()? != 2 {
+                return Err(failure(reader.remaining(), "invalid object header version"));
+            }
+            let flags = reader.u8()?;
+            creation_order = flags & 0x04 != 0;
+            if flags & 0x20 != 0 {
+                reader.skip(16)?;
+            }
+            if flags & 0x10 != 0 {
+                reader.skip(4)?;
+            }
+            let size = reader.usize(1 << (flags & 0x03))?;
+            blocks.push(reader.bytes(size)?);
+        } else {
+            if reader.u8()? != 1 {
+                return Err(failure(reader.remaining(), "invalid object header version"));
+            }
+            reader.skip(1 + 2 + 4)?;
+            let size = reader.u32()? as usize;
+            // The messages are aligned to 8 bytes
+            reader.skip(4)?;
+            blocks.push(reader.bytes(size)?);
+        }
+        let mut messages = Vec::new();
+        let mut index = 0;
+        while index < blocks.len() {
+            let mut reader = Reader::new(blocks[index]);
+            index += 1;
+            let header_size = if is_version2 {
+                4 + if creation_order { 2 } else { 0 }
+            } else {
+                8
+            };
+            while reader.remaining().len() >= header_size {
+                let (kind, size) = if is_version2 {
+                    let kind = reader.u8()? as u16;
+                    let size = reader.u16()? as usize;
+                    reader.skip(header_size - 3)?;
+                    (kind, size)
+                } else {
+                    let kind = reader.u16()?;
+                    let size = reader.u16()? as usize;
+                    reader.skip(4)?;
+                    (kind, size)
+                };
+                let data = reader.bytes(size)?;
+                if kind == MESSAGE_CONTINUATION {
+                    if blocks.len() > 4096 {
+                        return Err(failure(data, "too many object header continuations"));
+                    }
+                    let mut continuation = Reader::new(data);
+                    let address = self.address(&mut continuation)?;
+                    let length = self.length(&mut continuation)?;
+                    let address =
+                        address.ok_or_else(|| failure(data, "undefined continuation address"))?;
+                    let mut block = self.reader_at(address)?;
+                    let block = if is_version2 {
+                        block.tag(b"OCHK")?;
+                        // Leave out the checksum at the end
+                        let length = length
+                            .checked_sub(8)
+                            .ok_or_else(|| failure(data, "invalid continuation length"))?;
+                        block.bytes(length)?
+                    } else {
+                        block.bytes(length)?
+                    };
+                    blocks.push(block);
+                } else {
+                    messages.push(Message { kind, data });
+                }
+            }
+        }
+        Ok(messages)
+    }
+
+    fn attribute(&self, data: &'a [u8]) -> Parsed<'a, Attribute<'a>> {
+        let mut reader = Reader::new(data);
+        let version = reader.u8()?;
+        if !(1..=3).contains(&version) {
+            return Err(failure(data, "invalid attribute version"));
+        }
+        reader.skip(1)?;
+        let name_size = reader.u16()? as usize;
+        let datatype_size = reader.u16()? as usize;
+        let dataspace_size = reader.u16()? as usize;
+        if version == 3 {
+            // Character set of the name
+            reader.skip(1)?;
+        }
+        // Version 1 pads all parts to multiples of 8 bytes
+        let padded = |size: usize| {
+            if version == 1 {
+                size.div_ceil(8) * 8
+            } else {
+                size
+            }
+        };
+        let name = reader.bytes(padded(name_size))?;
+        let name = name[..name_size].split(|&b| b == 0).next().unwrap_or(&[]);
+        let name = String::from_utf8_lossy(name).into_owned();
+        let datatype = self.datatype(reader.bytes(padded(datatype_size))?)?;
+        let dims = self.dataspace(reader.bytes(padded(dataspace_size))?)?;
+        // A null dataspace has no elements at all
+        let count = match &dims {
+            Some(dims) => element_count(dims),
+            None => Some(0),
+        };
+        let dims = dims.unwrap_or_default();
+        let len = count
+            .and_then(|count| count.checked_mul(datatype.size()))
+            .ok_or_else(|| failure(data, "attribute too large"))?;
+        let data = reader.bytes(len)?;
+        Ok(Attribute {
+            name,
+            datatype,
+            dims,
+            data,
+        })
+    }
+
+    fn datatype(&self, data: &'a [u8]) -> Parsed<'a, Datatype> {
+        let mut reader = Reader::new(data);
+        let class_and_version = reader.u8()?;
+        let bits = reader.bytes(3)?;
+        let size = reader.u32()? as usize;
+        let version = class_and_version >> 4;
+        let big_endian = bits[0] & 0x01 != 0;
+        Ok(match class_and_version & 0x0F {
+            0 => Datatype::Integer {
+                size,
+                signed: bits[0] & 0x08 != 0,
+                big_endian,
+            },
+            1 => Datatype::Float { size, big_endian },
+            3 => Datatype::String { size },
+            6 => {
+                let count = u16::from_le_bytes([bits[0], bits[1]]) as usize;
+                let mut members = Vec::with_capacity(count);
+                for _ in 0..count {
+                    let start = reader.position;
+                    let name = reader.string()?;
+                    let offset = match version {
+                        1 | 2 => {
+                            // The name is padded to a multiple of 8 bytes
+                            let len = reader.position - start;
+                            reader.skip((8 - len % 8) % 8)?;
+                            reader.u32()? as usize
+                        }
+                        _ => {
+                            let offset_size = (1..=8)
+                                .find(|&bytes| bytes >= 8 || size >> (8 * bytes) == 0)
+                                .unwrap_or(8);
+                            reader.usize(offset_size)?
+                        }
+                    };
+                    if version == 1 {
+                        // Dimensionality, permutation and dimension sizes
+                        reader.skip(1 + 3 + 4 + 4 + 16)?;
+                    }
+                    let datatype = self.datatype(reader.remaining())?;
+                    reader.skip(datatype_message_size(&datatype, reader.remaining())?)?;
+                    members.push((name, offset, datatype));
+                }
+                Datatype::Compound { size, members }
+            }
+            7 => Datatype::Reference { size },
+            9 => Datatype::VariableLength { size },
+            _ => Datatype::Other { size },
+        })
+    }
+
+    /// Parses a dataspace message. Returns the dimensions (empty for a
+    /// scalar) or `None` for a null dataspace.
+    fn dataspace(&self, data: &'a [u8]) -> Parsed<'a, Option<Vec<u64>>> {
+        let mut reader = Reader::new(data);
+        let version = reader.u8()?;
+        let rank = reader.u8()? as usize;
+        let _flags = reader.u8()?;
+        match version {
+            1 => reader.skip(5)?,
+            2 => {
+                if reader.u8()? == 2 {
+                    return Ok(None);
+                }
+            }
+            _ => return Err(failure(data, "invalid dataspace version")),
+        }
+        let dims = (0..rank)
+            .map(|_| reader.uint(self.length_size))
+            .collect::<Result<_, _>>()?;
+        Ok(Some(dims))
+    }
+
+    fn layout(&self, data: &'a [u8]) -> Parsed<'a, Layout<'a>> {
+        let mut reader = Reader::new(data);
+        let version = reader.u8()?;
+        match version {
+            1 | 2 => {
+                let rank = reader.u8()? as usize;
+                let class = reader.u8()?;
+                reader.skip(5)?;
+                let address = if class == 0 {
+                    None
+                } else {
+                    self.address(&mut reader)?
+                };
+                let dims = (0..rank)
+                    .map(|_| reader.u32().map(|d| d as usize))
+                    .collect::<Result<Vec<_>, _>>()?;
+                match class {
+                    0 => {
+                        let size = reader.u32()? as usize;
+                        Ok(Layout::Compact(reader.bytes(size)?))
+                    }
+                    1 => Ok(Layout::Contiguous(address)),
+                    2 if !dims.is_empty() => Ok(Layout::Chunked {
+                        btree: address,
+                        chunk_dims: dims[..rank - 1].to_vec(),
+                    }),
+                    _ => Err(failure(data, "invalid layout")),
+                }
+            }
+            3 | 4 => match reader.u8()? {
+                0 => {
+                    let size = reader.u16()? as usize;
+                    Ok(Layout::Compact(reader.bytes(size)?))
+                }
+                1 => Ok(Layout::Contiguous(self.address(&mut reader)?)),
+                // Version 4 uses new kinds of chunk indices, which are not
+                // supported
+                2 if version == 3 => {
+                    let rank = reader.u8()? as usize;
+                    let btree = self.address(&mut reader)?;
+                    let dims = (0..rank)
+                        .map(|_| reader.u32().map(|d| d as usize))
+                        .collect::<Result<Vec<_>, _>>()?;
+                    if dims.is_empty() {
+                        return Err(failure(data, "invalid layout"));
+                    }
+                    Ok(Layout::Chunked {
+                        btree,
+                        chunk_dims: dims[..rank - 1].to_vec(),
+                    })
+                }
+                _ => Err(unsupported(data)),
+            },
+            _ => Err(failure(data, "invalid layout version")),
+        }
+    }
+
+    fn filters(&self, data: &'a [u8]) -> Parsed<'a, Vec<Filter>> {
+        let mut reader = Reader::new(data);
+        let version = reader.u8()?;
+        let count = reader.u8()? as usize;
+        if version == 1 {
+            reader.skip(6)?;
+        }
+        let mut filters = Vec::with_capacity(count);
+        for _ in 0..count {
+            let id = reader.u16()?;
+            let name_length = if version == 1 || id >= 256 {
+                reader.u16()? as usize
+            } else {
+                0
+            };
+            let _flags = reader.u16()?;
+            let client_data_count = reader.u16()? as usize;
+            if version == 1 {
+                reader.skip(name_length.div_ceil(8) * 8)?;
+            } else {
+                reader.skip(name_length)?;
+            }
+            let client_data = (0..client_data_count)
+                .map(|_| reader.u32())
+                .collect::<Result<_, _>>()?;
+            if version == 1 && client_data_count % 2 == 1 {
+                reader.skip(4)?;
+            }
+            filters.push(Filter { id, client_data });
+        }
+        Ok(filters)
+    }
+
+    /// Reads the raw data of a dataset, decompressing it if necessary.
+    fn data(&self, object: &Object<'a>, dims: &[u64], element_size: usize) -> Parsed<'a, Vec<u8>> {
+        let layout_message = object.message(MESSAGE_LAYOUT).unwrap_or(&[]);
+        let len = element_count(dims)
+            .and_then(|count| count.checked_mul(element_size))
+            .ok_or_else(|| failure(layout_message, "dataset too large"))?;
+        match self.layout(layout_message)? {
+            Layout::Compact(data) => data.get(..len).map(|data| data.to_vec()).ok_or_else(|| {
+                fail(
+                    data,
+                    ParseErrorKind::LengthMismatch {
+                        expected: len / element_size.max(1),
+                        actual: data.len() / element_size.max(1),
+                    },
+                )
+            }),
+            Layout::Contiguous(Some(address)) => Ok(self.slice(address, len)?.to_vec()),
+            // Data that was never written consists of fill values. Matlab
+            // always writes all data, so there can't be more of it than the
+            // file holds unless the file is corrupt.
+            Layout::Contiguous(None) | Layout::Chunked { btree: None, .. } => {
+                if len > self.data.len() {
+                    return Err(failure(layout_message, "dataset too large"));
+                }
+                Ok(vec![0; len])
+            }
+            Layout::Chunked {
+                btree: Some(btree),
+                chunk_dims,
+            } => {
+                let dims: Vec<usize> = dims.iter().map(|&d| d as usize).collect();
+                if chunk_dims.len() != dims.len() || chunk_dims.contains(&0) {
+                    return Err(failure(layout_message, "invalid chunk dimensions"));
+                }
+                let filters = match object.message(MESSAGE_FILTER_PIPELINE) {
+                    Some(data) => self.filters(data)?,
+                    None => Vec::new(),
+                };
+                let chunk_len = element_count(&chunk_dims)
+                    .and_then(|count| count.checked_mul(element_size))
+                    .ok_or_else(|| failure(layout_message, "chunk too large"))?;
+                let mut chunks = Vec::new();
+                self.chunks(btree, dims.len() + 1, 0, &mut chunks)?;
+                if len > chunks.len().saturating_mul(chunk_len) {
+                    return Err(failure(layout_message, "missing chunks"));
+                }
+                let mut output = Vec::new();
+                for chunk in chunks {
+                    if chunk.offsets.len() != dims.len()
+                        || chunk
+                            .offsets
+                            .iter()
+                            .zip(&dims)
+                            .any(|(offset, d)| offset >= d)
+                    {
+                        return Err(failure(layout_message, "invalid chunk offset"));
+                    }
+                    let raw = self.slice(chunk.address, chunk.size)?;
+                    let data = unfilter(raw, &filters, chunk.filter_mask)?;
+                    if data.len() < chunk_len {
+                        return Err(failure(raw, "chunk too small"));
+                    }
+                    // The size of the dataset can only be trusted once a
+                    // chunk actually had the expected size
+                    if output.is_empty() {
+                        output = vec![0; len];
+                    }
+                    copy_chunk(
+                        &mut output,
+                        &dims,
+                        &chunk_dims,
+                        &chunk.offsets,
+                        element_size,
+                        &data,
+                    );
+                }
+                output.resize(len, 0);
+                Ok(output)
+            }
+        }
+    }
+
+    /// Collects the chunks of a chunked dataset from its version 1 B-tree.
+    fn chunks(
+        &self,
+        address: u64,
+        key_dims: usize,
+        depth: usize,
+        chunks: &mut Vec<Chunk>,
+    ) -> Parsed<'a, ()> {
+        let mut reader = self.reader_at(address)?;
+        reader.tag(b"TREE")?;
+        let node_type = reader.u8()?;
+        let level = reader.u8()?;
+        let entries = reader.u16()? as usize;
+        if node_type != 1 || depth > MAX_DEPTH {
+            return Err(failure(reader.remaining(), "invalid B-tree node"));
+        }
+        // Siblings
+        reader.skip(2 * self.offset_size)?;
+        for _ in 0..entries {
+            let size = reader.u32()? as usize;
+            let filter_mask = reader.u32()?;
+            let offsets = (0..key_dims)
+                .map(|_| reader.usize(8))
+                .collect::<Result<Vec<_>, _>>()?;
+            let child = self
+                .address(&mut reader)?
+                .ok_or_else(|| failure(reader.remaining(), "undefined chunk address"))?;
+            if level == 0 {
+                chunks.push(Chunk {
+                    address: child,
+                    size,
+                    filter_mask,
+                    offsets: offsets[..key_dims - 1].to_vec(),
+                });
+            } else {
+                self.chunks(child, key_dims, depth + 1, chunks)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the names and object header addresses of the members of a
+    /// group.
+    fn links(&self, messages: &[Message<'a>]) -> Parsed<'a, Vec<(String, u64)>> {
+        let mut links = Vec::new();
+        for message in messages {
+            let mut reader = Reader::new(message.data);
+            match message.kind {
+                MESSAGE_SYMBOL_TABLE => {
+                    let btree = self.address(&mut reader)?;
+                    let heap = self.address(&mut reader)?;
+                    let (btree, heap) = match (btree, heap) {
+                        (Some(btree), Some(heap)) => (btree, heap),
+                        _ => return Err(failure(message.data, "undefined symbol table address")),
+                    };
+                    let heap = self.local_heap(heap)?;
+                    self.group_entries(btree, heap, 0, &mut links)?;
+                }
+                MESSAGE_LINK => {
+                    if reader.u8()? != 1 {
+                        return Err(failure(message.data, "invalid link version"));
+                    }
+                    let flags = reader.u8()?;
+                    let link_type = if flags & 0x08 != 0 { reader.u8()? } else { 0 };
+                    if flags & 0x04 != 0 {
+                        reader.skip(8)?;
+                    }
+                    if flags & 0x10 != 0 {
+                        reader.skip(1)?;
+                    }
+                    let name_length = reader.usize(1 << (flags & 0x03))?;
+                    let name = String::from_utf8_lossy(reader.bytes(name_length)?).into_owned();
+                    // Soft and external links are not followed
+                    if link_type == 0 {
+                        if let Some(address) = self.address(&mut reader)? {
+                            links.push((name, address));
+                        }
+                    }
+                }
+                MESSAGE_LINK_INFO => {
+                    reader.skip(1)?;
+                    let flags = reader.u8()?;
+                    if flags & 0x01 != 0 {
+                        reader.skip(8)?;
+                    }
+                    // Links stored in a fractal heap are not supported
+                    if self.address(&mut reader)?.is_some() {
+                        return Err(unsupported(message.data));
+                    }
+                }
+                _ => {}
+            }
+        }
+        Ok(links)
+    }
+
+    /// Returns the data segment of a local heap.
+    fn local_heap(&self, address: u64) -> Parsed<'a, &'a [u8]> {
+        let mut reader = self.reader_at(address)?;
+        reader.tag(b"HEAP")?;
+        reader.skip(4)?;
+        let size = self.length(&mut reader)?;
+        let _free_list = self.length(&mut reader)?;
+        let data = self
+            .address(&mut reader)?
+            .ok_or_else(|| failure(reader.remaining(), "undefined heap address"))?;
+        self.slice(data, size)
+    }
+
+    /// Collects the entries of a group from its version 1 B-tree and symbol
+    /// table nodes.
+    fn group_entries(
+        &self,
+        address: u64,
+        heap: &'a [u8],
+        depth: usize,
+        links: &mut Vec<(String, u64)>,
+    ) -> Parsed<'a, ()> {
+        let mut reader = self.reader_at(address)?;
+        reader.tag(b"TREE")?;
+        let node_type = reader.u8()?;
+        let level = reader.u8()?;
+        let entries = reader.u16()? as usize;
+        if node_type != 0 || depth > MAX_DEPTH {
+            return Err(failure(reader.remaining(), "invalid B-tree node"));
+        }
+        reader.skip(2 * self.offset_size)?;
+        for _ in 0..entries {
+            // Key
+            reader.skip(self.length_size)?;
+            let child = self
+                .address(&mut reader)?
+                .ok_or_else(|| failure(reader.remaining(), "undefined symbol table address"))?;
+            if level > 0 {
+                self.group_entries(child, heap, depth + 1, links)?;
+                continue;
+            }
+            let mut node = self.reader_at(child)?;
+            node.tag(b"SNOD")?;
+            node.skip(2)?;
+            let symbols = node.u16()?;
+            for _ in 0..symbols {
+                let name_offset = self.length(&mut node)?;
+                let object = self.address(&mut node)?;
+                // Cache type, reserved and scratch pad
+                node.skip(4 + 4 + 16)?;
+                let mut name = Reader::new(heap);
+                name.skip(name_offset)?;
+                let name = name.string()?;
+                if let Some(object) = object {
+                    links.push((name, object));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns an object stored in the global heap, which is where variable
+    /// length data lives.
+    fn global_heap_object(&self, address: u64, index: u16) -> Parsed<'a, &'a [u8]> {
+        let mut reader = self.reader_at(address)?;
+        reader.tag(b"GCOL")?;
+        reader.skip(4)?;
+        let size = self.length(&mut reader)?;
+        let end = (reader.position - 8 - self.length_size)
+            .checked_add(size)
+            .filter(|&end| end <= self.data.len())
+            .ok_or_else(|| fail(reader.remaining(), ParseErrorKind::Truncated))?;
+        while reader.position + 8 + self.length_size <= end {
+            let object_index = reader.u16()?;
+            reader.skip(2 + 4)?;
+            let object_size = self.length(&mut reader)?;
+            if object_index == 0 {
+                break;
+            }
+            let data = reader.bytes(object_size)?;
+            if object_index == index {
+                return Ok(data);
+            }
+            // The objects are padded to multiples of 8 bytes
+            reader.skip((8 - object_size % 8) % 8)?;
+        }
+        Err(failure(reader.remaining(), "missing global heap object"))
+    }
+
+    /// Decodes an attribute of variable length strings, like the
+    /// `MATLAB_fields` attribute of structs.
+    fn variable_length_strings(&self, attribute: &Attribute<'a>) -> Parsed<'a, Vec<String>> {
+        let mut reader = Reader::new(attribute.data);
+        let count = element_count(&attribute.dims)
+            .ok_or_else(|| failure(attribute.data, "invalid dimensions"))?;
+        // Each string takes at least 16 bytes, which limits the count of a
+        // valid attribute
+        let mut strings = Vec::with_capacity(count.min(attribute.data.len() / 16));
+        for _ in 0..count {
+            let _length = reader.u32()?;
+            let collection = self.address(&mut reader)?;
+            let index = reader.u32()?;
+            let string = match collection {
+                Some(collection) => self.global_heap_object(collection, index as u16)?,
+                None => &[],
+            };
+            let string = string.split(|&b| b == 0).next().unwrap_or(&[]);
+            strings.push(String::from_utf8_lossy(string).into_owned());
+        }
+        Ok(strings)
+    }
+
+    /// Reads the dimensions, datatype and data of a dataset.
+    fn dataset(&self, object: &Object<'a>) -> Parsed<'a, (Vec<u64>, Datatype, Vec<u8>)> {
+        let datatype = object
+            .message(MESSAGE_DATATYPE)
+            .ok_or_else(|| failure(object.header, "dataset without datatype"))?;
+        let datatype = self.datatype(datatype)?;
+        let dims = match object.message(MESSAGE_DATASPACE) {
+            Some(dataspace) => self.dataspace(dataspace)?,
+            None => None,
+        };
+        let dims = match dims {
+            Some(dims) => dims,
+            None => return Ok((vec![0], datatype, Vec::new())),
+        };
+        let data = self.data(object, &dims, datatype.size())?;
+        Ok((dims, datatype, data))
+    }
+
+    fn references(
+        &self,
+        object: &Object<'a>,
+        datatype: &Datatype,
+        data: &[u8],
+    ) -> Parsed<'a, Vec<u64>> {
+        match *datatype {
+            Datatype::Reference { size } if size == self.offset_size => Ok(data
+                .chunks_exact(size)
+                .map(|reference| {
+                    let mut address = [0; 8];
+                    address[..size].copy_from_slice(reference);
+                    u64::from_le_bytes(address)
+                })
+                .collect()),
+            _ => Err(failure(object.header, "invalid reference datatype")),
+        }
+    }
+
+    /// Converts a dataset or group into the data element a version 5 MAT
+    /// file would contain for the same variable.
+    fn convert(&self, name: String, object: &Object<'a>, depth: usize) -> Parsed<'a, DataElement> {
+        if depth > MAX_DEPTH {
+            return Err(failure(object.header, "nesting too deep"));
+        }
+        let class = object
+            .attribute("MATLAB_class")
+            .and_then(Attribute::text)
+            .unwrap_or_default();
+        // Objects of classdef classes, function handles and the like are
+        // not supported
+        if object.attribute("MATLAB_object_decode").is_some() {
+            return Ok(DataElement::UnsupportedMatrix(vec![1, 1], name, class));
+        }
+        if !object.is_dataset() {
+            return match object.attribute("MATLAB_sparse") {
+                Some(rows) => self.sparse(name, object, &class, rows.integer().unwrap_or(0)),
+                None if class == "struct" => self.structure(name, object, depth),
+                None => Ok(DataElement::UnsupportedMatrix(vec![1, 1], name, class)),
+            };
+        }
+        let (hdf5_dims, datatype, data) = self.dataset(object)?;
+        let is_empty = object
+            .attribute("MATLAB_empty")
+            .and_then(Attribute::integer)
+            .unwrap_or(0)
+            != 0;
+        // Matlab stores arrays in column-major order, which means the
+        // dimensions of the HDF5 dataset are reversed. Empty arrays store
+        // their dimensions as data instead.
+        let dims = if is_empty {
+            match datatype.numeric(&data) {
+                Some((NumericData::UInt64(dims), None)) => matlab_dims(&dims, object.header)?,
+                _ => vec![0, 0],
+            }
+        } else {
+            matlab_dims(&hdf5_dims, object.header)?
+        };
+        let mut flags = ArrayFlags {
+            complex: false,
+            global: object
+                .attribute("MATLAB_global")
+                .and_then(Attribute::integer)
+                .unwrap_or(0)
+                != 0,
+            logical: false,
+            class: ArrayType::Double,
+            nzmax: 0,
+        };
+        if class == "cell" {
+            flags.class = ArrayType::Cell;
+            let mut cells = Vec::new();
+            if !is_empty {
+                for address in self.references(object, &datatype, &data)? {
+                    let cell = self.object(address)?;
+                    cells.push(self.convert(String::new(), &cell, depth + 1)?);
+                }
+            }
+            return Ok(DataElement::CellMatrix(flags, dims, name, cells));
+        }
+        if class == "struct" {
+            // An empty struct array
+            return Ok(DataElement::StructureMatrix(
+                flags,
+                dims,
+                name,
+                Vec::new(),
+                Vec::new(),
+            ));
+        }
+        flags.class = match class_type(&class) {
+            Some(class) => class,
+            None => return Ok(DataElement::UnsupportedMatrix(dims, name, class)),
+        };
+        let (real, imag) = if is_empty {
+            (empty_numeric(flags.class), None)
+        } else {
+            datatype
+                .numeric(&data)
+                .ok_or_else(|| failure(object.header, "unsupported datatype"))?
+        };
+        flags.complex = imag.is_some();
+        match flags.class {
+            ArrayType::Char => {
+                let chars = match real {
+                    NumericData::UInt16(chars) => chars,
+                    NumericData::UInt8(chars) => chars.into_iter().map(u16::from).collect(),
+                    _ => return Err(failure(object.header, "invalid character datatype")),
+                };
+                Ok(DataElement::CharacterMatrix(flags, dims, name, chars))
+            }
+            _ => {
+                if class == "logical" {
+                    flags.logical = true;
+                }
+                Ok(DataElement::NumericMatrix(flags, dims, name, real, imag))
+            }
+        }
+    }
+
+    /// Converts a struct. A single struct is a group holding the fields. The
+    /// fields of a struct array are datasets of references to the values of
+    /// the individual structs instead.
+    fn structure(
+        &self,
+        name: String,
+        object: &Object<'a>,
+        depth: usize,
+    ) -> Parsed<'a, DataElement> {
+        let mut links = self.links(&object.messages)?;
+        // The links are sorted by name, the order of the fields is stored in
+        // an attribute
+        if let Some(fields) = object.attribute("MATLAB_fields") {
+            let field_names = self.variable_length_strings(fields)?;
+            links.sort_by_key(|(name, _)| {
+                field_names
+                    .iter()
+                    .position(|field_name| field_name == name)
+                    .unwrap_or(usize::MAX)
+            });
+        }
+        let mut fields = Vec::with_capacity(links.len());
+        for (name, address) in links {
+            let field = self.object(address)?;
+            fields.push((name, field));
+        }
+        let flags = ArrayFlags {
+            complex: false,
+            global: false,
+            logical: false,
+            class: ArrayType::Struct,
+            nzmax: 0,
+        };
+        let is_struct_array = !fields.is_empty()
+            && fields
+                .iter()
+                .all(|(_, field)| field.is_dataset() && field.attribute("MATLAB_class").is_none());
+        let field_names: Vec<String> = fields.iter().map(|(name, _)| name.clone()).collect();
+        if !is_struct_array {
+            let mut values = Vec::with_capacity(fields.len());
+            for (name, field) in &fields {
+                values.push(self.convert(name.clone(), field, depth + 1)?);
+            }
+            return Ok(DataElement::StructureMatrix(
+                flags,
+                vec![1, 1],
+                name,
+                field_names,
+                values,
+            ));
+        }
+        let mut dims = Vec::new();
+        let mut references = Vec::with_capacity(fields.len());
+        for (_, field) in &fields {
+            let (hdf5_dims, datatype, data) = self.dataset(field)?;
+            let field_dims = matlab_dims(&hdf5_dims, field.header)?;
+            if !dims.is_empty() && dims != field_dims {
+                return Err(failure(field.header, "struct array fields differ in size"));
+            }
+            dims = field_dims;
+            references.push(self.references(field, &datatype, &data)?);
+        }
+        let count = references[0].len();
+        let mut values = Vec::with_capacity(count * fields.len());
+        for index in 0..count {
+            for ((name, _), references) in fields.iter().zip(&references) {
+                let address = *references.get(index).ok_or_else(|| {
+                    fail(
+                        object.header,
+                        ParseErrorKind::LengthMismatch {
+                            expected: count,
+                            actual: references.len(),
+                        },
+                    )
+                })?;
+                let value = self.object(address)?;
+                values.push(self.convert(name.clone(), &value, depth + 1)?);
+            }
+        }
+        Ok(DataElement::StructureMatrix(
+            flags,
+            dims,
+            name,
+            field_names,
+            values,
+        ))
+    }
+
+    /// Converts a sparse matrix, which is a group holding the nonzero values
+    /// and the row and column indices in compressed column form.
+    fn sparse(
+        &self,
+        name: String,
+        object: &Object<'a>,
+        class: &str,
+        rows: u64,
+    ) -> Parsed<'a, DataElement> {
+        let mut data = None;
+        let mut row_index = Vec::new();
+        let mut column_shift = Vec::new();
+        for (member, address) in self.links(&object.messages)? {
+            let member_object = self.object(address)?;
+            let (_, datatype, bytes) = self.dataset(&member_object)?;
+            let numeric = datatype
+                .numeric(&bytes)
+                .ok_or_else(|| failure(member_object.header, "unsupported datatype"))?;
+            let indices = || match &numeric.0 {
+                NumericData::UInt64(indices) => indices
+                    .iter()
+                    .map(|&i| usize::try_from(i))
+                    .collect::<Result<_, _>>()
+                    .map_err(|_| failure(member_object.header, "invalid sparse index")),
+                _ => Err(failure(
+                    member_object.header,
+                    "invalid sparse index datatype",
+                )),
+            };
+            match member.as_str() {
+                "data" => data = Some(numeric.clone()),
+                "ir" => row_index = indices()?,
+                "jc" => column_shift = indices()?,
+                _ => {}
+            }
+        }
+        let columns = column_shift.len().saturating_sub(1);
+        let size = match (i32::try_from(rows), i32::try_from(columns)) {
+            (Ok(rows), Ok(columns)) => vec![rows, columns],
+            _ => return Err(failure(object.header, "dimension too large")),
+        };
+        if !valid_sparse_indices(rows as usize, columns, &row_index, &column_shift) {
+            return Err(failure(object.header, "invalid sparse matrix"));
+        }
+        let (real, imag) = data.unwrap_or((NumericData::Double(Vec::new()), None));
+        let flags = ArrayFlags {
+            complex: imag.is_some(),
+            global: false,
+            logical: class == "logical",
+            class: ArrayType::Sparse,
+            nzmax: real.len(),
+        };
+        Ok(DataElement::SparseMatrix(
+            flags,
+            size,
+            name,
+            row_index,
+            column_shift,
+            real,
+            imag,
+        ))
+    }
+}
+
+struct Chunk {
+    address: u64,
+    size: usize,
+    filter_mask: u32,
+    offsets: Vec<usize>,
+}
+
+/// The number of bytes a datatype message takes up, needed to skip over the
+/// member types of compound datatypes.
+fn datatype_message_size<'a>(datatype: &Datatype, data: &'a [u8]) -> Parsed<'a, usize> {
+    let properties = match datatype {
+        Datatype::Integer { .. } => 4,
+        Datatype::Float { .. } => 12,
+        Datatype::String { .. } | Datatype::Reference { .. } => 0,
+        _ => return Err(unsupported(data)),
+    };
+    Ok(8 + properties)
+}
+
+fn element_count<T: Copy + TryInto<usize>>(dims: &[T]) -> Option<usize> {
+    dims.iter()
+        .try_fold(1usize, |count, &d| count.checked_mul(d.try_into().ok()?))
+}
+
+/// Reverses HDF5 dimensions into Matlab dimensions, which always have at
+/// least two entries. Like in Level 5 files, each dimension is limited to
+/// `i32::MAX`. `header` is the object header, for reporting errors.
+fn matlab_dims<'a>(dims: &[u64], header: &'a [u8]) -> Parsed<'a, Vec<i32>> {
+    let mut dims = dims
+        .iter()
+        .rev()
+        .map(|&d| i32::try_from(d).map_err(|_| failure(header, "dimension too large")))
+        .collect::<Result<Vec<_>, _>>()?;
+    while dims.len() < 2 {
+        dims.push(1);
+    }
+    Ok(dims)
+}
+
+fn class_type(class: &str) -> Option<ArrayType> {
+    Some(match class {
+        "double" => ArrayType::Double,
+        "single" => ArrayType::Single,
+        "int8" => ArrayType::Int8,
+        "uint8" | "logical" => ArrayType::UInt8,
+        "int16" => ArrayType::Int16,
+        "uint16" => ArrayType::UInt16,
+        "int32" => ArrayType::Int32,
+        "uint32" => ArrayType::UInt32,
+        "int64" => ArrayType::Int64,
+        "uint64" => ArrayType::UInt64,
+        "char" => ArrayType::Char,
+        _ => return None,
+    })
+}
+
+fn empty_numeric(class: ArrayType) -> NumericData {
+    match class {
+        ArrayType::Single => NumericData::Single(Vec::new()),
+        ArrayType::Int8 => NumericData::Int8(Vec::new()),
+        ArrayType::UInt8 => NumericData::UInt8(Vec::new()),
+        ArrayType::Int16 => NumericData::Int16(Vec::new()),
+        ArrayType::UInt16 | ArrayType::Char => NumericData::UInt16(Vec::new()),
+        ArrayType::Int32 => NumericData::Int32(Vec::new()),
+        ArrayType::UInt32 => NumericData::UInt32(Vec::new()),
+        ArrayType::Int64 => NumericData::Int64(Vec::new()),
+        ArrayType::UInt64 => NumericData::UInt64(Vec::new()),
+        _ => NumericData::Double(Vec::new()),
+    }
+}
+
+/// Undoes the filters applied to a chunk, in reverse order. Filters whose
+/// bit is set in the filter mask were skipped when writing the chunk.
+fn unfilter<'a>(raw: &'a [u8], filters: &[Filter], filter_mask: u32) -> Parsed<'a, Vec<u8>> {
+    let mut data = raw.to_vec();
+    for (index, filter) in filters.iter().enumerate().rev() {
+        if index < 32 && filter_mask & (1 << index) != 0 {
+            continue;
+        }
+        data = match filter.id {
+            // Deflate
+            1 => {
+                let mut decompressed = Vec::new();
+                Decoder::new(data.as_slice())
+                    .and_then(|mut decoder| decoder.read_to_end(&mut decompressed))
+                    .map_err(|err| fail(raw, ParseErrorKind::Decompression(err.to_string())))?;
+                decompressed
+            }
+            // Shuffle
+            2 => {
+                let element_size = filter.client_data.first().copied().unwrap_or(1) as usize;
+                unshuffle(&data, element_size.max(1))
+            }
+            // Fletcher32 checksum
+            3 => {
+                let len = data
+                    .len()
+                    .checked_sub(4)
+                    .ok_or_else(|| fail(raw, ParseErrorKind::Truncated))?;
+                data.truncate(len);
+                data
+            }
+            _ => return Err(unsupported(raw)),
+        };
+    }
+    Ok(data)
+}
+
+/// The shuffle filter stores the first bytes of all elements, then the
+/// second bytes and so on.
+fn unshuffle(data: &[u8], element_size: usize) -> Vec<u8> {
+    let count = data.len() / element_size;
+    let mut output = data.to_vec();
+    for element in 0..count {
+        for byte in 0..element_size {
+            output[element * element_size + byte] = data[byte * count + element];
+        }
+    }
+    output
+}
+
+/// Copies the elements of a chunk into the (row-major) data of the whole
+/// dataset. Chunks at the edges may extend beyond the dataset.
+fn copy_chunk(
+    output: &mut [u8],
+    dims: &[usize],
+    chunk_dims: &[usize],
+    offsets: &[usize],
+    element_size: usize,
+    chunk: &[u8],
+) {
+    let rank = dims.len();
+    if rank == 0 || offsets.len() != rank {
+        return;
+    }
+    let last = rank - 1;
+    if offsets[last] >= dims[last] {
+        return;
+    }
+    let run = chunk_dims[last].min(dims[last] - offsets[last]);
+    let runs: usize = chunk_dims[..last].iter().product();
+    let mut index = vec![0; last];
+    for run_index in 0..runs {
+        let inside = (0..last).all(|d| offsets[d] + index[d] < dims[d]);
+        if inside {
+            let mut position = 0;
+            for d in 0..last {
+                position = position * dims[d] + offsets[d] + index[d];
+            }
+            position = position * dims[last] + offsets[last];
+            let source = run_index * chunk_dims[last] * element_size;
+            let target = position * element_size;
+            output[target..target + run * element_size]
+                .copy_from_slice(&chunk[source..source + run * element_size]);
+        }
+        // Advance to the next run
+        for d in (0..last).rev() {
+            index[d] += 1;
+            if index[d] < chunk_dims[d] {
+                break;
+            }
+            index[d] = 0;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn global_heap_size() {
+        let mut data = vec![0; 8];
+        data.extend_from_slice(b"GCOL\x01\0\0\0");
+        data.extend_from_slice(&u64::MAX.to_le_bytes());
+        let file = File {
+            data: &data,
+            offset_size: 8,
+            length_size: 8,
+            root: 0,
+        };
+        assert!(matches!(
+            file.global_heap_object(8, 1),
+            Err(nom::Err::Failure(crate::parse::Error {
+                kind: ParseErrorKind::Truncated,
+                ..
+            }))
+        ));
+    }
+
+    #[test]
+    fn dimensions() {
+        assert_eq!(matlab_dims(&[3], &[]), Ok(vec![3, 1]));
+        assert_eq!(matlab_dims(&[2, 3, 4], &[]), Ok(vec![4, 3, 2]));
+        assert_eq!(
+            matlab_dims(&[3_000_000_000, 1], &[]),
+            Err(failure(&[], "dimension too large"))
+        );
+    }
+}
@@ -0,0 +1,135 @@
+use crate::{parse, FileVersion};
+
+/// The header of a .mat file, see `MatFile::header`.
+///
+/// Level 5 and version 7.3 files start with a descriptive text like
+/// "MATLAB 5.0 MAT-file, Platform: GLNXA64, Created on: Mon Jan 15 10:30:00 2024".
+/// Level 4 files have no header, so only the version and the byte order are
+/// known for them.
+///
+/// ```rust
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let file = std::fs::File::open("tests/double.mat")?;
+/// let mat_file = matfile::MatFile::parse(file)?;
+/// let header = mat_file.header();
+/// println!("Written on {:?} at {:?}", header.platform(), header.created());
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct FileHeader {
+    text: String,
+    version: FileVersion,
+    is_little_endian: bool,
+    subsystem_data_offset: Option<u64>,
+}
+
+impl FileHeader {
+    pub(crate) fn new(header: &parse::Header, version: FileVersion) -> Self {
+        FileHeader {
+            text: header.text.trim_end_matches(&[' ', '\0'][..]).to_owned(),
+            version,
+            is_little_endian: header.is_little_endian,
+            subsystem_data_offset: header.subsystem_data_offset,
+        }
+    }
+
+    /// The descriptive text of the header without trailing padding. Empty
+    /// for Level 4 files.
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// The platform the file was written on (e.g. "GLNXA64", "PCWIN64" or
+    /// "MACI64"), if the text names one.
+    pub fn platform(&self) -> Option<&str> {
+        let start = self.text.find("Platform: ")? + "Platform: ".len();
+        let platform = self.text[start..].split(',').next()?.trim();
+        if platform.is_empty() {
+            None
+        } else {
+            Some(platform)
+        }
+    }
+
+    /// The time the file was created as seconds since 1970-01-01 00:00:00,
+    /// if the text has a valid "Created on: " date with a year up to 9999.
+    ///
+    /// Matlab writes the local time without a time zone, so this is the
+    /// displayed date and time as if it was UTC.
+    pub fn created(&self) -> Option<i64> {
+        let start = self.text.find("Created on: ")? + "Created on: ".len();
+        parse_asctime(self.text.get(start..)?)
+    }
+
+    /// Converts the creation time into a `chrono` date time, see
+    /// [`created`](FileHeader::created).
+    #[cfg(feature = "chrono")]
+    pub fn created_chrono(&self) -> Option<chrono::NaiveDateTime> {
+        chrono::DateTime::from_timestamp(self.created()?, 0).map(|t| t.naive_utc())
+    }
+
+    /// The version of the file, as detected by `MatFile::detect_version`.
+    pub fn version(&self) -> FileVersion {
+        self.version
+    }
+
+    /// Whether the numbers in the file are stored in little endian byte order.
+    pub fn is_little_endian(&self) -> bool {
+        self.is_little_endian
+    }
+
+    /// Offset of the subsystem data from the start of the file, if there is
+    /// any. Matlab stores the content of `classdef` objects there.
+    pub fn subsystem_data_offset(&self) -> Option<u64> {
+        self.subsystem_data_offset
+    }
+}
+
+/// Parses a date like "Mon Jan 15 10:30:00 2024" into seconds since the epoch.
+fn parse_asctime(text: &str) -> Option<i64> {
+    const MONTHS: [&str; 12] = [
+        "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+    ];
+    let mut parts = text.split_whitespace();
+    let _weekday = parts.next()?;
+    let month_name = parts.next()?;
+    let month = MONTHS.iter().position(|&m| m == month_name)? as i64 + 1;
+    let day: i64 = parts.next()?.parse().ok()?;
+    let mut time = parts
+        .next()?
+        .split(':')
+        .map(|part| part.parse::<i64>().ok());
+    let (hour, minute, second) = (time.next()??, time.next()??, time.next()??);
+    let year: i64 = parts.next()?.parse().ok()?;
+    if time.next().is_some()
+        || !(0..=9999).contains(&year)
+        || !(1..=days_in_month(year, month)).contains(&day)
+        || !(0..24).contains(&hour)
+        || !(0..60).contains(&minute)
+        || !(0..=60).contains(&second)
+    {
+        return None;
+    }
+    Some(days_from_civil(year, month, day) * 86400 + hour * 3600 + minute * 60 + second)
+}
+
+fn days_in_month(year: i64, month: i64) -> i64 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// The number of days since 1970-01-01 of a date in the proleptic Gregorian
+/// calendar.
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
@@ -6,17 +6,30 @@
 //!
 //! ## Feature Status
 //!
-//! Matfile currently allows you to load numeric arrays from .mat files (all floating point and integer types, including complex numbers). All other types are currently ignored.
+//! Matfile currently allows you to load numeric arrays (all floating point and integer types, including complex numbers), logical arrays, character arrays, cell arrays, structure arrays, legacy object arrays, sparse arrays, string arrays, datetime and duration arrays, tables, categorical arrays, `containers.Map` objects and function handles from .mat files. All other types are currently ignored.
 //!
 //! * [ ] Loading .mat files
 //!   * [x] Numeric arrays
-//!   * [ ] Cell arrays
-//!   * [ ] Structure arrays
-//!   * [ ] Object arrays
-//!   * [ ] Character arrays
-//!   * [ ] Sparse arrays
+//!   * [x] Logical arrays
+//!   * [x] Cell arrays
+//!   * [x] Structure arrays
+//!   * [x] Object arrays (legacy classes only)
+//!   * [x] Character arrays
+//!   * [x] Sparse arrays
+//!   * [x] String arrays (experimental)
+//!   * [x] Datetime and duration arrays (experimental)
+//!   * [x] Tables and timetables (experimental)
+//!   * [x] Categorical arrays (experimental)
+//!   * [x] `containers.Map` objects (experimental)
+//!   * [x] Function handles (experimental)
+//!   * [x] Level 4 files (numeric, character and sparse matrices)
+//!   * [x] Version 7.3 (HDF5 based) files (experimental; numeric, logical, character, cell, structure and sparse arrays)
 //! * [ ] Writing .mat files
 //!
+//! Items marked as experimental have only been tested with hand-assembled files
+//! rather than files saved by Matlab. The storage format of `classdef` objects,
+//! which most of them are, is not documented. See `tests/README.md` for details.
+//!
 //! ## Examples
 //!
 //! Loading a .mat file from disk and accessing one of its arrays by name:
@@ -39,17 +52,19 @@
 //!             2,
 //!             3
 //!         ],
-//!         data: Double {
-//!             real: [
-//!                 -5.0,
-//!                 8.0,
-//!                 6.0,
-//!                 9.0,
-//!                 7.0,
-//!                 10.0
-//!             ],
-//!             imag: None
-//!         }
+//!         data: Numeric(
+//!             Double {
+//!                 real: [
+//!                     -5.0,
+//!                     8.0,
+//!                     6.0,
+//!                     9.0,
+//!                     7.0,
+//!                     10.0
+//!                 ],
+//!                 imag: None
+//!             }
+//!         )
 //!     }
 //! )
 //! ```
@@ -58,15 +73,64 @@
 //! The following crate feature flags can be enabled in your Cargo.toml:
 //! * `ndarray`
 //!   * Enable conversions between Matfile and `ndarray` array types
+//! * `chrono`
+//!   * Enable conversions of `datetime` and `duration` arrays into `chrono` types
+//! * `log`
+//!   * Log warnings about skipped variables and other recoverable problems with the `log` crate
+//! * `regex`
+//!   * Select the variables to load with regular expressions, see `ParseOptions::variables_regex`
+//! * `mmap`
+//!   * Map files into memory with `MatFileView::map`
+//! * `rayon`
+//!   * Decompress and decode the variables of a file in parallel in `MatFile::parse`
+//! * `tokio`
+//!   * Read and write .mat files asynchronously, see `MatFile::parse_async` and `write::AsyncMatFileWriter`
 
 #[macro_use]
 extern crate enum_primitive_derive;
 
+mod categorical;
+mod cell;
+mod character;
+mod function;
+mod hdf5;
+mod header;
+mod map;
+mod mcos;
 #[cfg(feature = "ndarray")]
 pub mod ndarray;
+mod object;
+mod options;
 pub mod parse;
+mod reader;
+mod sparse;
+mod stream;
+mod string;
+mod structure;
+mod table;
+mod time;
+mod view;
+mod warning;
 pub mod write;
 
+pub use categorical::CategoricalArray;
+pub use cell::CellArray;
+pub use character::CharArray;
+pub use function::{FunctionHandle, FunctionHandleKind};
+pub use header::FileHeader;
+pub use map::{ContainersMap, MapKey};
+pub use object::ObjectArray;
+pub use options::ParseOptions;
+pub use reader::{MatFileReader, VariableInfo};
+pub use sparse::{SparseArray, SparseData};
+pub use stream::MatFileStream;
+pub use string::StringArray;
+pub use structure::{Struct, StructArray};
+pub use table::Table;
+pub use time::{DatetimeArray, DurationArray};
+pub use view::{MatFileView, NumericDataView};
+pub use warning::{SkipReason, SkippedVariable, Warning};
+
 /// MatFile is a collection of named arrays.
 ///
 /// You can load a ".mat" file from disk like this:
@@ -79,10 +143,12 @@ pub mod write;
 /// ```
 #[derive(Clone, Debug)]
 pub struct MatFile {
+    header: FileHeader,
     arrays: Vec<Array>,
+    skipped: Vec<SkippedVariable>,
 }
 
-/// A numeric array (the only type supported at the moment).
+/// A named array, such as a variable stored in a .mat file.
 ///
 /// You can access the arrays of a MatFile either by name or by iterating
 /// through all of them:
@@ -104,7 +170,40 @@ pub struct MatFile {
 pub struct Array {
     name: String,
     size: Vec<usize>,
-    data: NumericData,
+    data: ArrayData,
+}
+
+/// The contents of an array, depending on its Matlab class.
+#[derive(Clone, Debug)]
+pub enum ArrayData {
+    /// A numeric (`double`, `single` or integer) array
+    Numeric(NumericData),
+    /// A `logical` array, stored in column-major order
+    Logical(Vec<bool>),
+    /// A `char` array
+    Char(CharArray),
+    /// A `cell` array
+    Cell(CellArray),
+    /// A `struct` array
+    Struct(StructArray),
+    /// An array of objects of a legacy Matlab class
+    Object(ObjectArray),
+    /// A sparse matrix
+    Sparse(SparseArray),
+    /// A `string` array
+    String(StringArray),
+    /// A `datetime` array
+    Datetime(DatetimeArray),
+    /// A `duration` array
+    Duration(DurationArray),
+    /// A `table` or `timetable`
+    Table(Table),
+    /// A `categorical` array
+    Categorical(CategoricalArray),
+    /// A `containers.Map` object
+    Map(ContainersMap),
+    /// A function handle
+    FunctionHandle(FunctionHandle),
 }
 
 /// Stores the data of a numerical array and abstracts over the actual data
@@ -279,6 +378,22 @@ fn try_convert_number_format(
     }
 }
 
+/// Logical values are stored as numbers, usually as 8 bit unsigned integers
+fn logical_from(data: parse::NumericData) -> Vec<bool> {
+    match data {
+        parse::NumericData::Int8(data) => data.into_iter().map(|x| x != 0).collect(),
+        parse::NumericData::UInt8(data) => data.into_iter().map(|x| x != 0).collect(),
+        parse::NumericData::Int16(data) => data.into_iter().map(|x| x != 0).collect(),
+        parse::NumericData::UInt16(data) => data.into_iter().map(|x| x != 0).collect(),
+        parse::NumericData::Int32(data) => data.into_iter().map(|x| x != 0).collect(),
+        parse::NumericData::UInt32(data) => data.into_iter().map(|x| x != 0).collect(),
+        parse::NumericData::Int64(data) => data.into_iter().map(|x| x != 0).collect(),
+        parse::NumericData::UInt64(data) => data.into_iter().map(|x| x != 0).collect(),
+        parse::NumericData::Single(data) => data.into_iter().map(|x| x != 0.0).collect(),
+        parse::NumericData::Double(data) => data.into_iter().map(|x| x != 0.0).collect(),
+    }
+}
+
 impl NumericData {
     fn try_from(
         target_type: parse::ArrayType,
@@ -292,107 +407,115 @@ impl NumericData {
         };
         // The next step should never fail unless there is a bug in the code
         match (real, imag) {
-            (parse::NumericData::Double(real), None) => Ok(NumericData::Double {
-                real: real,
-                imag: None,
-            }),
+            (parse::NumericData::Double(real), None) => {
+                Ok(NumericData::Double { real, imag: None })
+            }
             (parse::NumericData::Double(real), Some(parse::NumericData::Double(imag))) => {
                 Ok(NumericData::Double {
-                    real: real,
+                    real,
                     imag: Some(imag),
                 })
             }
-            (parse::NumericData::Single(real), None) => Ok(NumericData::Single {
-                real: real,
-                imag: None,
-            }),
+            (parse::NumericData::Single(real), None) => {
+                Ok(NumericData::Single { real, imag: None })
+            }
             (parse::NumericData::Single(real), Some(parse::NumericData::Single(imag))) => {
                 Ok(NumericData::Single {
-                    real: real,
+                    real,
                     imag: Some(imag),
                 })
             }
-            (parse::NumericData::UInt64(real), None) => Ok(NumericData::UInt64 {
-                real: real,
-                imag: None,
-            }),
+            (parse::NumericData::UInt64(real), None) => {
+                Ok(NumericData::UInt64 { real, imag: None })
+            }
             (parse::NumericData::UInt64(real), Some(parse::NumericData::UInt64(imag))) => {
                 Ok(NumericData::UInt64 {
-                    real: real,
+                    real,
                     imag: Some(imag),
                 })
             }
-            (parse::NumericData::Int64(real), None) => Ok(NumericData::Int64 {
-                real: real,
-                imag: None,
-            }),
+            (parse::NumericData::Int64(real), None) => Ok(NumericData::Int64 { real, imag: None }),
             (parse::NumericData::Int64(real), Some(parse::NumericData::Int64(imag))) => {
                 Ok(NumericData::Int64 {
-                    real: real,
+                    real,
                     imag: Some(imag),
                 })
             }
-            (parse::NumericData::UInt32(real), None) => Ok(NumericData::UInt32 {
-                real: real,
-                imag: None,
-            }),
+            (parse::NumericData::UInt32(real), None) => {
+                Ok(NumericData::UInt32 { real, imag: None })
+            }
             (parse::NumericData::UInt32(real), Some(parse::NumericData::UInt32(imag))) => {
                 Ok(NumericData::UInt32 {
-                    real: real,
+                    real,
                     imag: Some(imag),
                 })
             }
-            (parse::NumericData::Int32(real), None) => Ok(NumericData::Int32 {
-                real: real,
-                imag: None,
-            }),
+            (parse::NumericData::Int32(real), None) => Ok(NumericData::Int32 { real, imag: None }),
             (parse::NumericData::Int32(real), Some(parse::NumericData::Int32(imag))) => {
                 Ok(NumericData::Int32 {
-                    real: real,
+                    real,
                     imag: Some(imag),
                 })
             }
-            (parse::NumericData::UInt16(real), None) => Ok(NumericData::UInt16 {
-                real: real,
-                imag: None,
-            }),
+            (parse::NumericData::UInt16(real), None) => {
+                Ok(NumericData::UInt16 { real, imag: None })
+            }
             (parse::NumericData::UInt16(real), Some(parse::NumericData::UInt16(imag))) => {
                 Ok(NumericData::UInt16 {
-                    real: real,
+                    real,
                     imag: Some(imag),
                 })
             }
-            (parse::NumericData::Int16(real), None) => Ok(NumericData::Int16 {
-                real: real,
-                imag: None,
-            }),
+            (parse::NumericData::Int16(real), None) => Ok(NumericData::Int16 { real, imag: None }),
             (parse::NumericData::Int16(real), Some(parse::NumericData::Int16(imag))) => {
                 Ok(NumericData::Int16 {
-                    real: real,
+                    real,
                     imag: Some(imag),
                 })
             }
-            (parse::NumericData::UInt8(real), None) => Ok(NumericData::UInt8 {
-                real: real,
-                imag: None,
-            }),
+            (parse::NumericData::UInt8(real), None) => Ok(NumericData::UInt8 { real, imag: None }),
             (parse::NumericData::UInt8(real), Some(parse::NumericData::UInt8(imag))) => {
                 Ok(NumericData::UInt8 {
-                    real: real,
+                    real,
                     imag: Some(imag),
                 })
             }
-            (parse::NumericData::Int8(real), None) => Ok(NumericData::Int8 {
-                real: real,
-                imag: None,
-            }),
+            (parse::NumericData::Int8(real), None) => Ok(NumericData::Int8 { real, imag: None }),
             (parse::NumericData::Int8(real), Some(parse::NumericData::Int8(imag))) => {
                 Ok(NumericData::Int8 {
-                    real: real,
+                    real,
                     imag: Some(imag),
                 })
             }
-            _ => return Err(Error::InternalError),
+            _ => Err(Error::InternalError),
+        }
+    }
+}
+
+/// The version of a .mat file, see `MatFile::detect_version`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FileVersion {
+    /// Level 4 file, the format used up to Matlab 4
+    V4,
+    /// Level 5 file without compression, as written by Matlab 5 and 6 or with
+    /// the `-v6` option
+    V6,
+    /// Level 5 file with compressed variables, the default since Matlab 7
+    V7,
+    /// HDF5 based file, as written with the `-v7.3` option
+    V73,
+    /// Not a .mat file at all
+    NotMatFile,
+}
+
+impl std::fmt::Display for FileVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            FileVersion::V4 => write!(f, "version 4 .mat file"),
+            FileVersion::V6 => write!(f, "version 6 .mat file"),
+            FileVersion::V7 => write!(f, "version 7 .mat file"),
+            FileVersion::V73 => write!(f, "version 7.3 .mat file"),
+            FileVersion::NotMatFile => write!(f, "file that is not a .mat file"),
         }
     }
 }
@@ -400,20 +523,61 @@ impl NumericData {
 #[derive(Debug)]
 pub enum Error {
     IOError(std::io::Error),
-    ParseError(nom::Err<nom::error::Error<&'static [u8]>>),
+    ParseError(ParseError),
     ConversionError,
     InternalError,
+    /// The file is not a .mat file or uses a format or feature that is not
+    /// supported, such as Level 4 files written on VAX machines
+    UnsupportedFormat(FileVersion),
+    /// A variable can't be loaded and the file was parsed in strict mode, see
+    /// [`ParseOptions::strict`]
+    UnsupportedVariable(SkippedVariable),
 }
 
 impl std::fmt::Display for Error {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         match self {
             Error::IOError(_) => write!(f, "An I/O error occurred"),
-            Error::ParseError(_) => write!(f, "An error occurred while parsing the file"),
+            Error::ParseError(err) => {
+                write!(f, "An error occurred while parsing the file: {}", err)
+            }
             Error::ConversionError => {
                 write!(f, "An error occurred while converting number formats")
             }
             Error::InternalError => write!(f, "An internal error occurred, this is a bug"),
+            Error::UnsupportedFormat(version) => {
+                write!(f, "Reading this {} is not supported", version)
+            }
+            Error::UnsupportedVariable(skipped) => {
+                write!(f, "Can't load {}", skipped)
+            }
+        }
+    }
+}
+
+impl Error {
+    /// Converts an error of the parsers. `input` is the parsed data, which
+    /// starts at `base_offset` in the file.
+    fn from_parse_error(
+        err: nom::Err<parse::Error<&[u8]>>,
+        input: &[u8],
+        base_offset: usize,
+        version: FileVersion,
+    ) -> Self {
+        match err {
+            nom::Err::Error(err) | nom::Err::Failure(err) if err.unsupported => {
+                Error::UnsupportedFormat(version)
+            }
+            nom::Err::Error(err) | nom::Err::Failure(err) => Error::ParseError(ParseError {
+                offset: base_offset + parse::offset(input, err.input),
+                variable: err.variable,
+                kind: err.kind,
+            }),
+            nom::Err::Incomplete(_) => Error::ParseError(ParseError {
+                offset: base_offset + input.len(),
+                variable: None,
+                kind: ParseErrorKind::Truncated,
+            }),
         }
     }
 }
@@ -422,11 +586,95 @@ impl std::error::Error for Error {
     fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
         match self {
             Error::IOError(ref err) => Some(err),
+            Error::ParseError(ref err) => Some(err),
             _ => None,
         }
     }
 }
 
+/// Describes where and why a file could not be parsed.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParseError {
+    offset: usize,
+    variable: Option<String>,
+    kind: ParseErrorKind,
+}
+
+impl ParseError {
+    /// The byte offset from the start of the file at which the error was
+    /// detected.
+    ///
+    /// Errors inside of compressed variables are reported at the offset of the
+    /// compressed data, since there is no position in the file that
+    /// corresponds to the decompressed bytes.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// The name of the variable being parsed, if it is known.
+    pub fn variable(&self) -> Option<&str> {
+        self.variable.as_deref()
+    }
+
+    /// The reason of the error.
+    pub fn kind(&self) -> &ParseErrorKind {
+        &self.kind
+    }
+}
+
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{} at byte offset {}", self.kind, self.offset)?;
+        if let Some(variable) = &self.variable {
+            write!(f, " in variable \"{}\"", variable)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// The reason why a file could not be parsed.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ParseErrorKind {
+    /// The file header is invalid
+    InvalidHeader,
+    /// A data element has a data type that is unknown or not allowed at its
+    /// position
+    BadTagType(u32),
+    /// The data ends in the middle of a data element
+    Truncated,
+    /// The number of elements does not match the dimensions of the array
+    LengthMismatch { expected: usize, actual: usize },
+    /// Compressed data could not be decompressed
+    Decompression(String),
+    /// An array has an unknown class. Variables of unknown classes are
+    /// skipped like other unsupported variables, see [`MatFile::skipped`]
+    UnsupportedClass(u8),
+    /// Any other inconsistency in the data
+    Invalid(&'static str),
+}
+
+impl std::fmt::Display for ParseErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            ParseErrorKind::InvalidHeader => write!(f, "invalid header"),
+            ParseErrorKind::BadTagType(data_type) => {
+                write!(f, "unexpected data type {}", data_type)
+            }
+            ParseErrorKind::Truncated => write!(f, "unexpected end of data"),
+            ParseErrorKind::LengthMismatch { expected, actual } => {
+                write!(f, "expected {} elements, but found {}", expected, actual)
+            }
+            ParseErrorKind::Decompression(message) => {
+                write!(f, "decompression failed ({})", message)
+            }
+            ParseErrorKind::UnsupportedClass(class) => write!(f, "unknown array class {}", class),
+            ParseErrorKind::Invalid(message) => write!(f, "invalid data ({})", message),
+        }
+    }
+}
+
 impl Array {
     /// The name of this array.
     pub fn name(&self) -> &str {
@@ -448,80 +696,358 @@ impl Array {
         self.size.len()
     }
 
-    /// The actual numerical data stored in this array.
+    /// The actual data stored in this array.
     ///
     /// ```rust
     /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
     /// # let file = std::fs::File::open("tests/double.mat")?;
     /// # let mat_file = matfile::MatFile::parse(file)?;
     /// # let array = &mat_file.arrays()[0];
-    /// if let matfile::NumericData::Double { real: real, imag: _ } = array.data() {
+    /// if let matfile::ArrayData::Numeric(matfile::NumericData::Double { real: real, imag: _ }) = array.data() {
     ///     println!("Real part of the data: {:?}", real);
     /// }
     /// # Ok(())
     /// # }
     /// ```
     ///
-    /// For a more convenient access to the data, consider using the
-    /// `matfile-ndarray` crate.
-    pub fn data(&self) -> &NumericData {
+    /// For a more convenient access to numeric data, consider enabling the
+    /// `ndarray` feature.
+    pub fn data(&self) -> &ArrayData {
         &self.data
     }
+
+    /// The numerical data stored in this array, if it is a numeric array.
+    pub fn numeric_data(&self) -> Option<&NumericData> {
+        match self.data {
+            ArrayData::Numeric(ref data) => Some(data),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn with_name(mut self, name: &str) -> Self {
+        self.name = name.to_owned();
+        self
+    }
+
+    /// Converts a parsed data element into an array. Returns `None` for data
+    /// elements of unsupported type.
+    fn from_data_element(
+        data_element: parse::DataElement,
+        context: mcos::Context,
+    ) -> Result<Option<Self>, Error> {
+        match data_element {
+            parse::DataElement::NumericMatrix(flags, dims, name, real, imag) => {
+                if let Some(object) = context.resolve_embedded(&flags, &real) {
+                    return Ok(object?.map(|(size, data)| Array { size, name, data }));
+                }
+                let size = dims.into_iter().map(|d| d as usize).collect();
+                let data = if flags.logical {
+                    ArrayData::Logical(logical_from(real))
+                } else {
+                    ArrayData::Numeric(NumericData::try_from(flags.class, real, imag)?)
+                };
+                Ok(Some(Array { size, name, data }))
+            }
+            parse::DataElement::CharacterMatrix(_flags, dims, name, data) => {
+                let size: Vec<usize> = dims.into_iter().map(|d| d as usize).collect();
+                Ok(Some(Array {
+                    size: size.clone(),
+                    name,
+                    data: ArrayData::Char(CharArray::new(size, data)),
+                }))
+            }
+            parse::DataElement::CellMatrix(_flags, dims, name, cells) => {
+                let size: Vec<usize> = dims.into_iter().map(|d| d as usize).collect();
+                // A cell array is only supported if all of its cells are
+                let cells: Option<Vec<Array>> = cells
+                    .into_iter()
+                    .map(|cell| Array::from_data_element(cell, context))
+                    .collect::<Result<_, _>>()?;
+                Ok(cells.map(|cells| Array {
+                    size: size.clone(),
+                    name,
+                    data: ArrayData::Cell(CellArray::new(size, cells)),
+                }))
+            }
+            parse::DataElement::StructureMatrix(_flags, dims, name, field_names, values) => {
+                let size: Vec<usize> = dims.into_iter().map(|d| d as usize).collect();
+                let structs =
+                    StructArray::from_data_elements(size.clone(), field_names, values, context)?;
+                Ok(structs.map(|structs| Array {
+                    size,
+                    name,
+                    data: ArrayData::Struct(structs),
+                }))
+            }
+            parse::DataElement::ObjectMatrix(
+                _flags,
+                dims,
+                name,
+                class_name,
+                field_names,
+                values,
+            ) => {
+                let size: Vec<usize> = dims.into_iter().map(|d| d as usize).collect();
+                let fields =
+                    StructArray::from_data_elements(size.clone(), field_names, values, context)?;
+                Ok(fields.map(|fields| Array {
+                    size,
+                    name,
+                    data: ArrayData::Object(ObjectArray::new(class_name, fields)),
+                }))
+            }
+            parse::DataElement::SparseMatrix(
+                flags,
+                dims,
+                name,
+                row_index,
+                column_shift,
+                real,
+                imag,
+            ) => {
+                let size: Vec<usize> = dims.into_iter().map(|d| d as usize).collect();
+                // Matlab only supports sparse matrices of type double or logical
+                let data = if flags.logical {
+                    SparseData::Logical(logical_from(real))
+                } else {
+                    SparseData::Numeric(NumericData::try_from(
+                        parse::ArrayType::Double,
+                        real,
+                        imag,
+                    )?)
+                };
+                Ok(Some(Array {
+                    size: size.clone(),
+                    name,
+                    data: ArrayData::Sparse(SparseArray::new(
+                        size,
+                        flags.nzmax,
+                        row_index,
+                        column_shift,
+                        data,
+                    )),
+                }))
+            }
+            parse::DataElement::FunctionMatrix(_flags, dims, name, data) => {
+                let size = dims.into_iter().map(|d| d as usize).collect();
+                let handle = FunctionHandle::from_data_element(*data, context)?;
+                Ok(handle.map(|handle| Array {
+                    size,
+                    name,
+                    data: ArrayData::FunctionHandle(handle),
+                }))
+            }
+            parse::DataElement::OpaqueMatrix(_flags, name, type_system, _class_name, data) => {
+                let object = context.resolve_opaque(&type_system, &data)?;
+                Ok(object.map(|(size, data)| Array { size, name, data }))
+            }
+            _ => Ok(None),
+        }
+    }
 }
 
 impl MatFile {
     /// Tries to parse a byte sequence as a ".mat" file.
-    pub fn parse<R: std::io::Read>(mut reader: R) -> Result<Self, Error> {
+    ///
+    /// Level 5 files (versions 6 and 7), the HDF5 based files of version 7.3
+    /// and the older Level 4 files are supported, the format is detected
+    /// automatically. Level 4 files written on VAX or Cray machines can't be
+    /// read. Of version 7.3 files only numeric, logical, character, cell,
+    /// structure and sparse arrays are loaded.
+    ///
+    /// Variables that can't be loaded are left out and listed by
+    /// [`skipped`](MatFile::skipped). Use [`parse_with`](MatFile::parse_with)
+    /// to be notified about them or to fail instead.
+    pub fn parse<R: std::io::Read>(reader: R) -> Result<Self, Error> {
+        Self::parse_with(reader, &ParseOptions::default())
+    }
+
+    /// Tries to parse a byte sequence as a ".mat" file, using the given
+    /// options.
+    pub fn parse_with<R: std::io::Read>(
+        mut reader: R,
+        options: &ParseOptions,
+    ) -> Result<Self, Error> {
         let mut buf = Vec::new();
-        reader
-            .read_to_end(&mut buf)
-            .map_err(|err| Error::IOError(err))?;
-        let (_remaining, parse_result) = parse::parse_all(&buf)
-            .map_err(|err| Error::ParseError(parse::replace_err_slice(err, &[])))?;
-        let arrays: Result<Vec<Array>, Error> = parse_result
-            .data_elements
-            .into_iter()
-            .filter_map(|data_element| match data_element {
-                parse::DataElement::NumericMatrix(flags, dims, name, real, imag) => {
-                    let size = dims.into_iter().map(|d| d as usize).collect();
-                    let numeric_data = match NumericData::try_from(flags.class, real, imag) {
-                        Ok(numeric_data) => numeric_data,
-                        Err(err) => return Some(Err(err)),
-                    };
-                    Some(Ok(Array {
-                        size: size,
-                        name: name,
-                        data: numeric_data,
-                    }))
+        reader.read_to_end(&mut buf).map_err(Error::IOError)?;
+        let version = parse::detect_version(&buf);
+        if version == FileVersion::NotMatFile {
+            return Err(Error::UnsupportedFormat(version));
+        }
+        let (_remaining, parse_result) = parse::parse_selected(&buf, &|name| options.selects(name))
+            .map_err(|err| Error::from_parse_error(err, &buf, 0, version))?;
+        let header = &parse_result.header;
+        let file_header = FileHeader::new(header, version);
+        if let (0x0100, Some(offset), None) = (
+            header.version,
+            header.subsystem_data_offset,
+            &parse_result.subsystem_data,
+        ) {
+            options.warn(Warning::IgnoredSubsystemData { offset });
+        }
+        let mut subsystem = mcos::Subsystem::new(
+            parse_result.subsystem_data,
+            parse_result.header.is_little_endian,
+        );
+        subsystem.consume(&parse_result.data_elements);
+        let context = mcos::Context::new(&subsystem);
+        let mut arrays = Vec::new();
+        let mut skipped = Vec::new();
+        for data_element in parse_result.data_elements {
+            if let parse::DataElement::Unsupported(data_type) = data_element {
+                options.warn(Warning::IgnoredDataElement {
+                    data_type: data_type as u32,
+                });
+                continue;
+            }
+            let skipped_variable = SkippedVariable::from_data_element(&data_element);
+            match Array::from_data_element(data_element, context)? {
+                Some(array) => arrays.push(array),
+                None if options.is_strict() => {
+                    return Err(Error::UnsupportedVariable(skipped_variable))
                 }
-                _ => None,
-            })
-            .collect();
-        let arrays = arrays?;
-        Ok(MatFile { arrays: arrays })
+                None => {
+                    options.warn(Warning::SkippedVariable(skipped_variable.clone()));
+                    skipped.push(skipped_variable);
+                }
+            }
+        }
+        Ok(MatFile {
+            header: file_header,
+            arrays,
+            skipped,
+        })
+    }
+
+    /// Reads a ".mat" file from an asynchronous reader and parses it like
+    /// [`parse`](MatFile::parse). The file is read asynchronously, parsing
+    /// and decompressing it runs on the blocking thread pool of the `tokio`
+    /// runtime, so it must be called within one.
+    ///
+    /// ```rust
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let runtime = tokio::runtime::Builder::new_current_thread().build()?;
+    /// # runtime.block_on(async {
+    /// let data = std::fs::read("tests/double.mat")?;
+    /// let mat_file = matfile::MatFile::parse_async(data.as_slice()).await?;
+    /// # Ok(())
+    /// # })
+    /// # }
+    /// ```
+    #[cfg(feature = "tokio")]
+    pub async fn parse_async<R: tokio::io::AsyncRead + Unpin>(reader: R) -> Result<Self, Error> {
+        Self::parse_async_with(reader, &ParseOptions::default()).await
+    }
+
+    /// Reads a ".mat" file from an asynchronous reader and parses it using
+    /// the given options. See [`parse_async`](MatFile::parse_async).
+    #[cfg(feature = "tokio")]
+    pub async fn parse_async_with<R: tokio::io::AsyncRead + Unpin>(
+        mut reader: R,
+        options: &ParseOptions,
+    ) -> Result<Self, Error> {
+        use tokio::io::AsyncReadExt;
+        let mut buf = Vec::new();
+        reader.read_to_end(&mut buf).await.map_err(Error::IOError)?;
+        let options = options.clone();
+        let task = tokio::task::spawn_blocking(move || Self::parse_with(buf.as_slice(), &options));
+        match task.await {
+            Ok(result) => result,
+            Err(err) if err.is_panic() => std::panic::resume_unwind(err.into_panic()),
+            Err(err) => Err(Error::IOError(std::io::Error::other(err))),
+        }
+    }
+
+    /// Reads the variables of a ".mat" file one at a time, while reading
+    /// the file. Unlike [`parse`](MatFile::parse) this doesn't need the
+    /// whole file in memory, only the variable being decoded, so it also
+    /// works for files that are piped in or received over the network.
+    ///
+    /// Only Level 5 files (versions 6 and 7) can be streamed. Variables
+    /// holding objects of `classdef` classes (like strings or tables) need
+    /// the subsystem data at the end of the file, so they are kept until the
+    /// end of the file and returned last.
+    ///
+    /// ```rust
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let file = std::fs::File::open("tests/two_arrays.mat")?;
+    /// for array in matfile::MatFile::stream(file)? {
+    ///     let array = array?;
+    ///     println!("{}: {:?}", array.name(), array.size());
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn stream<R: std::io::Read>(reader: R) -> Result<MatFileStream<R>, Error> {
+        Self::stream_with(reader, &ParseOptions::default())
+    }
+
+    /// Reads the variables of a ".mat" file one at a time, using the given
+    /// options. See [`stream`](MatFile::stream).
+    pub fn stream_with<R: std::io::Read>(
+        reader: R,
+        options: &ParseOptions,
+    ) -> Result<MatFileStream<R>, Error> {
+        MatFileStream::new(reader, options)
+    }
+
+    /// Detects the version of a .mat file from its first bytes (at least the
+    /// first 136 bytes are needed to tell version 6 and 7 files apart).
+    ///
+    /// Files of version 7 can contain uncompressed variables, so a version 7
+    /// file whose first variable isn't compressed is detected as version 6.
+    ///
+    /// ```rust
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let data = std::fs::read("tests/double.mat")?;
+    /// let version = matfile::MatFile::detect_version(&data);
+    /// assert_ne!(version, matfile::FileVersion::NotMatFile);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn detect_version(data: &[u8]) -> FileVersion {
+        parse::detect_version(data)
+    }
+
+    /// The header of this .mat file, with the platform and the time it was
+    /// written on.
+    pub fn header(&self) -> &FileHeader {
+        &self.header
     }
 
     /// List of all arrays in this .mat file.
     ///
-    /// When parsing a .mat file all arrays of unsupported type (currently all
-    /// non-numerical and sparse arrays) will be ignored and will thus not be
-    /// part of this list.
+    /// When parsing a .mat file all arrays of unsupported type (currently
+    /// objects of `classdef` classes not listed in the crate documentation)
+    /// will be ignored and will thus not be part of this list. They are listed
+    /// by [`skipped`](MatFile::skipped) instead.
     pub fn arrays(&self) -> &Vec<Array> {
         &self.arrays
     }
 
     /// Returns an array with the given name if it exists. Case sensitive.
     ///
-    /// When parsing a .mat file all arrays of unsupported type (currently all
-    /// non-numerical and sparse arrays) will be ignored and will thus not be
-    /// returned by this function.
+    /// When parsing a .mat file all arrays of unsupported type (currently
+    /// objects of `classdef` classes not listed in the crate documentation)
+    /// will be ignored and will thus not be returned by this function.
     pub fn find_by_name<'me>(&'me self, name: &'_ str) -> Option<&'me Array> {
-        for array in &self.arrays {
-            if array.name == name {
-                return Some(array);
-            }
-        }
-        None
+        self.arrays.iter().find(|array| array.name == name)
+    }
+
+    /// List of the variables that were left out because they can't be loaded.
+    ///
+    /// ```rust
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let file = std::fs::File::open("tests/v73.mat")?;
+    /// let mat_file = matfile::MatFile::parse(file)?;
+    /// for skipped in mat_file.skipped() {
+    ///     println!("Skipped {} of class {}", skipped.name(), skipped.class());
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn skipped(&self) -> &[SkippedVariable] {
+        &self.skipped
     }
 }
 
@@ -574,4 +1100,1335 @@ mod tests {
         let data = include_bytes!("../tests/long_name.mat");
         let _mat_file = MatFile::parse(data.as_ref()).unwrap();
     }
+
+    #[test]
+    fn character_array() {
+        let data = include_bytes!("../tests/character.mat");
+        let mat_file = MatFile::parse(data.as_ref()).unwrap();
+        let array = mat_file.find_by_name("chr").unwrap();
+        assert_eq!(array.size(), &vec![1, 13]);
+        match array.data() {
+            ArrayData::Char(chars) => assert_eq!(chars.to_string(), "Hello, world!"),
+            _ => panic!("Expected a character array"),
+        }
+    }
+
+    #[test]
+    fn character_matrix() {
+        let data = include_bytes!("../tests/char_matrix.mat");
+        let mat_file = MatFile::parse(data.as_ref()).unwrap();
+        let labels = mat_file.find_by_name("labels").unwrap();
+        match labels.data() {
+            ArrayData::Char(chars) => {
+                assert_eq!(chars.size(), &vec![3, 4]);
+                assert_eq!(chars.rows(), vec!["ch1 ", "ch2 ", "ch10"]);
+                assert_eq!(chars.to_string(), "ch1 \nch2 \nch10");
+            }
+            _ => panic!("Expected a character array"),
+        }
+        // Stored as UTF-8 in the file
+        let unit = mat_file.find_by_name("unit").unwrap();
+        match unit.data() {
+            ArrayData::Char(chars) => assert_eq!(chars.to_string(), "\u{b5}V"),
+            _ => panic!("Expected a character array"),
+        }
+    }
+
+    #[test]
+    fn cell_array() {
+        let data = include_bytes!("../tests/cell.mat");
+        let mat_file = MatFile::parse(data.as_ref()).unwrap();
+        let array = mat_file.find_by_name("c").unwrap();
+        let cells = match array.data() {
+            ArrayData::Cell(cells) => cells,
+            _ => panic!("Expected a cell array"),
+        };
+        assert_eq!(cells.size(), &vec![2, 3]);
+        assert_eq!(cells.len(), 6);
+        match cells.get(0).unwrap().numeric_data() {
+            Some(NumericData::Double { real, imag: None }) => assert_eq!(real, &vec![1.5]),
+            _ => panic!("Expected a double array"),
+        }
+        match cells.get_by_subscripts(&[1, 0]).unwrap().numeric_data() {
+            Some(NumericData::Int8 { real, imag: None }) => assert_eq!(real, &vec![1, 2, 3]),
+            _ => panic!("Expected an int8 array"),
+        }
+        match cells.get_by_subscripts(&[0, 1]).unwrap().data() {
+            ArrayData::Char(chars) => assert_eq!(chars.to_string(), "abc"),
+            _ => panic!("Expected a character array"),
+        }
+        match cells.get_by_subscripts(&[1, 1]).unwrap().data() {
+            ArrayData::Cell(nested) => match nested.get(0).unwrap().data() {
+                ArrayData::Char(chars) => assert_eq!(chars.to_string(), "x"),
+                _ => panic!("Expected a character array"),
+            },
+            _ => panic!("Expected a nested cell array"),
+        }
+        // Empty cells
+        assert_eq!(cells.get(4).unwrap().size(), &vec![0, 0]);
+        assert_eq!(cells.get(5).unwrap().size(), &vec![0, 0]);
+        assert!(cells.get_by_subscripts(&[2, 0]).is_none());
+        assert!(cells.get_by_subscripts(&[0]).is_none());
+    }
+
+    #[test]
+    fn structure_array() {
+        let data = include_bytes!("../tests/struct.mat");
+        let mat_file = MatFile::parse(data.as_ref()).unwrap();
+
+        let config = match mat_file.find_by_name("config").unwrap().data() {
+            ArrayData::Struct(config) => config,
+            _ => panic!("Expected a structure array"),
+        };
+        assert_eq!(config.size(), &vec![1, 1]);
+        assert_eq!(config.field_names(), &["name", "gain", "nested"]);
+        let name = config.get_field("name").unwrap();
+        assert_eq!(name.name(), "name");
+        match name.data() {
+            ArrayData::Char(chars) => assert_eq!(chars.to_string(), "run1"),
+            _ => panic!("Expected a character array"),
+        }
+        match config.get_field("gain").unwrap().numeric_data() {
+            Some(NumericData::Double { real, imag: None }) => assert_eq!(real, &vec![2.5]),
+            _ => panic!("Expected a double array"),
+        }
+        match config.get_field("nested").unwrap().data() {
+            ArrayData::Struct(nested) => match nested.get_field("n").unwrap().numeric_data() {
+                Some(NumericData::Int32 { real, imag: None }) => assert_eq!(real, &vec![7]),
+                _ => panic!("Expected an int32 array"),
+            },
+            _ => panic!("Expected a nested structure array"),
+        }
+        assert!(config.get_field("missing").is_none());
+
+        let trials = match mat_file.find_by_name("trials").unwrap().data() {
+            ArrayData::Struct(trials) => trials,
+            _ => panic!("Expected a structure array"),
+        };
+        assert_eq!(trials.len(), 2);
+        assert!(trials.get_field("id").is_none());
+        let labels: Vec<String> = trials
+            .iter()
+            .map(|trial| match trial.get_field("label").unwrap().data() {
+                ArrayData::Char(chars) => chars.to_string(),
+                _ => panic!("Expected a character array"),
+            })
+            .collect();
+        assert_eq!(labels, vec!["a", "b"]);
+        let second = trials.get_by_subscripts(&[0, 1]).unwrap();
+        match second.get_field("id").unwrap().numeric_data() {
+            Some(NumericData::Double { real, imag: None }) => assert_eq!(real, &vec![2.0]),
+            _ => panic!("Expected a double array"),
+        }
+        assert!(trials.get(2).is_none());
+
+        match mat_file.find_by_name("mixed").unwrap().data() {
+            ArrayData::Cell(cells) => match cells.get(0).unwrap().data() {
+                ArrayData::Struct(s) => assert_eq!(s.field_names(), &["x"]),
+                _ => panic!("Expected a structure array"),
+            },
+            _ => panic!("Expected a cell array"),
+        }
+    }
+
+    #[test]
+    fn sparse_array() {
+        let data = include_bytes!("../tests/sparse1.mat");
+        let mat_file = MatFile::parse(data.as_ref()).unwrap();
+        let sparse = match mat_file.arrays()[0].data() {
+            ArrayData::Sparse(sparse) => sparse,
+            _ => panic!("Expected a sparse array"),
+        };
+        assert_eq!(sparse.size(), &vec![8, 8]);
+        assert_eq!(sparse.nnz(), 7);
+        assert_eq!(sparse.nzmax(), 7);
+        let real = match sparse.data() {
+            SparseData::Numeric(NumericData::Double { real, imag: None }) => real,
+            _ => panic!("Expected real double data"),
+        };
+        let triplets: Vec<_> = sparse.triplets(real).collect();
+        assert_eq!(
+            triplets,
+            vec![
+                (5, 0, 2.0),
+                (7, 1, 7.0),
+                (2, 3, 4.0),
+                (0, 4, 9.0),
+                (1, 5, 5.0),
+                (3, 6, 8.0),
+                (6, 7, 6.0)
+            ]
+        );
+        match sparse.to_dense() {
+            ArrayData::Numeric(NumericData::Double { real, imag: None }) => {
+                assert_eq!(real.len(), 64);
+                assert_eq!(real[5], 2.0);
+                assert_eq!(real[7 + 8], 7.0);
+                assert_eq!(real[6 + 7 * 8], 6.0);
+                assert_eq!(real.iter().filter(|&&x| x != 0.0).count(), 7);
+            }
+            _ => panic!("Expected real double data"),
+        }
+    }
+
+    #[test]
+    fn complex_sparse_array() {
+        let data = include_bytes!("../tests/sparse2.mat");
+        let mat_file = MatFile::parse(data.as_ref()).unwrap();
+        let sparse = match mat_file.arrays()[0].data() {
+            ArrayData::Sparse(sparse) => sparse,
+            _ => panic!("Expected a sparse array"),
+        };
+        match sparse.to_dense() {
+            ArrayData::Numeric(NumericData::Double {
+                real,
+                imag: Some(imag),
+            }) => {
+                assert_eq!(real[2 + 3 * 8], 4.0);
+                assert_eq!(imag[2 + 3 * 8], 3.0);
+            }
+            _ => panic!("Expected complex double data"),
+        }
+    }
+
+    #[test]
+    fn logical_array() {
+        let data = include_bytes!("../tests/logical.mat");
+        let mat_file = MatFile::parse(data.as_ref()).unwrap();
+        match mat_file.find_by_name("mask").unwrap().data() {
+            ArrayData::Logical(mask) => {
+                assert_eq!(mask, &vec![true, false, false, true, true, false])
+            }
+            _ => panic!("Expected a logical array"),
+        }
+        // Genuine uint8 data must not be mistaken for logical data
+        match mat_file.find_by_name("img").unwrap().data() {
+            ArrayData::Numeric(NumericData::UInt8 { real, imag: None }) => {
+                assert_eq!(real, &vec![1, 0, 255])
+            }
+            _ => panic!("Expected a uint8 array"),
+        }
+        let sparse = match mat_file.find_by_name("spmask").unwrap().data() {
+            ArrayData::Sparse(sparse) => sparse,
+            _ => panic!("Expected a sparse array"),
+        };
+        match sparse.data() {
+            SparseData::Logical(values) => {
+                let triplets: Vec<_> = sparse.triplets(values).collect();
+                assert_eq!(triplets, vec![(2, 0, true), (0, 1, true)]);
+            }
+            _ => panic!("Expected logical sparse data"),
+        }
+        match sparse.to_dense() {
+            ArrayData::Logical(dense) => {
+                assert_eq!(dense, vec![false, false, true, true, false, false])
+            }
+            _ => panic!("Expected logical data"),
+        }
+    }
+
+    #[test]
+    fn object_array() {
+        let data = include_bytes!("../tests/object.mat");
+        let mat_file = MatFile::parse(data.as_ref()).unwrap();
+        let object = match mat_file.find_by_name("obj").unwrap().data() {
+            ArrayData::Object(object) => object,
+            _ => panic!("Expected an object array"),
+        };
+        assert_eq!(object.class_name(), "polynom");
+        assert_eq!(object.size(), &vec![1, 1]);
+        assert_eq!(object.field_names(), &["coef", "var"]);
+        match object.get_field("coef").unwrap().numeric_data() {
+            Some(NumericData::Double { real, imag: None }) => {
+                assert_eq!(real, &vec![1.0, 2.0, 3.0])
+            }
+            _ => panic!("Expected a double array"),
+        }
+        match object.get(0).unwrap().get_field("var").unwrap().data() {
+            ArrayData::Char(chars) => assert_eq!(chars.to_string(), "x"),
+            _ => panic!("Expected a character array"),
+        }
+    }
+
+    #[test]
+    fn string_array() {
+        let data = include_bytes!("../tests/string.mat");
+        let mat_file = MatFile::parse(data.as_ref()).unwrap();
+        // The subsystem data is not a variable
+        assert_eq!(mat_file.arrays().len(), 3);
+        let s = mat_file.find_by_name("s").unwrap();
+        assert_eq!(s.size(), &vec![1, 1]);
+        match s.data() {
+            ArrayData::String(strings) => assert_eq!(strings.get(0), Some("hello")),
+            _ => panic!("Expected a string array"),
+        }
+        let names = mat_file.find_by_name("names").unwrap();
+        assert_eq!(names.size(), &vec![2, 3]);
+        let names = match names.data() {
+            ArrayData::String(strings) => strings,
+            _ => panic!("Expected a string array"),
+        };
+        assert_eq!(
+            names.iter().collect::<Vec<_>>(),
+            vec![
+                Some("alpha"),
+                Some(""),
+                Some("\u{3b2}"),
+                Some("gamma"),
+                None,
+                Some("delta")
+            ]
+        );
+        assert_eq!(names.get_by_subscripts(&[1, 2]), Some("delta"));
+        let c = match mat_file.find_by_name("c").unwrap().data() {
+            ArrayData::Cell(cells) => cells,
+            _ => panic!("Expected a cell array"),
+        };
+        match c.get(0).unwrap().data() {
+            ArrayData::String(strings) => assert_eq!(strings.get(0), Some("in cell")),
+            _ => panic!("Expected a string array"),
+        }
+    }
+
+    #[test]
+    fn invalid_encoded_objects() {
+        // "hi" in a 1x1 array
+        let valid = [1, 2, 1, 1, 2, 0x0069_0068];
+        assert!(StringArray::from_encoded(&valid, true).is_some());
+        for data in [
+            &[1, u64::MAX][..],
+            &[1, 2, u64::MAX, 2, 2][..],
+            &[1, 2, 1, 2, 2, u64::MAX - 1, 0x0069_0068][..],
+        ] {
+            assert_eq!(StringArray::from_encoded(data, true), None);
+        }
+        assert_eq!(
+            mcos::opaque_size(
+                "MCOS",
+                &invalid_reference(&[0xdd00_0000, 2, u32::MAX, 3, 1])
+            ),
+            None
+        );
+        assert_eq!(
+            mcos::opaque_size(
+                "MCOS",
+                &invalid_reference(&[0xdd00_0000, 3, u32::MAX, u32::MAX, u32::MAX])
+            ),
+            None
+        );
+    }
+
+    fn invalid_reference(data: &[u32]) -> parse::DataElement {
+        parse::DataElement::NumericMatrix(
+            parse::ArrayFlags {
+                complex: false,
+                global: false,
+                logical: false,
+                class: parse::ArrayType::UInt32,
+                nzmax: 0,
+            },
+            vec![1, data.len() as i32],
+            String::new(),
+            parse::NumericData::UInt32(data.to_vec()),
+            None,
+        )
+    }
+
+    #[test]
+    fn datetime_and_duration_arrays() {
+        let data = include_bytes!("../tests/datetime.mat");
+        let mat_file = MatFile::parse(data.as_ref()).unwrap();
+        let t = match mat_file.find_by_name("t").unwrap().data() {
+            ArrayData::Datetime(t) => t,
+            _ => panic!("Expected a datetime array"),
+        };
+        assert_eq!(t.size(), &vec![1, 3]);
+        assert_eq!(&t.millis()[..2], &[1705314600000.0, 1705314601500.0]);
+        assert!(t.millis()[2].is_nan());
+        assert_eq!(t.time_zone(), None);
+        assert_eq!(t.format(), Some("yyyy-MM-dd HH:mm:ss"));
+        // The format of this one is taken from the class defaults
+        let zoned = match mat_file.find_by_name("zoned").unwrap().data() {
+            ArrayData::Datetime(zoned) => zoned,
+            _ => panic!("Expected a datetime array"),
+        };
+        assert_eq!(zoned.millis(), &[1705314600000.25]);
+        assert_eq!(zoned.time_zone(), Some("Europe/Berlin"));
+        assert_eq!(zoned.format(), None);
+        let d = match mat_file.find_by_name("d").unwrap().data() {
+            ArrayData::Duration(d) => d,
+            _ => panic!("Expected a duration array"),
+        };
+        assert_eq!(d.size(), &vec![2, 1]);
+        assert_eq!(d.millis(), &[1500.0, -60000.0]);
+        assert_eq!(d.format(), Some("hh:mm:ss"));
+        #[cfg(feature = "chrono")]
+        {
+            let date_times = t.to_chrono();
+            assert_eq!(
+                date_times[1].unwrap().to_string(),
+                "2024-01-15 10:30:01.500"
+            );
+            assert_eq!(date_times[2], None);
+            assert_eq!(
+                d.to_chrono(),
+                vec![
+                    Some(chrono::Duration::milliseconds(1500)),
+                    Some(chrono::Duration::minutes(-1))
+                ]
+            );
+        }
+    }
+
+    #[test]
+    fn table_and_timetable() {
+        let data = include_bytes!("../tests/table.mat");
+        let mat_file = MatFile::parse(data.as_ref()).unwrap();
+        let t = mat_file.find_by_name("T").unwrap();
+        assert_eq!(t.size(), &vec![3, 3]);
+        let t = match t.data() {
+            ArrayData::Table(t) => t,
+            _ => panic!("Expected a table"),
+        };
+        assert!(!t.is_timetable());
+        assert_eq!(t.num_rows(), 3);
+        assert_eq!(t.column_names(), &["Name", "Age", "Label"]);
+        assert_eq!(t.row_names(), &["r1", "r2", "r3"]);
+        match t.column("Age").unwrap().numeric_data() {
+            Some(NumericData::Double { real, imag: None }) => {
+                assert_eq!(real, &vec![31.0, 42.0, 27.0])
+            }
+            _ => panic!("Expected a double array"),
+        }
+        match t.column("Name").unwrap().data() {
+            ArrayData::Cell(names) => match names.get(1).unwrap().data() {
+                ArrayData::Char(chars) => assert_eq!(chars.to_string(), "Bob"),
+                _ => panic!("Expected a character array"),
+            },
+            _ => panic!("Expected a cell array"),
+        }
+        // Columns can be objects themselves
+        let label = t.column("Label").unwrap();
+        assert_eq!(label.name(), "Label");
+        match label.data() {
+            ArrayData::String(strings) => assert_eq!(strings.get(2), Some("z")),
+            _ => panic!("Expected a string array"),
+        }
+
+        let tt = match mat_file.find_by_name("TT").unwrap().data() {
+            ArrayData::Table(tt) => tt,
+            _ => panic!("Expected a timetable"),
+        };
+        assert!(tt.is_timetable());
+        assert_eq!(tt.num_rows(), 2);
+        assert_eq!(tt.column_names(), &["Temp"]);
+        assert!(tt.row_names().is_empty());
+        match tt.row_times().unwrap().data() {
+            ArrayData::Datetime(times) => {
+                assert_eq!(times.millis(), &[1705314600000.0, 1705314660000.0])
+            }
+            _ => panic!("Expected a datetime array"),
+        }
+    }
+
+    #[test]
+    fn categorical_array_and_map() {
+        let data = include_bytes!("../tests/categorical.mat");
+        let mat_file = MatFile::parse(data.as_ref()).unwrap();
+        let levels = match mat_file.find_by_name("levels").unwrap().data() {
+            ArrayData::Categorical(levels) => levels,
+            _ => panic!("Expected a categorical array"),
+        };
+        assert_eq!(levels.size(), &vec![2, 2]);
+        assert_eq!(levels.categories(), &["low", "high"]);
+        assert_eq!(levels.codes(), &[1, 2, 0, 1]);
+        assert!(levels.is_ordinal());
+        assert!(!levels.is_protected());
+        assert_eq!(
+            levels.iter().collect::<Vec<_>>(),
+            vec![Some("low"), Some("high"), None, Some("low")]
+        );
+        assert_eq!(levels.get_by_subscripts(&[1, 0]), Some("high"));
+
+        let lookup = match mat_file.find_by_name("lookup").unwrap().data() {
+            ArrayData::Map(lookup) => lookup,
+            _ => panic!("Expected a map"),
+        };
+        assert_eq!(lookup.key_type(), "char");
+        assert_eq!(lookup.value_type(), "any");
+        assert_eq!(lookup.len(), 2);
+        match lookup.get("a").unwrap().numeric_data() {
+            Some(NumericData::Double { real, imag: None }) => assert_eq!(real, &vec![1.0]),
+            _ => panic!("Expected a double array"),
+        }
+        match lookup.get("b").unwrap().data() {
+            ArrayData::Char(chars) => assert_eq!(chars.to_string(), "two"),
+            _ => panic!("Expected a character array"),
+        }
+        assert!(lookup.get("c").is_none());
+
+        let numeric_lookup = match mat_file.find_by_name("numeric_lookup").unwrap().data() {
+            ArrayData::Map(numeric_lookup) => numeric_lookup,
+            _ => panic!("Expected a map"),
+        };
+        assert_eq!(numeric_lookup.key_type(), "double");
+        assert_eq!(
+            numeric_lookup.keys(),
+            &[MapKey::Numeric(1.0), MapKey::Numeric(2.5)]
+        );
+        match numeric_lookup.get_numeric(2.5).unwrap().data() {
+            ArrayData::Char(chars) => assert_eq!(chars.to_string(), "y"),
+            _ => panic!("Expected a character array"),
+        }
+    }
+
+    #[test]
+    fn function_handles() {
+        let data = include_bytes!("../tests/function.mat");
+        let mat_file = MatFile::parse(data.as_ref()).unwrap();
+        let sine = match mat_file.find_by_name("sine").unwrap().data() {
+            ArrayData::FunctionHandle(sine) => sine,
+            _ => panic!("Expected a function handle"),
+        };
+        assert_eq!(sine.kind(), &FunctionHandleKind::Simple);
+        assert_eq!(sine.function(), "sin");
+        assert_eq!(sine.file(), None);
+        assert!(sine.captured_variables().is_empty());
+        let square = match mat_file.find_by_name("square").unwrap().data() {
+            ArrayData::FunctionHandle(square) => square,
+            _ => panic!("Expected a function handle"),
+        };
+        assert_eq!(square.kind(), &FunctionHandleKind::Anonymous);
+        assert_eq!(square.function(), "@(x)x.^2+a");
+        // The captured Java object is not supported and left out
+        assert_eq!(square.captured_variables().len(), 1);
+        match square.captured_variable("a").unwrap().numeric_data() {
+            Some(NumericData::Double { real, imag: None }) => assert_eq!(real, &vec![3.0]),
+            _ => panic!("Expected a double array"),
+        }
+        let helper = match mat_file.find_by_name("helper").unwrap().data() {
+            ArrayData::FunctionHandle(helper) => helper,
+            _ => panic!("Expected a function handle"),
+        };
+        assert_eq!(helper.kind(), &FunctionHandleKind::Nested);
+        assert_eq!(helper.file(), Some("/home/user/run.m"));
+    }
+
+    #[test]
+    fn level4_files() {
+        for data in [
+            include_bytes!("../tests/v4.mat").as_ref(),
+            include_bytes!("../tests/v4_be.mat").as_ref(),
+        ] {
+            let mat_file = MatFile::parse(data).unwrap();
+            assert_eq!(mat_file.arrays().len(), 6);
+            let x = mat_file.find_by_name("x").unwrap();
+            assert_eq!(x.size(), &vec![2, 3]);
+            match x.data() {
+                ArrayData::Numeric(NumericData::Double { real, imag: None }) => {
+                    assert_eq!(real, &vec![1.0, 4.0, 2.0, 5.0, 3.0, 6.0])
+                }
+                _ => panic!("Expected real double data"),
+            }
+            match mat_file.find_by_name("z").unwrap().data() {
+                ArrayData::Numeric(NumericData::Double {
+                    real,
+                    imag: Some(imag),
+                }) => {
+                    assert_eq!(real, &vec![1.0, 2.0]);
+                    assert_eq!(imag, &vec![3.0, -4.0]);
+                }
+                _ => panic!("Expected complex double data"),
+            }
+            // Level 4 files only know double matrices
+            match mat_file.find_by_name("u").unwrap().data() {
+                ArrayData::Numeric(NumericData::Double { real, imag: None }) => {
+                    assert_eq!(real, &vec![7.0, 8.0, 255.0])
+                }
+                _ => panic!("Expected real double data"),
+            }
+            match mat_file.find_by_name("s").unwrap().data() {
+                ArrayData::Char(chars) => assert_eq!(chars.rows(), vec!["ab", "cd"]),
+                _ => panic!("Expected a character array"),
+            }
+            let sparse = match mat_file.find_by_name("sp").unwrap().data() {
+                ArrayData::Sparse(sparse) => sparse,
+                _ => panic!("Expected a sparse array"),
+            };
+            assert_eq!(sparse.size(), &vec![3, 4]);
+            let real = match sparse.data() {
+                SparseData::Numeric(NumericData::Double { real, imag: None }) => real,
+                _ => panic!("Expected real double data"),
+            };
+            assert_eq!(
+                sparse.triplets(real).collect::<Vec<_>>(),
+                vec![(1, 0, 5.0), (0, 2, 6.0), (2, 2, 7.0)]
+            );
+            match mat_file.find_by_name("spz").unwrap().data() {
+                ArrayData::Sparse(sparse) => match sparse.data() {
+                    SparseData::Numeric(NumericData::Double {
+                        real,
+                        imag: Some(imag),
+                    }) => {
+                        assert_eq!(sparse.size(), &vec![2, 2]);
+                        assert_eq!(real, &vec![1.0, 0.0]);
+                        assert_eq!(imag, &vec![2.0, 0.0]);
+                    }
+                    _ => panic!("Expected complex double data"),
+                },
+                _ => panic!("Expected a sparse array"),
+            }
+        }
+    }
+
+    #[test]
+    fn level4_vax_file() {
+        // A VAX D-float double scalar
+        let mut data = Vec::new();
+        for field in &[2000u32, 1, 1, 0, 2] {
+            data.extend_from_slice(&field.to_le_bytes());
+        }
+        data.extend_from_slice(b"x\0");
+        data.extend_from_slice(&[0; 8]);
+        assert_eq!(MatFile::detect_version(&data), FileVersion::V4);
+        assert!(matches!(
+            MatFile::parse(data.as_slice()),
+            Err(Error::UnsupportedFormat(FileVersion::V4))
+        ));
+    }
+
+    #[test]
+    fn v73_file() {
+        let data = include_bytes!("../tests/v73.mat");
+        let mat_file = MatFile::parse(data.as_ref()).unwrap();
+        // The string object is not supported and #refs# is not a variable
+        assert_eq!(mat_file.arrays().len(), 11);
+        let a = mat_file.find_by_name("a").unwrap();
+        assert_eq!(a.size(), &vec![2, 3]);
+        match a.data() {
+            ArrayData::Numeric(NumericData::Double { real, imag: None }) => {
+                assert_eq!(real, &vec![1.0, 4.0, 2.0, 5.0, 3.0, 6.0])
+            }
+            _ => panic!("Expected real double data"),
+        }
+        match mat_file.find_by_name("z").unwrap().data() {
+            ArrayData::Numeric(NumericData::Double {
+                real,
+                imag: Some(imag),
+            }) => {
+                assert_eq!(real, &vec![1.0, 2.0]);
+                assert_eq!(imag, &vec![3.0, -4.0]);
+            }
+            _ => panic!("Expected complex double data"),
+        }
+        match mat_file.find_by_name("w").unwrap().data() {
+            ArrayData::Numeric(NumericData::Single { real, imag: None }) => {
+                assert_eq!(real, &vec![1.5, -2.0])
+            }
+            _ => panic!("Expected real single data"),
+        }
+        // Chunked, shuffled and compressed
+        let big = mat_file.find_by_name("big").unwrap();
+        assert_eq!(big.size(), &vec![40, 30]);
+        match big.data() {
+            ArrayData::Numeric(NumericData::Int16 { real, imag: None }) => {
+                assert_eq!(real, &(0..1200).collect::<Vec<i16>>())
+            }
+            _ => panic!("Expected real int16 data"),
+        }
+        match mat_file.find_by_name("s").unwrap().data() {
+            ArrayData::Char(chars) => assert_eq!(chars.to_string(), "hello"),
+            _ => panic!("Expected a character array"),
+        }
+        match mat_file.find_by_name("flag").unwrap().data() {
+            ArrayData::Logical(values) => assert_eq!(values, &vec![true, false, true]),
+            _ => panic!("Expected a logical array"),
+        }
+        assert_eq!(mat_file.find_by_name("e").unwrap().size(), &vec![0, 3]);
+        let c = match mat_file.find_by_name("c").unwrap().data() {
+            ArrayData::Cell(cells) => cells,
+            _ => panic!("Expected a cell array"),
+        };
+        assert_eq!(c.size(), &vec![1, 2]);
+        match c.get(1).unwrap().data() {
+            ArrayData::Char(chars) => assert_eq!(chars.to_string(), "xy"),
+            _ => panic!("Expected a character array"),
+        }
+        let st = match mat_file.find_by_name("st").unwrap().data() {
+            ArrayData::Struct(st) => st,
+            _ => panic!("Expected a structure array"),
+        };
+        assert_eq!(st.field_names(), &["zeta", "alpha"]);
+        let sa = match mat_file.find_by_name("sa").unwrap().data() {
+            ArrayData::Struct(sa) => sa,
+            _ => panic!("Expected a structure array"),
+        };
+        assert_eq!(sa.size(), &vec![1, 2]);
+        match sa.get(1).unwrap().get_field("v").unwrap().data() {
+            ArrayData::Numeric(NumericData::Double { real, .. }) => assert_eq!(real, &vec![2.0]),
+            _ => panic!("Expected real double data"),
+        }
+        let sparse = match mat_file.find_by_name("sp").unwrap().data() {
+            ArrayData::Sparse(sparse) => sparse,
+            _ => panic!("Expected a sparse array"),
+        };
+        assert_eq!(sparse.size(), &vec![3, 3]);
+        let real = match sparse.data() {
+            SparseData::Numeric(NumericData::Double { real, imag: None }) => real,
+            _ => panic!("Expected real double data"),
+        };
+        assert_eq!(
+            sparse.triplets(real).collect::<Vec<_>>(),
+            vec![(1, 0, 5.0), (2, 2, 6.0)]
+        );
+    }
+
+    #[test]
+    fn v73_file_with_new_style_groups() {
+        let data = include_bytes!("../tests/v73_new.mat");
+        let mat_file = MatFile::parse(data.as_ref()).unwrap();
+        assert_eq!(mat_file.arrays().len(), 2);
+        match mat_file.find_by_name("x").unwrap().data() {
+            ArrayData::Numeric(NumericData::UInt32 { real, imag: None }) => {
+                assert_eq!(real, &vec![10, 20, 30])
+            }
+            _ => panic!("Expected real uint32 data"),
+        }
+        assert!(mat_file.find_by_name("y").is_some());
+    }
+
+    #[test]
+    fn parse_errors() {
+        let parse_error = |data: &[u8]| match MatFile::parse(data) {
+            Err(Error::ParseError(err)) => err,
+            _ => panic!("Expected a parse error"),
+        };
+        let data = include_bytes!("../tests/small_matrix.mat");
+        let err = parse_error(&data[..data.len() - 4]);
+        assert_eq!(err.kind(), &ParseErrorKind::Truncated);
+        assert_eq!(err.offset(), 128);
+        assert_eq!(err.variable(), Some("abcde"));
+        // Four columns, but only three values
+        let mut corrupt = data.to_vec();
+        corrupt[164] = 4;
+        let err = parse_error(&corrupt);
+        assert_eq!(
+            err.kind(),
+            &ParseErrorKind::LengthMismatch {
+                expected: 4,
+                actual: 3
+            }
+        );
+        assert_eq!(err.offset(), 184);
+        // Unknown data type of the real part
+        let mut corrupt = data.to_vec();
+        corrupt[184] = 32;
+        let err = parse_error(&corrupt);
+        assert_eq!(err.kind(), &ParseErrorKind::BadTagType(32));
+        assert_eq!(err.offset(), 184);
+        assert_eq!(
+            err.to_string(),
+            "unexpected data type 32 at byte offset 184 in variable \"abcde\""
+        );
+        // Broken checksum of compressed data
+        let data = include_bytes!("../tests/double.mat");
+        let mut corrupt = data.to_vec();
+        corrupt[data.len() - 1] ^= 0xFF;
+        let err = parse_error(&corrupt);
+        assert!(matches!(err.kind(), ParseErrorKind::Decompression(_)));
+        assert_eq!(err.offset(), 136);
+        assert_eq!(err.variable(), Some("A"));
+    }
+
+    #[test]
+    fn warnings() {
+        let collect_warnings = |data: &[u8]| {
+            let warnings = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+            let sink = warnings.clone();
+            let options = ParseOptions::new()
+                .on_warning(move |warning| sink.lock().unwrap().push(warning.clone()));
+            let mat_file = MatFile::parse_with(data, &options).unwrap();
+            let warnings = warnings.lock().unwrap().clone();
+            (mat_file, warnings)
+        };
+        let (_, warnings) = collect_warnings(include_bytes!("../tests/double.mat"));
+        assert!(warnings.is_empty());
+        let (mat_file, warnings) = collect_warnings(include_bytes!("../tests/v73.mat"));
+        assert!(mat_file.find_by_name("str").is_none());
+        assert_eq!(
+            warnings,
+            vec![Warning::SkippedVariable(mat_file.skipped()[0].clone())]
+        );
+        // A double element at the top level, which is not a variable
+        let mut data = include_bytes!("../tests/small_matrix.mat").to_vec();
+        data.extend_from_slice(&[9, 0, 0, 0, 8, 0, 0, 0]);
+        data.extend_from_slice(&1.0f64.to_le_bytes());
+        let (mat_file, warnings) = collect_warnings(&data);
+        assert_eq!(mat_file.arrays().len(), 1);
+        assert_eq!(warnings, vec![Warning::IgnoredDataElement { data_type: 9 }]);
+        // A variable of an unknown class
+        let mut data = include_bytes!("../tests/small_matrix.mat").to_vec();
+        data[144] = 31;
+        let (mat_file, warnings) = collect_warnings(&data);
+        assert!(mat_file.arrays().is_empty());
+        assert_eq!(
+            warnings,
+            vec![Warning::SkippedVariable(mat_file.skipped()[0].clone())]
+        );
+    }
+
+    #[test]
+    fn skipped_variables() {
+        let data = include_bytes!("../tests/v73.mat");
+        let mat_file = MatFile::parse(data.as_ref()).unwrap();
+        assert_eq!(mat_file.skipped().len(), 1);
+        let skipped = &mat_file.skipped()[0];
+        assert_eq!(skipped.name(), "str");
+        assert_eq!(skipped.class(), "string");
+        assert_eq!(skipped.reason(), SkipReason::UnsupportedClass);
+        let double = MatFile::parse(include_bytes!("../tests/double.mat").as_ref()).unwrap();
+        assert!(double.skipped().is_empty());
+
+        let strict = ParseOptions::new().strict(true);
+        match MatFile::parse_with(data.as_ref(), &strict) {
+            Err(Error::UnsupportedVariable(variable)) => assert_eq!(&variable, skipped),
+            other => panic!("unexpected result {:?}", other),
+        }
+        assert!(
+            MatFile::parse_with(include_bytes!("../tests/double.mat").as_ref(), &strict).is_ok()
+        );
+
+        // Variables of unknown classes are skipped as well
+        let mut data = include_bytes!("../tests/small_matrix.mat").to_vec();
+        data[144] = 31;
+        let mat_file = MatFile::parse(data.as_slice()).unwrap();
+        assert!(mat_file.arrays().is_empty());
+        let skipped = &mat_file.skipped()[0];
+        assert_eq!(skipped.name(), "abcde");
+        assert_eq!(skipped.class(), "unknown class 31");
+        assert_eq!(skipped.size(), &vec![1, 3]);
+        assert_eq!(skipped.reason(), SkipReason::UnsupportedClass);
+        match MatFile::parse_with(data.as_slice(), &strict) {
+            Err(Error::UnsupportedVariable(variable)) => assert_eq!(&variable, skipped),
+            other => panic!("unexpected result {:?}", other),
+        }
+        let mut reader = MatFileReader::new(std::io::Cursor::new(&data)).unwrap();
+        assert_eq!(reader.variables()[0].class(), "unknown class 31");
+        assert!(matches!(
+            reader.load("abcde"),
+            Err(Error::UnsupportedVariable(_))
+        ));
+    }
+
+    #[test]
+    fn header() {
+        let data = include_bytes!("../tests/double.mat");
+        let header = MatFile::parse(data.as_ref()).unwrap().header().clone();
+        assert_eq!(header.platform(), Some("MACI64"));
+        assert_eq!(header.created(), Some(1553547803));
+        assert!(header
+            .text()
+            .ends_with("Created on: Mon Mar 25 21:03:23 2019"));
+        assert!(header.is_little_endian());
+        assert_eq!(header.subsystem_data_offset(), None);
+        assert_eq!(header.version(), FileVersion::V7);
+        #[cfg(feature = "chrono")]
+        assert_eq!(
+            header.created_chrono().unwrap().to_string(),
+            "2019-03-25 21:03:23"
+        );
+
+        let data = include_bytes!("../tests/v73.mat");
+        let header = MatFile::parse(data.as_ref()).unwrap().header().clone();
+        assert!(header.text().starts_with("MATLAB 7.3 MAT-file"));
+        assert_eq!(header.platform(), None);
+        assert_eq!(header.created(), None);
+        assert_eq!(header.version(), FileVersion::V73);
+
+        let data = include_bytes!("../tests/v4.mat");
+        let header = MatFile::parse(data.as_ref()).unwrap().header().clone();
+        assert_eq!(header.text(), "");
+        assert_eq!(header.platform(), None);
+        assert_eq!(header.created(), None);
+        assert_eq!(header.version(), FileVersion::V4);
+
+        let created = |date: &str| {
+            let mut data = include_bytes!("../tests/small_matrix.mat").to_vec();
+            let text = format!("MATLAB 5.0 MAT-file, Created on: {}", date);
+            data[..116].copy_from_slice(format!("{:116}", text).as_bytes());
+            MatFile::parse(data.as_slice()).unwrap().header().created()
+        };
+        assert_eq!(created("Fri Dec 31 23:59:59 9999"), Some(253402300799));
+        assert_eq!(created("Mon Jan 1 00:00:00 10000"), None);
+        assert_eq!(created("Thu Feb 29 00:00:00 2024"), Some(1709164800));
+        assert_eq!(created("Sat Feb 31 00:00:00 2024"), None);
+        assert_eq!(created("Thu Feb 29 00:00:00 2023"), None);
+        assert_eq!(created("Mon Feb 29 00:00:00 1900"), None);
+        assert_eq!(created("Tue Feb 29 00:00:00 2000"), Some(951782400));
+        assert_eq!(created("Sun Apr 31 00:00:00 2024"), None);
+        assert_eq!(created("Mon Jan 1 00:00:00 9223372036854775807"), None);
+        assert_eq!(created("Mon Jan 1 00:00:00 -9223372036854775808"), None);
+    }
+
+    #[test]
+    fn reader() {
+        for entry in std::fs::read_dir("tests").unwrap() {
+            let path = entry.unwrap().path();
+            let data = std::fs::read(&path).unwrap();
+            let version = MatFile::detect_version(&data);
+            if version != FileVersion::V6 && version != FileVersion::V7 {
+                assert!(matches!(
+                    MatFileReader::new(std::io::Cursor::new(&data)),
+                    Err(Error::UnsupportedFormat(_))
+                ));
+                continue;
+            }
+            let mat_file = MatFile::parse(data.as_slice()).unwrap();
+            let mut reader = MatFileReader::new(std::io::Cursor::new(&data)).unwrap();
+            assert_eq!(reader.header(), mat_file.header());
+            assert_eq!(
+                reader.variables().len(),
+                mat_file.arrays().len() + mat_file.skipped().len(),
+                "{:?}",
+                path
+            );
+            for variable in reader.variables().to_vec() {
+                match mat_file.find_by_name(variable.name()) {
+                    Some(array) => {
+                        let loaded = reader.load(variable.name()).unwrap().unwrap();
+                        assert_eq!(format!("{:?}", loaded), format!("{:?}", array));
+                        // Arrays of `classdef` objects like datetimes
+                        // store their size within the objects
+                        let is_classdef = matches!(
+                            array.data(),
+                            ArrayData::String(_)
+                                | ArrayData::Datetime(_)
+                                | ArrayData::Duration(_)
+                                | ArrayData::Table(_)
+                                | ArrayData::Categorical(_)
+                                | ArrayData::Map(_)
+                        );
+                        if !is_classdef {
+                            assert_eq!(variable.size(), array.size(), "{:?}", path);
+                        }
+                    }
+                    None => {
+                        let skipped = mat_file
+                            .skipped()
+                            .iter()
+                            .find(|skipped| skipped.name() == variable.name())
+                            .unwrap();
+                        assert_eq!(variable.class(), skipped.class());
+                        assert_eq!(variable.size(), skipped.size());
+                        assert!(matches!(
+                            reader.load(variable.name()),
+                            Err(Error::UnsupportedVariable(_))
+                        ));
+                    }
+                }
+            }
+            assert!(reader.load("no such variable").unwrap().is_none());
+        }
+
+        let data = include_bytes!("../tests/double.mat");
+        let reader = MatFileReader::new(std::io::Cursor::new(data)).unwrap();
+        let variable = &reader.variables()[0];
+        assert_eq!(variable.name(), "A");
+        assert_eq!(variable.class(), "double");
+        assert_eq!(variable.offset(), 128);
+        assert_eq!(variable.stored_size() as usize, data.len() - 128);
+        assert!(variable.is_compressed());
+        // Truncated files are reported when they are scanned
+        let truncated = std::io::Cursor::new(&data[..data.len() - 1]);
+        match MatFileReader::new(truncated) {
+            Err(Error::ParseError(err)) => {
+                assert_eq!(err.kind(), &ParseErrorKind::Truncated);
+                assert_eq!(err.offset(), 128);
+                assert_eq!(err.variable(), Some("A"));
+            }
+            other => panic!("unexpected result {:?}", other.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn reader_large_header() {
+        use std::io::{Read, Seek, Write};
+
+        /// Counts the bytes read from a file.
+        struct Counting<R> {
+            inner: R,
+            count: std::rc::Rc<std::cell::Cell<usize>>,
+        }
+        impl<R: Read> Read for Counting<R> {
+            fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+                let len = self.inner.read(buf)?;
+                self.count.set(self.count.get() + len);
+                Ok(len)
+            }
+        }
+        impl<R: Seek> Seek for Counting<R> {
+            fn seek(&mut self, pos: std::io::SeekFrom) -> std::io::Result<u64> {
+                self.inner.seek(pos)
+            }
+        }
+
+        // A compressed logical array whose dimensions alone take more than
+        // the 4 KB read up front, followed by a lot of random values
+        let mut dims = vec![1; 1200];
+        dims.push(1 << 20);
+        let mut state = 1u32;
+        let values: Vec<bool> = (0..1 << 20)
+            .map(|_| {
+                state = state.wrapping_mul(1_103_515_245).wrapping_add(12345);
+                state & 0x1_0000 != 0
+            })
+            .collect();
+        let mut uncompressed = Vec::new();
+        write::MatFileWriter::new(&mut uncompressed)
+            .unwrap()
+            .write_logical_array("mask", &dims, &values)
+            .unwrap();
+        let mut encoder = libflate::zlib::Encoder::new(Vec::new()).unwrap();
+        encoder.write_all(&uncompressed[128..]).unwrap();
+        let compressed = encoder.finish().into_result().unwrap();
+        let mut data = uncompressed[..128].to_vec();
+        data.extend_from_slice(&15u32.to_le_bytes());
+        data.extend_from_slice(&(compressed.len() as u32).to_le_bytes());
+        data.extend_from_slice(&compressed);
+
+        let count = std::rc::Rc::default();
+        let mut reader = MatFileReader::new(Counting {
+            inner: std::io::Cursor::new(&data),
+            count: std::rc::Rc::clone(&count),
+        })
+        .unwrap();
+        assert_eq!(reader.variables()[0].size(), dims.as_slice());
+        // Only the start of the variable has been decompressed
+        assert!(count.get() < compressed.len() / 4);
+        match reader.load("mask").unwrap().unwrap().data() {
+            ArrayData::Logical(loaded) => assert_eq!(loaded, &values),
+            _ => panic!("Expected a logical array"),
+        }
+    }
+
+    #[test]
+    fn wildcard_patterns() {
+        let selects =
+            |pattern: &str, name: &str| ParseOptions::new().variables([pattern]).selects(name);
+        assert!(selects("abc", "abc"));
+        assert!(!selects("abc", "abcd"));
+        assert!(selects("a?c", "abc"));
+        assert!(!selects("a?c", "ac"));
+        assert!(selects("*", ""));
+        assert!(selects("a*", "a"));
+        assert!(selects("*c", "abc"));
+        assert!(selects("a*b*c", "aXbYbZc"));
+        assert!(!selects("a*b*c", "aXbYbZ"));
+        assert!(selects("**a**", "bab"));
+        assert!(selects("*?", "x"));
+        assert!(!selects("*?", ""));
+        // Would take exponential time with naive backtracking
+        let name = "a".repeat(100);
+        assert!(!selects(&format!("{}b", "a*".repeat(20)), &name));
+        assert!(selects(&"a*".repeat(20), &name));
+    }
+
+    #[test]
+    fn select_variables() {
+        let names = |data: &[u8], options: &ParseOptions| -> Vec<String> {
+            let mat_file = MatFile::parse_with(data, options).unwrap();
+            mat_file
+                .arrays()
+                .iter()
+                .map(|a| a.name().to_owned())
+                .collect()
+        };
+        let data = include_bytes!("../tests/struct.mat");
+        let options = ParseOptions::new().variables(["trials"]);
+        assert_eq!(names(data, &options), ["trials"]);
+        let options = ParseOptions::new().variables(["c*", "m?xed"]);
+        assert_eq!(names(data, &options), ["config", "mixed"]);
+        let options = ParseOptions::new().variables(Vec::<String>::new());
+        assert!(names(data, &options).is_empty());
+        let reader = MatFileReader::new_with(std::io::Cursor::new(data), &options).unwrap();
+        assert!(reader.variables().is_empty());
+        let options = ParseOptions::new().variables(["t*"]);
+        let reader = MatFileReader::new_with(std::io::Cursor::new(data), &options).unwrap();
+        assert_eq!(reader.variables()[0].name(), "trials");
+        assert_eq!(reader.variables().len(), 1);
+
+        let options = ParseOptions::new().variables(["s*"]);
+        let data = include_bytes!("../tests/v4.mat");
+        assert_eq!(names(data, &options), ["s", "sp", "spz"]);
+        let data = include_bytes!("../tests/v73.mat");
+        assert_eq!(names(data, &options), ["s", "sa", "sp", "st"]);
+        // Variables that are not selected are not reported as skipped
+        let options = ParseOptions::new().variables(["a"]);
+        let mat_file = MatFile::parse_with(data.as_ref(), &options).unwrap();
+        assert!(mat_file.skipped().is_empty());
+
+        #[cfg(feature = "regex")]
+        {
+            let regex = regex::Regex::new("ed$").unwrap();
+            let options = ParseOptions::new()
+                .variables_regex(regex)
+                .variables(["config"]);
+            let data = include_bytes!("../tests/struct.mat");
+            assert_eq!(names(data, &options), ["config", "mixed"]);
+        }
+    }
+
+    #[test]
+    fn stream() {
+        let sorted_arrays = |arrays: Vec<Array>| {
+            let mut arrays: Vec<String> = arrays.iter().map(|a| format!("{:?}", a)).collect();
+            arrays.sort();
+            arrays
+        };
+        for entry in std::fs::read_dir("tests").unwrap() {
+            let path = entry.unwrap().path();
+            let data = std::fs::read(&path).unwrap();
+            let version = MatFile::detect_version(&data);
+            if version != FileVersion::V6 && version != FileVersion::V7 {
+                assert!(matches!(
+                    MatFile::stream(data.as_slice()),
+                    Err(Error::UnsupportedFormat(_))
+                ));
+                continue;
+            }
+            let mat_file = MatFile::parse(data.as_slice()).unwrap();
+            let stream = MatFile::stream(data.as_slice()).unwrap();
+            assert_eq!(stream.header(), mat_file.header());
+            let arrays: Vec<Array> = stream.collect::<Result<_, _>>().unwrap();
+            assert_eq!(
+                sorted_arrays(arrays),
+                sorted_arrays(mat_file.arrays().clone()),
+                "{:?}",
+                path
+            );
+        }
+
+        // Variables holding objects are returned after the other ones. Add
+        // a variable without objects right before the subsystem data.
+        let strings = include_bytes!("../tests/string.mat");
+        let matrix = &include_bytes!("../tests/small_matrix.mat")[128..];
+        let mut ssdo = [0; 8];
+        ssdo.copy_from_slice(&strings[116..124]);
+        let ssdo = u64::from_le_bytes(ssdo) as usize;
+        let mut data = strings[..ssdo].to_vec();
+        data.extend_from_slice(matrix);
+        data.extend_from_slice(&strings[ssdo..]);
+        data[116..124].copy_from_slice(&((ssdo + matrix.len()) as u64).to_le_bytes());
+        let names = |arrays: Vec<Array>| -> Vec<String> {
+            arrays.iter().map(|a| a.name().to_owned()).collect()
+        };
+        let mat_file = MatFile::parse(data.as_slice()).unwrap();
+        assert_eq!(
+            names(mat_file.arrays().clone()),
+            ["s", "names", "c", "abcde"]
+        );
+        let stream = MatFile::stream(data.as_slice()).unwrap();
+        let arrays = stream.collect::<Result<_, _>>().unwrap();
+        assert_eq!(names(arrays), ["abcde", "s", "names", "c"]);
+
+        let options = ParseOptions::new().variables(["t*"]);
+        let data = include_bytes!("../tests/struct.mat");
+        let stream = MatFile::stream_with(data.as_ref(), &options).unwrap();
+        let arrays = stream.collect::<Result<_, _>>().unwrap();
+        assert_eq!(names(arrays), ["trials"]);
+
+        let data = include_bytes!("../tests/small_matrix.mat");
+        let mut stream = MatFile::stream(&data[..data.len() - 1]).unwrap();
+        match stream.next() {
+            Some(Err(Error::ParseError(err))) => {
+                assert_eq!(err.kind(), &ParseErrorKind::Truncated);
+                assert_eq!(err.offset(), 128);
+                assert_eq!(err.variable(), Some("abcde"));
+            }
+            other => panic!("unexpected result {:?}", other),
+        }
+        assert!(stream.next().is_none());
+        let data = include_bytes!("../tests/double.mat");
+        match MatFile::stream(&data[..data.len() - 1]).unwrap().next() {
+            Some(Err(Error::ParseError(err))) => {
+                assert_eq!(err.kind(), &ParseErrorKind::Truncated);
+                assert_eq!(err.offset(), 128);
+                assert_eq!(err.variable(), Some("A"));
+            }
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+
+    #[test]
+    fn view() {
+        let mut file = Vec::new();
+        let mut writer = write::MatFileWriter::new(&mut file).unwrap();
+        let real = parse::NumericData::Double(vec![1.5, -2.0, 3.25]);
+        let imag = parse::NumericData::Double(vec![0.5, 0.0, -1.0]);
+        writer.write_array("z", real, Some(imag)).unwrap();
+        let packed = parse::NumericData::UInt8(vec![1, 2, 3]);
+        writer.write_array("packed", packed, None).unwrap();
+        // A mapped page is aligned for all numeric types
+        let mut mmap = memmap2::MmapMut::map_anon(file.len()).unwrap();
+        mmap.copy_from_slice(&file);
+
+        let view = MatFileView::new(&mmap).unwrap();
+        let mat_file = MatFile::parse(file.as_slice()).unwrap();
+        assert_eq!(view.variables().len(), 2);
+        let z = view.numeric_data("z").unwrap().unwrap();
+        assert_eq!(z.is_borrowed(), cfg!(target_endian = "little"));
+        let expected = mat_file.find_by_name("z").map(|a| a.data().clone());
+        assert_eq!(
+            format!("{:?}", Some(ArrayData::Numeric(z.into_owned()))),
+            format!("{:?}", expected)
+        );
+        // Stored as 8 bit integers, so the values have to be converted
+        let packed = view.numeric_data("packed").unwrap().unwrap();
+        assert!(!packed.is_borrowed());
+        assert!(
+            matches!(&packed, NumericDataView::Double { real, imag: None } if real[..] == [1.0, 2.0, 3.0])
+        );
+        assert_eq!(
+            format!("{:?}", view.load("packed").unwrap().as_ref()),
+            format!("{:?}", mat_file.find_by_name("packed"))
+        );
+        assert!(view.numeric_data("missing").unwrap().is_none());
+
+        // Compressed variables are always copied
+        let data = include_bytes!("../tests/double.mat");
+        let view = MatFileView::new(data).unwrap();
+        let a = view.numeric_data("A").unwrap().unwrap();
+        assert!(!a.is_borrowed());
+        let mat_file = MatFile::parse(data.as_ref()).unwrap();
+        let expected = mat_file.find_by_name("A").map(|a| a.data().clone());
+        assert_eq!(
+            format!("{:?}", Some(ArrayData::Numeric(a.into_owned()))),
+            format!("{:?}", expected)
+        );
+        let data = include_bytes!("../tests/cell.mat");
+        let view = MatFileView::new(data).unwrap();
+        let name = view.variables()[0].name().to_owned();
+        assert!(view.numeric_data(&name).unwrap().is_none());
+
+        #[cfg(feature = "mmap")]
+        {
+            let file = std::fs::File::open("tests/small_matrix.mat").unwrap();
+            let view = unsafe { MatFileView::map(&file) }.unwrap();
+            let data = include_bytes!("../tests/small_matrix.mat");
+            let mat_file = MatFile::parse(data.as_ref()).unwrap();
+            assert_eq!(
+                format!("{:?}", view.load("abcde").unwrap().as_ref()),
+                format!("{:?}", mat_file.find_by_name("abcde"))
+            );
+        }
+    }
+
+    #[test]
+    fn many_variables() {
+        let mut data = Vec::new();
+        let mut writer = write::MatFileWriter::new(&mut data).unwrap();
+        for i in 0..20 {
+            let real = parse::NumericData::Int32(vec![i; i as usize]);
+            writer.write_array(&format!("x{}", i), real, None).unwrap();
+        }
+        // A compressed variable
+        data.extend(&include_bytes!("../tests/double.mat")[128..]);
+        let mat_file = MatFile::parse(data.as_slice()).unwrap();
+        let names: Vec<&str> = mat_file.arrays().iter().map(|a| a.name()).collect();
+        let mut expected: Vec<String> = (0..20).map(|i| format!("x{}", i)).collect();
+        expected.push("A".to_owned());
+        assert_eq!(names, expected);
+
+        // The first error is reported
+        use std::convert::TryInto;
+        let mut offset = 128;
+        for _ in 0..5 {
+            let size = u32::from_ne_bytes(data[offset + 4..offset + 8].try_into().unwrap());
+            offset += 8 + size as usize;
+        }
+        // The tag of the real part of x5
+        let offset = offset + 56;
+        data[offset] = 99;
+        data.pop();
+        match MatFile::parse(data.as_slice()) {
+            Err(Error::ParseError(err)) => {
+                assert_eq!(err.kind(), &ParseErrorKind::BadTagType(99));
+                assert_eq!(err.offset(), offset);
+                assert_eq!(err.variable(), Some("x5"));
+            }
+            other => panic!("unexpected result {:?}", other.map(|_| ())),
+        }
+    }
+
+    #[cfg(feature = "tokio")]
+    #[test]
+    fn async_io() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        runtime.block_on(async {
+            let data = include_bytes!("../tests/struct.mat");
+            let mat_file = MatFile::parse_async(data.as_ref()).await.unwrap();
+            let expected = MatFile::parse(data.as_ref()).unwrap();
+            assert_eq!(
+                format!("{:?}", mat_file.arrays()),
+                format!("{:?}", expected.arrays())
+            );
+            let options = ParseOptions::new().strict(true);
+            let data = include_bytes!("../tests/v73.mat");
+            assert!(matches!(
+                MatFile::parse_async_with(data.as_ref(), &options).await,
+                Err(Error::UnsupportedVariable(_))
+            ));
+
+            let mut written = Vec::new();
+            let mut writer = write::AsyncMatFileWriter::new(&mut written).await.unwrap();
+            let real = parse::NumericData::Int32(vec![1, 2, 3]);
+            writer.write_array("x", real, None).await.unwrap();
+            writer
+                .write_logical_array("b", &[2, 1], &[true, false])
+                .await
+                .unwrap();
+            let mut expected = Vec::new();
+            let mut writer = write::MatFileWriter::new(&mut expected).unwrap();
+            let real = parse::NumericData::Int32(vec![1, 2, 3]);
+            writer.write_array("x", real, None).unwrap();
+            writer
+                .write_logical_array("b", &[2, 1], &[true, false])
+                .unwrap();
+            assert_eq!(written, expected);
+        });
+    }
+
+    #[test]
+    fn file_versions() {
+        let versions = [
+            (include_bytes!("../tests/v4.mat").as_ref(), FileVersion::V4),
+            (
+                include_bytes!("../tests/v4_be.mat").as_ref(),
+                FileVersion::V4,
+            ),
+            (
+                include_bytes!("../tests/small_matrix.mat").as_ref(),
+                FileVersion::V6,
+            ),
+            (
+                include_bytes!("../tests/double.mat").as_ref(),
+                FileVersion::V7,
+            ),
+            (
+                include_bytes!("../tests/v73.mat").as_ref(),
+                FileVersion::V73,
+            ),
+            (b"Not a mat file at all".as_ref(), FileVersion::NotMatFile),
+            (b"".as_ref(), FileVersion::NotMatFile),
+        ];
+        for (data, version) in versions.iter() {
+            assert_eq!(MatFile::detect_version(data), *version);
+        }
+        assert!(matches!(
+            MatFile::parse(b"Not a mat file at all".as_ref()),
+            Err(Error::UnsupportedFormat(FileVersion::NotMatFile))
+        ));
+    }
 }
@@ -0,0 +1,107 @@
+use crate::Array;
+
+/// A `containers.Map` object.
+///
+/// The keys of a map are either character vectors or real numeric scalars,
+/// depending on its key type. The values can be arrays of any kind unless the
+/// value type of the map restricts them.
+///
+/// ```rust
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let file = std::fs::File::open("tests/categorical.mat")?;
+/// let mat_file = matfile::MatFile::parse(file)?;
+/// if let Some(matfile::ArrayData::Map(map)) = mat_file.find_by_name("lookup").map(|a| a.data()) {
+///     println!("Map from {} to {}", map.key_type(), map.value_type());
+///     for (key, value) in map.iter() {
+///         println!("{:?} => {:?}", key, value.data());
+///     }
+///     let a = map.get("a");
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct ContainersMap {
+    key_type: String,
+    value_type: String,
+    keys: Vec<MapKey>,
+    values: Vec<Array>,
+}
+
+/// The key of an entry of a `containers.Map`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum MapKey {
+    /// A character vector key
+    Char(String),
+    /// A numeric key, converted to `f64` regardless of the key type
+    Numeric(f64),
+}
+
+impl ContainersMap {
+    pub(crate) fn new(
+        key_type: String,
+        value_type: String,
+        keys: Vec<MapKey>,
+        values: Vec<Array>,
+    ) -> Self {
+        ContainersMap {
+            key_type,
+            value_type,
+            keys,
+            values,
+        }
+    }
+
+    /// The key type, such as "char" or "double".
+    pub fn key_type(&self) -> &str {
+        &self.key_type
+    }
+
+    /// The value type, such as "any" or "double".
+    pub fn value_type(&self) -> &str {
+        &self.value_type
+    }
+
+    /// The number of entries.
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    /// Whether this map has no entries at all.
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    /// The keys of all entries in sorted order, just like Matlab's `keys`
+    /// function returns them.
+    pub fn keys(&self) -> &[MapKey] {
+        &self.keys
+    }
+
+    /// The values of all entries in the order of their keys.
+    pub fn values(&self) -> &[Array] {
+        &self.values
+    }
+
+    /// Returns the value for a character vector key.
+    pub fn get(&self, key: &str) -> Option<&Array> {
+        self.find(|k| matches!(k, MapKey::Char(k) if k == key))
+    }
+
+    /// Returns the value for a numeric key.
+    pub fn get_numeric(&self, key: f64) -> Option<&Array> {
+        self.find(|k| matches!(k, MapKey::Numeric(k) if *k == key))
+    }
+
+    /// Iterates over all entries as pairs of key and value.
+    pub fn iter(&self) -> impl Iterator<Item = (&MapKey, &Array)> {
+        self.keys.iter().zip(self.values.iter())
+    }
+
+    fn find<P: Fn(&MapKey) -> bool>(&self, predicate: P) -> Option<&Array> {
+        self.keys
+            .iter()
+            .position(predicate)
+            .map(|index| &self.values[index])
+    }
+}
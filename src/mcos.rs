@@ -0,0 +1,902 @@
+//! Objects of `classdef` classes (MCOS, the Matlab Class Object System).
+//!
+//! A variable holding such objects is stored as an opaque array whose
+//! content is merely a reference: a uint32 column vector consisting of the
+//! marker `0xDD000000`, the number of dimensions, the dimensions, one object
+//! id per object and finally the class id.
+//!
+//! The classes and the saved properties of all objects live in the subsystem
+//! data of the file, a 1x1 struct whose field `MCOS` holds an opaque array of
+//! class `FileWrapper__`. Its content is a cell array: the first cell contains
+//! binary metadata describing classes and objects, the second one is unused,
+//! and the remaining cells hold the property values the metadata refers to.
+//! The last of them is a cell array with a struct of default property values
+//! per class.
+//!
+//! Within the subsystem data references to other objects are not wrapped in
+//! opaque arrays but stored as plain uint32 arrays.
+
+use std::cell::RefCell;
+
+use crate::{
+    parse, Array, ArrayData, CategoricalArray, CharArray, ContainersMap, DatetimeArray,
+    DurationArray, Error, MapKey, NumericData, StringArray, Table,
+};
+
+const REFERENCE_MARKER: u32 = 0xDD00_0000;
+
+/// Limits the nesting of objects to guard against cyclic references in
+/// malformed files.
+const MAX_DEPTH: usize = 64;
+
+/// The size and content of resolved objects, `None` if their class is not
+/// supported.
+type Resolved = Result<Option<(Vec<usize>, ArrayData)>, Error>;
+
+/// The decoded subsystem data.
+#[derive(Debug, Default)]
+pub(crate) struct Subsystem {
+    /// Class names indexed by class id
+    class_names: Vec<String>,
+    /// Objects indexed by object id
+    objects: Vec<Object>,
+    /// The cells of the `FileWrapper__` array. Cells that are only needed
+    /// once are moved out when they are converted, see `consume`.
+    cells: Vec<RefCell<Option<parse::DataElement>>>,
+    /// Whether the cell of the same index can be moved out
+    consumable: Vec<bool>,
+    /// Structs holding the default property values, indexed by class id
+    defaults: Vec<parse::DataElement>,
+    is_little_endian: bool,
+}
+
+#[derive(Debug)]
+struct Object {
+    class_id: usize,
+    properties: Vec<Property>,
+}
+
+#[derive(Debug)]
+struct Property {
+    name: String,
+    value: PropertyValue,
+}
+
+#[derive(Debug)]
+enum PropertyValue {
+    /// A string from the names stored in the metadata
+    Name(String),
+    /// An index into the cells following the metadata
+    Cell(usize),
+    /// A boolean stored directly in the metadata
+    Boolean(bool),
+}
+
+/// A reference to one or more objects of the same class.
+#[derive(Debug)]
+struct Reference {
+    size: Vec<usize>,
+    object_ids: Vec<usize>,
+    class_id: usize,
+}
+
+/// Everything needed to resolve object references while converting data
+/// elements into arrays.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Context<'a> {
+    subsystem: &'a Subsystem,
+    /// Whether the data elements are stored within the subsystem data, where
+    /// references are plain uint32 arrays
+    in_subsystem: bool,
+    depth: usize,
+}
+
+impl Subsystem {
+    /// Decodes the subsystem data. Returns an empty subsystem if the data
+    /// does not contain any (valid) MCOS objects.
+    pub(crate) fn new(subsystem_data: Option<parse::DataElement>, is_little_endian: bool) -> Self {
+        subsystem_data
+            .and_then(|data| Subsystem::from_data_element(data, is_little_endian))
+            .unwrap_or_default()
+    }
+
+    fn from_data_element(data: parse::DataElement, is_little_endian: bool) -> Option<Self> {
+        let file_wrapper = match data {
+            parse::DataElement::StructureMatrix(_, _, _, field_names, values) => {
+                let index = field_names.iter().position(|name| name == "MCOS")?;
+                values.into_iter().nth(index)?
+            }
+            _ => return None,
+        };
+        let cells = match file_wrapper {
+            parse::DataElement::OpaqueMatrix(_, _, type_system, class_name, data)
+                if type_system == "MCOS" && class_name == "FileWrapper__" =>
+            {
+                match *data {
+                    parse::DataElement::CellMatrix(_, _, _, cells) => cells,
+                    _ => return None,
+                }
+            }
+            _ => return None,
+        };
+        let metadata = match cells.first()? {
+            parse::DataElement::NumericMatrix(_, _, _, parse::NumericData::UInt8(bytes), _) => {
+                Metadata {
+                    bytes,
+                    is_little_endian,
+                }
+            }
+            _ => return None,
+        };
+        let (class_names, objects) = metadata.decode()?;
+        // The default values are stored in one of the trailing cells
+        let num_classes = class_names.len();
+        let defaults = cells
+            .iter()
+            .rev()
+            .take(3)
+            .find_map(|cell| match cell {
+                parse::DataElement::CellMatrix(_, _, _, defaults)
+                    if defaults.len() == num_classes
+                        && defaults.iter().all(|default| {
+                            matches!(
+                                default,
+                                parse::DataElement::StructureMatrix(_, dims, ..) if dims == &[1, 1]
+                            )
+                        }) =>
+                {
+                    Some(defaults.clone())
+                }
+                _ => None,
+            })
+            .unwrap_or_default();
+        Some(Subsystem {
+            class_names,
+            objects,
+            cells: cells
+                .into_iter()
+                .map(|cell| RefCell::new(Some(cell)))
+                .collect(),
+            consumable: Vec::new(),
+            defaults,
+            is_little_endian,
+        })
+    }
+
+    /// Lets the conversion of the given variables move cells out of the
+    /// subsystem data instead of copying them, if no other object can need
+    /// them. Each of the variables must be converted at most once afterwards.
+    pub(crate) fn consume(&mut self, variables: &[parse::DataElement]) {
+        // Objects referenced by the variables or by the class defaults, which
+        // are used for any number of objects
+        let mut references = vec![0; self.objects.len()];
+        let add = |references: &mut Vec<u8>, id: usize, count: u8| {
+            if let Some(references) = references.get_mut(id) {
+                *references = references.saturating_add(count).min(2);
+            }
+        };
+        for variable in variables {
+            object_references(variable, false, &mut |id| add(&mut references, id, 1));
+        }
+        for default in &self.defaults {
+            object_references(default, true, &mut |id| add(&mut references, id, 2));
+        }
+        // Objects whose properties refer to each object
+        let mut parents = vec![Vec::new(); self.objects.len()];
+        for (parent, index) in self.cell_properties() {
+            let cell = match self.cells.get(index) {
+                Some(cell) => cell.borrow(),
+                None => continue,
+            };
+            if let Some(cell) = &*cell {
+                object_references(cell, true, &mut |id| {
+                    if let Some(parents) = parents.get_mut(id) {
+                        parents.push(parent);
+                    }
+                });
+            }
+        }
+        let mut uses = vec![None; self.objects.len()];
+        let mut cell_uses = vec![0u8; self.cells.len()];
+        for (object_id, index) in self.cell_properties() {
+            let count = decode_count(object_id, &references, &parents, &mut uses, 0);
+            if let Some(cell_uses) = cell_uses.get_mut(index) {
+                *cell_uses = cell_uses.saturating_add(count).min(2);
+            }
+        }
+        self.consumable = cell_uses.into_iter().map(|uses| uses == 1).collect();
+    }
+
+    /// The object id and the cell index of all properties stored in cells.
+    fn cell_properties(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.objects
+            .iter()
+            .enumerate()
+            .flat_map(|(object_id, object)| {
+                object
+                    .properties
+                    .iter()
+                    .filter_map(move |property| match property.value {
+                        PropertyValue::Cell(index) => Some((object_id, index + 2)),
+                        _ => None,
+                    })
+            })
+    }
+
+    /// Returns a cell of the `FileWrapper__` array, which is moved out if it
+    /// is consumable.
+    fn cell(&self, index: usize) -> Option<parse::DataElement> {
+        let cell = self.cells.get(index)?;
+        if self.consumable.get(index) == Some(&true) {
+            cell.borrow_mut().take()
+        } else {
+            cell.borrow().clone()
+        }
+    }
+}
+
+/// How often an object is converted, given how often it is referenced
+/// directly and by which objects. Counts saturate at 2, meaning "more than
+/// once", which cyclic references end up with as well.
+fn decode_count(
+    object_id: usize,
+    references: &[u8],
+    parents: &[Vec<usize>],
+    uses: &mut [Option<u8>],
+    depth: usize,
+) -> u8 {
+    if let Some(count) = uses[object_id] {
+        return count;
+    }
+    if depth >= MAX_DEPTH {
+        return 2;
+    }
+    // Marks the object as being counted, in case it refers to itself
+    uses[object_id] = Some(2);
+    let mut count = references[object_id];
+    for &parent in &parents[object_id] {
+        if count >= 2 {
+            break;
+        }
+        count += decode_count(parent, references, parents, uses, depth + 1);
+    }
+    let count = count.min(2);
+    uses[object_id] = Some(count);
+    count
+}
+
+/// Calls `f` with the id of each object a data element refers to.
+fn object_references(data: &parse::DataElement, in_subsystem: bool, f: &mut dyn FnMut(usize)) {
+    let reference = match data {
+        parse::DataElement::NumericMatrix(flags, _, _, parse::NumericData::UInt32(data), _)
+            if in_subsystem && flags.class == parse::ArrayType::UInt32 =>
+        {
+            Reference::new(data)
+        }
+        parse::DataElement::OpaqueMatrix(_, _, type_system, _, data) if type_system == "MCOS" => {
+            match &**data {
+                parse::DataElement::NumericMatrix(_, _, _, parse::NumericData::UInt32(data), _) => {
+                    Reference::new(data)
+                }
+                _ => None,
+            }
+        }
+        parse::DataElement::CellMatrix(_, _, _, elements)
+        | parse::DataElement::StructureMatrix(_, _, _, _, elements)
+        | parse::DataElement::ObjectMatrix(_, _, _, _, _, elements) => {
+            for element in elements {
+                object_references(element, in_subsystem, f);
+            }
+            None
+        }
+        parse::DataElement::FunctionMatrix(_, _, _, data) => {
+            object_references(data, in_subsystem, f);
+            None
+        }
+        _ => None,
+    };
+    for &object_id in reference.iter().flat_map(|reference| &reference.object_ids) {
+        f(object_id);
+    }
+}
+
+/// Reads the binary metadata stored in the first cell of the
+/// `FileWrapper__` array.
+///
+/// The metadata starts with a version number, the number of names and eight
+/// offsets of the regions following the names:
+///
+/// 0. class ids: namespace and class name indices, 4 uint32 per class
+/// 1. properties of objects with a custom `saveobj` method
+/// 2. object ids: class id, two unknown values, index into region 1, index
+///    into region 3 and the dependency id, 6 uint32 per object
+/// 3. properties of all other objects
+/// 4. dynamic properties
+///
+/// Each property region consists of one block per object: the number of
+/// properties followed by name index, type and value of each property, padded
+/// to 8 bytes. The first entry of every region is a dummy entry for id 0.
+/// Name indices are one-based.
+struct Metadata<'a> {
+    bytes: &'a [u8],
+    is_little_endian: bool,
+}
+
+impl<'a> Metadata<'a> {
+    fn u32(&self, offset: usize) -> Option<u32> {
+        let bytes = self.bytes.get(offset..offset + 4)?;
+        let mut word = [0; 4];
+        word.copy_from_slice(bytes);
+        Some(if self.is_little_endian {
+            u32::from_le_bytes(word)
+        } else {
+            u32::from_be_bytes(word)
+        })
+    }
+
+    fn decode(&self) -> Option<(Vec<String>, Vec<Object>)> {
+        let num_names = self.u32(4)? as usize;
+        let offsets = (0..8)
+            .map(|index| self.u32(8 + 4 * index).map(|offset| offset as usize))
+            .collect::<Option<Vec<_>>>()?;
+        let names: Vec<String> = self
+            .bytes
+            .get(40..offsets[0])?
+            .split(|&b| b == 0)
+            .take(num_names)
+            .map(|name| String::from_utf8_lossy(name).into_owned())
+            .collect();
+        let name =
+            |index: u32| -> Option<String> { names.get((index as usize).checked_sub(1)?).cloned() };
+
+        let class_names = (offsets[0]..offsets[1])
+            .step_by(16)
+            .map(|offset| {
+                let namespace = self.u32(offset)?;
+                let class_name = self.u32(offset + 4)?;
+                if class_name == 0 {
+                    return Some(String::new());
+                }
+                Some(match name(namespace) {
+                    Some(namespace) => format!("{}.{}", namespace, name(class_name)?),
+                    None => name(class_name)?,
+                })
+            })
+            .collect::<Option<Vec<_>>>()?;
+
+        let saveobj_blocks = self.property_blocks(offsets[1], offsets[2])?;
+        let blocks = self.property_blocks(offsets[3], offsets[4])?;
+        let objects = (offsets[2]..offsets[3])
+            .step_by(24)
+            .map(|offset| {
+                let class_id = self.u32(offset)? as usize;
+                let saveobj_id = self.u32(offset + 12)? as usize;
+                let id = self.u32(offset + 16)? as usize;
+                let block = if saveobj_id != 0 {
+                    saveobj_blocks.get(saveobj_id)?
+                } else {
+                    blocks.get(id)?
+                };
+                let properties = block
+                    .iter()
+                    .map(|&(name_index, kind, value)| {
+                        let value = match kind {
+                            0 => PropertyValue::Name(name(value)?),
+                            1 => PropertyValue::Cell(value as usize),
+                            _ => PropertyValue::Boolean(value != 0),
+                        };
+                        Some(Property {
+                            name: name(name_index)?,
+                            value,
+                        })
+                    })
+                    .collect::<Option<Vec<_>>>()?;
+                Some(Object {
+                    class_id,
+                    properties,
+                })
+            })
+            .collect::<Option<Vec<_>>>()?;
+        Some((class_names, objects))
+    }
+
+    /// Splits a property region into blocks of (name index, type, value)
+    /// triples.
+    #[allow(clippy::type_complexity)]
+    fn property_blocks(&self, start: usize, end: usize) -> Option<Vec<Vec<(u32, u32, u32)>>> {
+        let mut blocks = Vec::new();
+        let mut offset = start;
+        while offset < end {
+            let num_properties = self.u32(offset)? as usize;
+            let block = (0..num_properties)
+                .map(|index| {
+                    let offset = offset + 4 + 12 * index;
+                    Some((
+                        self.u32(offset)?,
+                        self.u32(offset + 4)?,
+                        self.u32(offset + 8)?,
+                    ))
+                })
+                .collect::<Option<Vec<_>>>()?;
+            blocks.push(block);
+            offset += padded(4 + 12 * num_properties);
+        }
+        Some(blocks)
+    }
+}
+
+/// The size of a block in the metadata including the padding to 8 bytes.
+fn padded(size: usize) -> usize {
+    size + (8 - size % 8) % 8
+}
+
+impl<'a> Context<'a> {
+    pub(crate) fn new(subsystem: &'a Subsystem) -> Self {
+        Context {
+            subsystem,
+            in_subsystem: false,
+            depth: 0,
+        }
+    }
+
+    /// Checks whether a numeric array stored within the subsystem data is a
+    /// reference to objects and resolves it if so.
+    pub(crate) fn resolve_embedded(
+        &self,
+        flags: &parse::ArrayFlags,
+        data: &parse::NumericData,
+    ) -> Option<Resolved> {
+        if !self.in_subsystem || flags.class != parse::ArrayType::UInt32 {
+            return None;
+        }
+        match data {
+            parse::NumericData::UInt32(data) => Reference::new(data).map(|r| self.resolve(&r)),
+            _ => None,
+        }
+    }
+
+    /// Resolves the content of an opaque array. Returns `None` if it is not
+    /// an object of a supported class.
+    pub(crate) fn resolve_opaque(&self, type_system: &str, data: &parse::DataElement) -> Resolved {
+        match (type_system, data) {
+            (
+                "MCOS",
+                parse::DataElement::NumericMatrix(_, _, _, parse::NumericData::UInt32(data), _),
+            ) => match Reference::new(data) {
+                Some(reference) => self.resolve(&reference),
+                None => Ok(None),
+            },
+            _ => Ok(None),
+        }
+    }
+
+    fn resolve(&self, reference: &Reference) -> Resolved {
+        if self.depth >= MAX_DEPTH {
+            return Ok(None);
+        }
+        let context = Context {
+            subsystem: self.subsystem,
+            in_subsystem: true,
+            depth: self.depth + 1,
+        };
+        let class_name = match self.subsystem.class_names.get(reference.class_id) {
+            Some(class_name) => class_name.as_str(),
+            None => return Ok(None),
+        };
+        // Arrays of value objects such as strings or datetimes are stored as
+        // a single object holding all values, along with their size
+        let object_id = match reference.object_ids.as_slice() {
+            [object_id] => *object_id,
+            _ => return Ok(None),
+        };
+        let object = match class_name {
+            "string" => context
+                .property(object_id, "any")?
+                .and_then(|any| match any.data {
+                    ArrayData::Numeric(NumericData::UInt64 { real, .. }) => {
+                        StringArray::from_encoded(&real, self.subsystem.is_little_endian)
+                    }
+                    _ => None,
+                })
+                .map(|strings| (strings.size().clone(), ArrayData::String(strings))),
+            "datetime" => {
+                let time_zone = text(context.property(object_id, "tz")?);
+                let format = text(context.property(object_id, "fmt")?);
+                // The imaginary part holds additional sub-millisecond precision
+                context
+                    .property(object_id, "data")?
+                    .and_then(|data| match data.data {
+                        ArrayData::Numeric(NumericData::Double { real, imag }) => {
+                            let millis = match imag {
+                                Some(imag) => real.iter().zip(imag).map(|(r, i)| r + i).collect(),
+                                None => real,
+                            };
+                            Some(DatetimeArray::new(data.size, millis, time_zone, format))
+                        }
+                        _ => None,
+                    })
+                    .map(|datetimes| (datetimes.size().clone(), ArrayData::Datetime(datetimes)))
+            }
+            "duration" => {
+                let format = text(context.property(object_id, "fmt")?);
+                context
+                    .property(object_id, "millis")?
+                    .and_then(|millis| match millis.data {
+                        ArrayData::Numeric(NumericData::Double { real, .. }) => {
+                            Some(DurationArray::new(millis.size, real, format))
+                        }
+                        _ => None,
+                    })
+                    .map(|durations| (durations.size().clone(), ArrayData::Duration(durations)))
+            }
+            "table" | "timetable" => {
+                // Timetables store their properties in a struct
+                let mut any = match context.property(object_id, "any")? {
+                    Some(Array {
+                        data: ArrayData::Struct(any),
+                        ..
+                    }) => Some(any),
+                    _ => None,
+                };
+                let mut property = |names: &[&str]| -> Result<Option<Array>, Error> {
+                    for name in names {
+                        let value = match &mut any {
+                            Some(any) => any.remove_field(name),
+                            None => context.property(object_id, name)?,
+                        };
+                        if value.is_some() {
+                            return Ok(value);
+                        }
+                    }
+                    Ok(None)
+                };
+                match (property(&["data"])?, property(&["varnames", "varNames"])?) {
+                    (Some(data), Some(variable_names)) => Table::from_properties(
+                        class_name == "timetable",
+                        data,
+                        variable_names,
+                        property(&["rownames", "rowNames"])?,
+                        property(&["rowTimes"])?,
+                        property(&["nrows", "numRows"])?,
+                    )
+                    .map(|table| {
+                        let size = vec![table.num_rows(), table.num_columns()];
+                        (size, ArrayData::Table(table))
+                    }),
+                    _ => None,
+                }
+            }
+            "categorical" => {
+                let categories = context
+                    .property(object_id, "categoryNames")?
+                    .and_then(|names| text_cells(&names));
+                let is_ordinal = flag(context.property(object_id, "isOrdinal")?);
+                let is_protected = flag(context.property(object_id, "isProtected")?);
+                match (context.property(object_id, "codes")?, categories) {
+                    (Some(array), Some(categories)) => array.numeric_data().map(|data| {
+                        let codes = numbers(data).into_iter().map(|code| code as u32).collect();
+                        let categorical = CategoricalArray::new(
+                            array.size().clone(),
+                            codes,
+                            categories,
+                            is_ordinal,
+                            is_protected,
+                        );
+                        (
+                            categorical.size().clone(),
+                            ArrayData::Categorical(categorical),
+                        )
+                    }),
+                    _ => None,
+                }
+            }
+            "containers.Map" => match context.property(object_id, "serialization")? {
+                Some(Array {
+                    data: ArrayData::Struct(serialization),
+                    ..
+                }) => {
+                    let field = |name| serialization.get_field(name).map(|field| field.data());
+                    let key_type = text(serialization.get_field("keyType").cloned());
+                    let value_type = text(serialization.get_field("valueType").cloned());
+                    match (field("keys"), field("values")) {
+                        (Some(ArrayData::Cell(keys)), Some(ArrayData::Cell(values)))
+                            if keys.len() == values.len() =>
+                        {
+                            keys.iter()
+                                .map(|key| match key.data() {
+                                    ArrayData::Char(chars) => Some(MapKey::Char(chars.to_string())),
+                                    ArrayData::Numeric(data) => {
+                                        numbers(data).first().map(|&key| MapKey::Numeric(key))
+                                    }
+                                    _ => None,
+                                })
+                                .collect::<Option<Vec<_>>>()
+                                .map(|keys| {
+                                    let map = ContainersMap::new(
+                                        key_type.unwrap_or_else(|| "char".to_owned()),
+                                        value_type.unwrap_or_else(|| "any".to_owned()),
+                                        keys,
+                                        values.iter().cloned().collect(),
+                                    );
+                                    (vec![1, 1], ArrayData::Map(map))
+                                })
+                        }
+                        _ => None,
+                    }
+                }
+                _ => None,
+            },
+            _ => None,
+        };
+        Ok(object)
+    }
+
+    /// Returns the value of a property of an object, falling back to the
+    /// default value of its class if the property was not saved.
+    fn property(&self, object_id: usize, name: &str) -> Result<Option<Array>, Error> {
+        let object = match self.subsystem.objects.get(object_id) {
+            Some(object) => object,
+            None => return Ok(None),
+        };
+        let property = object
+            .properties
+            .iter()
+            .find(|property| property.name == name);
+        let array = match property.map(|property| &property.value) {
+            Some(PropertyValue::Name(value)) => {
+                let data: Vec<u16> = value.encode_utf16().collect();
+                let size = vec![1, data.len()];
+                Some(Array {
+                    name: String::new(),
+                    size: size.clone(),
+                    data: ArrayData::Char(CharArray::new(size, data)),
+                })
+            }
+            Some(&PropertyValue::Boolean(value)) => Some(Array {
+                name: String::new(),
+                size: vec![1, 1],
+                data: ArrayData::Logical(vec![value]),
+            }),
+            Some(&PropertyValue::Cell(index)) => match self.subsystem.cell(index + 2) {
+                Some(cell) => Array::from_data_element(cell, *self)?,
+                None => None,
+            },
+            None => match self.subsystem.defaults.get(object.class_id) {
+                Some(parse::DataElement::StructureMatrix(_, _, _, field_names, values)) => {
+                    let index = field_names.iter().position(|field_name| field_name == name);
+                    match index.and_then(|index| values.get(index)) {
+                        Some(value) => Array::from_data_element(value.clone(), *self)?,
+                        None => None,
+                    }
+                }
+                _ => None,
+            },
+        };
+        Ok(array.map(|array| array.with_name(name)))
+    }
+}
+
+impl Reference {
+    fn new(data: &[u32]) -> Option<Self> {
+        if data.len() < 2 || data[0] != REFERENCE_MARKER {
+            return None;
+        }
+        let ids_start = 2usize.checked_add(data[1] as usize)?;
+        let size: Vec<usize> = data
+            .get(2..ids_start)?
+            .iter()
+            .map(|&d| d as usize)
+            .collect();
+        let num_objects = size.iter().try_fold(1usize, |n, &d| n.checked_mul(d))?;
+        let class_id_index = ids_start.checked_add(num_objects)?;
+        let object_ids = data.get(ids_start..class_id_index)?;
+        if data.len() != class_id_index + 1 {
+            return None;
+        }
+        Some(Reference {
+            object_ids: object_ids.iter().map(|&id| id as usize).collect(),
+            size,
+            class_id: data[class_id_index] as usize,
+        })
+    }
+}
+
+/// Whether a data element contains opaque arrays, which need the subsystem
+/// data to be resolved.
+pub(crate) fn needs_subsystem(data: &parse::DataElement) -> bool {
+    match data {
+        parse::DataElement::OpaqueMatrix(..) => true,
+        parse::DataElement::CellMatrix(_, _, _, elements)
+        | parse::DataElement::StructureMatrix(_, _, _, _, elements)
+        | parse::DataElement::ObjectMatrix(_, _, _, _, _, elements) => {
+            elements.iter().any(needs_subsystem)
+        }
+        parse::DataElement::FunctionMatrix(_, _, _, data) => needs_subsystem(data),
+        _ => false,
+    }
+}
+
+/// The size of an opaque array, as far as it can be told without resolving
+/// the objects.
+pub(crate) fn opaque_size(type_system: &str, data: &parse::DataElement) -> Option<Vec<usize>> {
+    match (type_system, data) {
+        (
+            "MCOS",
+            parse::DataElement::NumericMatrix(_, _, _, parse::NumericData::UInt32(data), _),
+        ) => Reference::new(data).map(|reference| reference.size),
+        _ => None,
+    }
+}
+
+/// The content of a text property, `None` if it is empty.
+fn text(array: Option<Array>) -> Option<String> {
+    match array?.data {
+        ArrayData::Char(chars) if !chars.code_units().is_empty() => Some(chars.to_string()),
+        _ => None,
+    }
+}
+
+/// Reads a cell array of character arrays. An empty array stands for no
+/// text at all.
+pub(crate) fn text_cells(array: &Array) -> Option<Vec<String>> {
+    match array.data() {
+        ArrayData::Cell(cells) => cells
+            .iter()
+            .map(|cell| match cell.data() {
+                ArrayData::Char(chars) => Some(chars.to_string()),
+                _ => None,
+            })
+            .collect(),
+        ArrayData::Numeric(_) if array.size().iter().product::<usize>() == 0 => Some(Vec::new()),
+        _ => None,
+    }
+}
+
+/// The value of a boolean property, `false` if it is missing.
+fn flag(array: Option<Array>) -> bool {
+    match array.map(|array| array.data) {
+        Some(ArrayData::Logical(values)) => values.first().copied().unwrap_or(false),
+        Some(ArrayData::Numeric(data)) => numbers(&data).first().is_some_and(|&v| v != 0.0),
+        _ => false,
+    }
+}
+
+/// Converts the real part of numeric data into floating point numbers.
+fn numbers(data: &NumericData) -> Vec<f64> {
+    match data {
+        NumericData::Int8 { real, .. } => real.iter().map(|&v| v as f64).collect(),
+        NumericData::UInt8 { real, .. } => real.iter().map(|&v| v as f64).collect(),
+        NumericData::Int16 { real, .. } => real.iter().map(|&v| v as f64).collect(),
+        NumericData::UInt16 { real, .. } => real.iter().map(|&v| v as f64).collect(),
+        NumericData::Int32 { real, .. } => real.iter().map(|&v| v as f64).collect(),
+        NumericData::UInt32 { real, .. } => real.iter().map(|&v| v as f64).collect(),
+        NumericData::Int64 { real, .. } => real.iter().map(|&v| v as f64).collect(),
+        NumericData::UInt64 { real, .. } => real.iter().map(|&v| v as f64).collect(),
+        NumericData::Single { real, .. } => real.iter().map(|&v| v as f64).collect(),
+        NumericData::Double { real, .. } => real.clone(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn padding() {
+        assert_eq!(padded(0), 0);
+        assert_eq!(padded(4), 8);
+        assert_eq!(padded(5), 8);
+        assert_eq!(padded(8), 8);
+        assert_eq!(padded(10), 16);
+        assert_eq!(padded(16), 16);
+    }
+
+    #[test]
+    fn empty_class_defaults() {
+        // A struct of default values without any elements
+        let defaults = parse::DataElement::StructureMatrix(
+            parse::ArrayFlags {
+                complex: false,
+                global: false,
+                logical: false,
+                class: parse::ArrayType::Struct,
+                nzmax: 0,
+            },
+            vec![0, 0],
+            String::new(),
+            vec!["fmt".to_owned()],
+            Vec::new(),
+        );
+        let subsystem = Subsystem {
+            class_names: vec!["duration".to_owned()],
+            objects: vec![Object {
+                class_id: 0,
+                properties: Vec::new(),
+            }],
+            defaults: vec![defaults],
+            ..Subsystem::default()
+        };
+        let context = Context::new(&subsystem);
+        assert!(matches!(context.property(0, "fmt"), Ok(None)));
+    }
+
+    #[test]
+    fn consumed_cells() {
+        let flags = |class| parse::ArrayFlags {
+            complex: false,
+            global: false,
+            logical: false,
+            class,
+            nzmax: 0,
+        };
+        let numeric = |class, dims, data| {
+            parse::DataElement::NumericMatrix(flags(class), dims, String::new(), data, None)
+        };
+        // A duration whose milliseconds are stored in the third cell, and a
+        // variable referring to it
+        let subsystem = || Subsystem {
+            class_names: vec!["duration".to_owned()],
+            objects: vec![
+                Object {
+                    class_id: 0,
+                    properties: Vec::new(),
+                },
+                Object {
+                    class_id: 0,
+                    properties: vec![Property {
+                        name: "millis".to_owned(),
+                        value: PropertyValue::Cell(0),
+                    }],
+                },
+            ],
+            cells: (0..3)
+                .map(|_| {
+                    RefCell::new(Some(numeric(
+                        parse::ArrayType::Double,
+                        vec![1, 1],
+                        parse::NumericData::Double(vec![1500.0]),
+                    )))
+                })
+                .collect(),
+            ..Subsystem::default()
+        };
+        let variable = parse::DataElement::OpaqueMatrix(
+            flags(parse::ArrayType::Opaque),
+            "d".to_owned(),
+            "MCOS".to_owned(),
+            "duration".to_owned(),
+            Box::new(numeric(
+                parse::ArrayType::UInt32,
+                vec![6, 1],
+                parse::NumericData::UInt32(vec![REFERENCE_MARKER, 2, 1, 1, 1, 0]),
+            )),
+        );
+        let mut consumed = subsystem();
+        consumed.consume(std::slice::from_ref(&variable));
+        let context = Context::new(&consumed);
+        assert!(matches!(context.property(1, "millis"), Ok(Some(_))));
+        assert!(consumed.cells[2].borrow().is_none());
+        // Both variables need the cell
+        let mut shared = subsystem();
+        shared.consume(&[variable.clone(), variable]);
+        let context = Context::new(&shared);
+        assert!(matches!(context.property(1, "millis"), Ok(Some(_))));
+        assert!(matches!(context.property(1, "millis"), Ok(Some(_))));
+    }
+
+    #[test]
+    fn property_blocks() {
+        // Blocks with one (16 bytes) and two (28 + 4 bytes of padding)
+        // properties, followed by an empty block
+        let words: [u32; 16] = [1, 1, 1, 5, 2, 2, 0, 6, 3, 2, 1, 0, 0, 0, 0, 0];
+        let bytes: Vec<u8> = words.iter().flat_map(|w| w.to_le_bytes()).collect();
+        let metadata = Metadata {
+            bytes: &bytes,
+            is_little_endian: true,
+        };
+        assert_eq!(
+            metadata.property_blocks(0, 56),
+            Some(vec![vec![(1, 1, 5)], vec![(2, 0, 6), (3, 2, 1)], vec![]])
+        );
+    }
+}
@@ -80,11 +80,11 @@ macro_rules! dynamic_conversions {
         impl<'me> TryInto<nd::ArrayViewD<'me, $num>> for &'me crate::Array {
             type Error = Error;
             fn try_into(self) -> Result<nd::ArrayViewD<'me, $num>, Self::Error> {
-                match self.data() {
-                    crate::NumericData::$variant {
+                match self.numeric_data() {
+                    Some(crate::NumericData::$variant {
                         ref real,
                         imag: None,
-                    } => {
+                    }) => {
                         let dimension: nd::IxDyn = self.size().clone().into_dimension();
                         nd::ArrayView::from_shape(dimension.set_f(true), real)
                             .map_err(|_err| Error::ShapeError)
@@ -97,11 +97,11 @@ macro_rules! dynamic_conversions {
         impl TryInto<nd::ArrayD<$num>> for &crate::Array {
             type Error = Error;
             fn try_into(self) -> Result<nd::ArrayD<$num>, Self::Error> {
-                match self.data() {
-                    crate::NumericData::$variant {
+                match self.numeric_data() {
+                    Some(crate::NumericData::$variant {
                         ref real,
                         imag: None,
-                    } => {
+                    }) => {
                         let dimension: nd::IxDyn = self.size().clone().into_dimension();
                         nd::Array::from_shape_vec(dimension.set_f(true), real.clone())
                             .map_err(|_err| Error::ShapeError)
@@ -114,11 +114,11 @@ macro_rules! dynamic_conversions {
         impl TryInto<nd::ArrayD<Complex<$num>>> for &crate::Array {
             type Error = Error;
             fn try_into(self) -> Result<nd::ArrayD<Complex<$num>>, Self::Error> {
-                match self.data() {
-                    crate::NumericData::$variant {
+                match self.numeric_data() {
+                    Some(crate::NumericData::$variant {
                         ref real,
                         imag: Some(ref imag),
-                    } => {
+                    }) => {
                         let dimension: nd::IxDyn = self.size().clone().into_dimension();
                         let values = real
                             .iter()
@@ -150,11 +150,11 @@ macro_rules! static_conversions_n {
                 }
                 let mut shape = [0; $ndims];
                 shape.copy_from_slice(size);
-                match self.data() {
-                    crate::NumericData::$variant {
+                match self.numeric_data() {
+                    Some(crate::NumericData::$variant {
                         ref real,
                         imag: None,
-                    } => {
+                    }) => {
                         let dimension: nd::Dim<[nd::Ix; $ndims]> = shape.into_dimension();
                         nd::ArrayView::from_shape(dimension.set_f(true), real)
                             .map_err(|_err| Error::ShapeError)
@@ -173,11 +173,11 @@ macro_rules! static_conversions_n {
                 }
                 let mut shape = [0; $ndims];
                 shape.copy_from_slice(size);
-                match self.data() {
-                    crate::NumericData::$variant {
+                match self.numeric_data() {
+                    Some(crate::NumericData::$variant {
                         ref real,
                         imag: None,
-                    } => {
+                    }) => {
                         let dimension: nd::Dim<[nd::Ix; $ndims]> = shape.into_dimension();
                         nd::Array::from_shape_vec(dimension.set_f(true), real.clone())
                             .map_err(|_err| Error::ShapeError)
@@ -198,11 +198,11 @@ macro_rules! static_conversions_n {
                 }
                 let mut shape = [0; $ndims];
                 shape.copy_from_slice(size);
-                match self.data() {
-                    crate::NumericData::$variant {
+                match self.numeric_data() {
+                    Some(crate::NumericData::$variant {
                         ref real,
                         imag: Some(ref imag),
-                    } => {
+                    }) => {
                         let dimension: nd::Dim<[nd::Ix; $ndims]> = shape.into_dimension();
                         let values = real
                             .iter()
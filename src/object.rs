@@ -0,0 +1,73 @@
+use crate::{Array, Struct, StructArray};
+
+/// An array of objects of a legacy (pre-`classdef`) Matlab class.
+///
+/// Such objects are stored just like structure arrays with an additional
+/// class name. Their properties can be accessed like the fields of a struct.
+///
+/// ```rust
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let file = std::fs::File::open("tests/object.mat")?;
+/// let mat_file = matfile::MatFile::parse(file)?;
+/// if let Some(matfile::ArrayData::Object(object)) = mat_file.find_by_name("obj").map(|a| a.data()) {
+///     println!("Object of class {}", object.class_name());
+///     for field_name in object.field_names() {
+///         println!("{}: {:?}", field_name, object.get_field(field_name));
+///     }
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct ObjectArray {
+    class_name: String,
+    fields: StructArray,
+}
+
+impl ObjectArray {
+    pub(crate) fn new(class_name: String, fields: StructArray) -> Self {
+        ObjectArray { class_name, fields }
+    }
+
+    /// The name of the class of the objects.
+    pub fn class_name(&self) -> &str {
+        &self.class_name
+    }
+
+    /// The size of this object array, see `Array::size`.
+    pub fn size(&self) -> &Vec<usize> {
+        self.fields.size()
+    }
+
+    /// The number of objects in this object array.
+    pub fn len(&self) -> usize {
+        self.fields.len()
+    }
+
+    /// Whether this object array contains no objects at all.
+    pub fn is_empty(&self) -> bool {
+        self.fields.is_empty()
+    }
+
+    /// The names of the fields (properties) of the objects.
+    pub fn field_names(&self) -> &[String] {
+        self.fields.field_names()
+    }
+
+    /// Returns the value of a field of a 1x1 object array, see
+    /// `StructArray::get_field`.
+    pub fn get_field(&self, name: &str) -> Option<&Array> {
+        self.fields.get_field(name)
+    }
+
+    /// Returns the fields of the object at the given linear (column-major)
+    /// index.
+    pub fn get(&self, index: usize) -> Option<Struct<'_>> {
+        self.fields.get(index)
+    }
+
+    /// The fields of all objects as a structure array.
+    pub fn as_struct_array(&self) -> &StructArray {
+        &self.fields
+    }
+}
@@ -0,0 +1,173 @@
+use crate::Warning;
+use std::sync::Arc;
+
+type WarningCallback = Arc<dyn Fn(&Warning) + Send + Sync>;
+
+/// Options for parsing a .mat file with [`MatFile::parse_with`](crate::MatFile::parse_with).
+///
+/// # Example
+///
+/// ```rust
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let options = matfile::ParseOptions::new()
+///     .on_warning(|warning| eprintln!("Warning: {}", warning));
+/// let file = std::fs::File::open("tests/double.mat")?;
+/// let mat_file = matfile::MatFile::parse_with(file, &options)?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Default)]
+pub struct ParseOptions {
+    on_warning: Option<WarningCallback>,
+    strict: bool,
+    /// The variables to load, all of them if `None`
+    variables: Option<Vec<VariablePattern>>,
+}
+
+#[derive(Clone, Debug)]
+enum VariablePattern {
+    /// A name with `*` and `?` wildcards
+    Wildcard(String),
+    #[cfg(feature = "regex")]
+    Regex(regex::Regex),
+}
+
+impl VariablePattern {
+    fn matches(&self, name: &str) -> bool {
+        match self {
+            VariablePattern::Wildcard(pattern) => {
+                let pattern: Vec<char> = pattern.chars().collect();
+                let name: Vec<char> = name.chars().collect();
+                wildcard_match(&pattern, &name)
+            }
+            #[cfg(feature = "regex")]
+            VariablePattern::Regex(regex) => regex.is_match(name),
+        }
+    }
+}
+
+impl ParseOptions {
+    /// The default options.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets a function that is called for every [`Warning`] while parsing.
+    /// By default warnings are ignored.
+    pub fn on_warning<F: Fn(&Warning) + Send + Sync + 'static>(mut self, callback: F) -> Self {
+        self.on_warning = Some(Arc::new(callback));
+        self
+    }
+
+    /// In strict mode parsing fails with
+    /// [`Error::UnsupportedVariable`](crate::Error::UnsupportedVariable) when
+    /// a variable can't be loaded, instead of leaving it out. Off by default.
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// Only loads the variables with the given names, like Matlab's
+    /// `load(filename, 'x', 'y*')`. The names may contain the wildcards `*`
+    /// (any number of characters) and `?` (a single character). Can be
+    /// combined with further calls and with `variables_regex` (with the
+    /// `regex` feature), then all variables matching any of the patterns are
+    /// loaded. By default all variables are loaded.
+    ///
+    /// The other variables are skipped without decoding them and, where
+    /// possible, without decompressing them.
+    ///
+    /// ```rust
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let options = matfile::ParseOptions::new().variables(["pos", "vel*"]);
+    /// let file = std::fs::File::open("tests/double.mat")?;
+    /// let mat_file = matfile::MatFile::parse_with(file, &options)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn variables<I>(mut self, patterns: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: Into<String>,
+    {
+        self.variables.get_or_insert_with(Vec::new).extend(
+            patterns
+                .into_iter()
+                .map(|pattern| VariablePattern::Wildcard(pattern.into())),
+        );
+        self
+    }
+
+    /// Only loads the variables whose name matches the regular expression,
+    /// like Matlab's `load(filename, '-regexp', expression)`. The expression
+    /// matches if it's found anywhere in the name. See
+    /// [`variables`](ParseOptions::variables) for combining it with other
+    /// patterns.
+    #[cfg(feature = "regex")]
+    pub fn variables_regex(mut self, regex: regex::Regex) -> Self {
+        self.variables
+            .get_or_insert_with(Vec::new)
+            .push(VariablePattern::Regex(regex));
+        self
+    }
+
+    /// Whether the variable with the given name is to be loaded.
+    pub(crate) fn selects(&self, name: &str) -> bool {
+        match &self.variables {
+            Some(patterns) => patterns.iter().any(|pattern| pattern.matches(name)),
+            None => true,
+        }
+    }
+
+    pub(crate) fn is_strict(&self) -> bool {
+        self.strict
+    }
+
+    pub(crate) fn warn(&self, warning: Warning) {
+        #[cfg(feature = "log")]
+        log::warn!("{}", warning);
+        if let Some(on_warning) = &self.on_warning {
+            on_warning(&warning);
+        }
+    }
+}
+
+impl std::fmt::Debug for ParseOptions {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("ParseOptions")
+            .field("on_warning", &self.on_warning.is_some())
+            .field("strict", &self.strict)
+            .field("variables", &self.variables)
+            .finish()
+    }
+}
+
+/// Matches a name against a pattern with `*` and `?` wildcards.
+fn wildcard_match(pattern: &[char], name: &[char]) -> bool {
+    let (mut p, mut n) = (0, 0);
+    // The position of the last `*` in the pattern and the position in the
+    // name it was tried to match up to, to backtrack to if the rest fails
+    let mut star = None;
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, n));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match star {
+                // Let the `*` match one more character
+                Some((star_p, star_n)) => {
+                    star = Some((star_p, star_n + 1));
+                    p = star_p + 1;
+                    n = star_n + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
@@ -1,32 +1,102 @@
+use crate::{FileVersion, ParseErrorKind};
 use libflate::zlib::Decoder;
 use nom::branch::alt;
 use nom::bytes::complete::tag;
 use nom::bytes::complete::take;
 use nom::character::complete::char;
 use nom::combinator::{complete, cond, map, map_res, not, opt, peek, value};
-use nom::multi::{count, length_value, many0};
-use nom::number::complete::f32;
-use nom::number::complete::f64;
-use nom::number::complete::i16;
+use nom::multi::{count, many0};
 use nom::number::complete::i32;
-use nom::number::complete::i64;
-use nom::number::complete::i8;
 use nom::number::complete::u16;
 use nom::number::complete::u32;
-use nom::number::complete::u64;
-use nom::number::complete::u8;
 use nom::sequence::pair;
-use nom::{error_position, IResult};
 use num_traits::FromPrimitive;
+use std::convert::TryFrom;
 use std::io::Read;
 
 // https://www.mathworks.com/help/pdf_doc/matlab/matfile_format.pdf
 // https://www.mathworks.com/help/matlab/import_export/mat-file-versions.html
 
+pub type IResult<I, O> = nom::IResult<I, O, Error<I>>;
+
+/// The error of the parsers in this module.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Error<I> {
+    /// The remaining input at the position of the error
+    pub input: I,
+    pub kind: ParseErrorKind,
+    /// The name of the variable being parsed, once it is known
+    pub variable: Option<String>,
+    /// Whether the input is valid, but uses a format or feature that is not
+    /// supported
+    pub unsupported: bool,
+}
+
+impl<I> Error<I> {
+    fn new(input: I, kind: ParseErrorKind) -> Self {
+        Error {
+            input,
+            kind,
+            variable: None,
+            unsupported: false,
+        }
+    }
+
+    /// Records the name of the variable the error occurred in, unless the
+    /// error already belongs to a (nested) variable with a name.
+    pub(crate) fn in_variable(mut self, name: impl FnOnce() -> Option<String>) -> Self {
+        if self.variable.is_none() {
+            self.variable = name().filter(|name| !name.is_empty());
+        }
+        self
+    }
+}
+
+impl<I> nom::error::ParseError<I> for Error<I> {
+    fn from_error_kind(input: I, kind: nom::error::ErrorKind) -> Self {
+        let kind = match kind {
+            nom::error::ErrorKind::Eof | nom::error::ErrorKind::Complete => {
+                ParseErrorKind::Truncated
+            }
+            _ => ParseErrorKind::Invalid("unexpected content"),
+        };
+        Error::new(input, kind)
+    }
+
+    fn append(_input: I, _kind: nom::error::ErrorKind, other: Self) -> Self {
+        other
+    }
+}
+
+impl<I, E> nom::error::FromExternalError<I, E> for Error<I> {
+    fn from_external_error(input: I, _kind: nom::error::ErrorKind, _e: E) -> Self {
+        Error::new(input, ParseErrorKind::Invalid("invalid text encoding"))
+    }
+}
+
+/// An unrecoverable error at the given position.
+pub(crate) fn fail<I>(i: I, kind: ParseErrorKind) -> nom::Err<Error<I>> {
+    nom::Err::Failure(Error::new(i, kind))
+}
+
+/// The byte offset of the remaining input `i` within `input`.
+pub(crate) fn offset(input: &[u8], i: &[u8]) -> usize {
+    (i.as_ptr() as usize)
+        .checked_sub(input.as_ptr() as usize)
+        .filter(|&offset| offset <= input.len())
+        .unwrap_or(input.len())
+}
+
 #[derive(Clone, Debug)]
 pub struct Header {
     pub text: String,
+    /// Offset of the subsystem data element from the start of the file, if
+    /// there is any subsystem data
+    pub subsystem_data_offset: Option<u64>,
     pub is_little_endian: bool,
+    /// The version field of the header: 0x0100 for Level 5 files and 0x0200
+    /// for the HDF5 based files of version 7.3
+    pub version: u16,
 }
 
 #[derive(Clone, Debug, PartialEq)]
@@ -59,6 +129,10 @@ impl NumericData {
         }
     }
 
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
     pub fn data_type(&self) -> DataType {
         match self {
             NumericData::Single(_) => DataType::Single,
@@ -93,11 +167,63 @@ pub enum DataElement {
         NumericData,
         Option<NumericData>,
     ),
-    // CharacterMatrix,
-    // Cell Matrix,
-    // Structure Matrix,
-    // Object Matrix,
-    Unsupported,
+    CharacterMatrix(ArrayFlags, Dimensions, String, CharacterData),
+    CellMatrix(ArrayFlags, Dimensions, String, Vec<DataElement>),
+    StructureMatrix(ArrayFlags, Dimensions, String, FieldNames, Vec<DataElement>),
+    ObjectMatrix(
+        ArrayFlags,
+        Dimensions,
+        String,
+        String,
+        FieldNames,
+        Vec<DataElement>,
+    ),
+    FunctionMatrix(ArrayFlags, Dimensions, String, Box<DataElement>),
+    OpaqueMatrix(ArrayFlags, String, String, String, Box<DataElement>),
+    /// A variable of a class that can't be read, with its dimensions, name
+    /// and class name
+    UnsupportedMatrix(Dimensions, String, String),
+    /// A data element that is not a matrix and thus not a variable
+    Unsupported(DataType),
+}
+
+impl DataElement {
+    /// The name of the variable, empty if the data element is no variable.
+    pub fn name(&self) -> &str {
+        match self {
+            DataElement::NumericMatrix(_, _, name, _, _)
+            | DataElement::SparseMatrix(_, _, name, _, _, _, _)
+            | DataElement::CharacterMatrix(_, _, name, _)
+            | DataElement::CellMatrix(_, _, name, _)
+            | DataElement::StructureMatrix(_, _, name, _, _)
+            | DataElement::ObjectMatrix(_, _, name, _, _, _)
+            | DataElement::FunctionMatrix(_, _, name, _)
+            | DataElement::OpaqueMatrix(_, name, _, _, _)
+            | DataElement::UnsupportedMatrix(_, name, _) => name,
+            DataElement::Unsupported(_) => "",
+        }
+    }
+
+    /// The Matlab class of the variable, such as "double" or "cell".
+    pub fn class_name(&self) -> String {
+        match self {
+            DataElement::NumericMatrix(flags, ..) | DataElement::SparseMatrix(flags, ..)
+                if flags.logical =>
+            {
+                "logical".to_owned()
+            }
+            DataElement::SparseMatrix(..) => "double".to_owned(),
+            DataElement::NumericMatrix(flags, ..)
+            | DataElement::CharacterMatrix(flags, ..)
+            | DataElement::CellMatrix(flags, ..)
+            | DataElement::StructureMatrix(flags, ..)
+            | DataElement::FunctionMatrix(flags, ..) => flags.class.class_name().to_owned(),
+            DataElement::ObjectMatrix(_, _, _, class_name, _, _)
+            | DataElement::OpaqueMatrix(_, _, _, class_name, _)
+            | DataElement::UnsupportedMatrix(_, _, class_name) => class_name.clone(),
+            DataElement::Unsupported(_) => String::new(),
+        }
+    }
 }
 
 // #[cfg(feature = "ndarray")]
@@ -118,7 +244,7 @@ pub fn parse_header(i: &[u8]) -> IResult<&[u8], Header> {
     // Header text field
     let (i, text) = take(116usize)(i)?;
     // Header subsystem data offset field
-    let (i, _ssdo) = take(8usize)(i)?;
+    let (i, ssdo) = take(8usize)(i)?;
     // Header flag fields
     // Assume little endian for now
     let (i, mut version) = u16(nom::number::Endianness::Little)(i)?;
@@ -128,44 +254,51 @@ pub fn parse_header(i: &[u8]) -> IResult<&[u8], Header> {
     if !is_little_endian {
         version = version.swap_bytes();
     }
-    if version != 0x0100 {
-        return Err(nom::Err::Failure(error_position!(
-            i,
-            // TODO
-            nom::error::ErrorKind::Tag
-        )));
+    if version != 0x0100 && version != 0x0200 {
+        return Err(fail(i, ParseErrorKind::InvalidHeader));
     }
+    // The subsystem data offset is written in the byte order of the file.
+    // Files without subsystem data have either zeros or spaces in this field.
+    let mut ssdo_bytes = [0; 8];
+    ssdo_bytes.copy_from_slice(ssdo);
+    let ssdo = if is_little_endian {
+        u64::from_le_bytes(ssdo_bytes)
+    } else {
+        u64::from_be_bytes(ssdo_bytes)
+    };
+    let subsystem_data_offset = match ssdo {
+        0 | 0x2020_2020_2020_2020 => None,
+        offset => Some(offset),
+    };
     Ok((
         i,
         Header {
-            text: std::str::from_utf8(text).unwrap_or(&"").to_owned(),
-            is_little_endian: is_little_endian,
+            text: std::str::from_utf8(text).unwrap_or("").to_owned(),
+            subsystem_data_offset,
+            is_little_endian,
+            version,
         },
     ))
 }
 
-fn constant<T: Clone>(v: T) -> impl Fn(&[u8]) -> IResult<&[u8], T> {
-    move |i: &[u8]| Ok((i, v.clone()))
-}
-
-fn parse_next_data_element(
+pub(crate) fn parse_next_data_element(
     endianness: nom::number::Endianness,
 ) -> impl Fn(&[u8]) -> IResult<&[u8], DataElement> {
-    move |i: &[u8]| {
-        let (i, data_element_tag) = parse_data_element_tag(endianness)(i)?;
+    move |input: &[u8]| {
+        let (i, data_element_tag) = parse_data_element_tag(endianness)(input)?;
         let next_parser: Box<dyn Fn(_) -> _> = match data_element_tag.data_type {
             DataType::Matrix => Box::new(parse_matrix_data_element(endianness)),
             DataType::Compressed => Box::new(parse_compressed_data_element(endianness)),
-            _ => {
-                println!(
-                    "Unsupported variable type: {:?} (must be Matrix or Compressed)",
-                    data_element_tag.data_type
-                );
-                Box::new(parse_unsupported_data_element(endianness))
-            }
+            data_type => Box::new(parse_unsupported_data_element(data_type)),
         };
-        let (i, data_element) =
-            length_value(constant(data_element_tag.data_byte_size), next_parser)(i)?;
+        let in_variable = |err: nom::Err<Error<_>>| {
+            err.map(|err| err.in_variable(|| peek_variable_name(endianness, input)))
+        };
+        if i.len() < data_element_tag.data_byte_size as usize {
+            return Err(in_variable(fail(input, ParseErrorKind::Truncated)));
+        }
+        let (i, data) = take(data_element_tag.data_byte_size)(i)?;
+        let (_, data_element) = next_parser(data).map_err(in_variable)?;
         // Take care of padding. It seems like either all variables in a mat file are compressed or none are.
         // If the variables are compressed there is no alignment to take care of (only uncompressed data
         // needs to be aligned according to the spec). Otherwise make sure that we end up on an 8 byte
@@ -180,6 +313,117 @@ fn parse_next_data_element(
     }
 }
 
+/// Parses the next data element like `parse_next_data_element` if it's not a
+/// variable or if `select` accepts its name. Otherwise the data element is
+/// skipped and `None` returned.
+fn parse_selected_data_element<'a>(
+    endianness: nom::number::Endianness,
+    select: &'a (dyn Fn(&str) -> bool + Sync),
+) -> impl Fn(&[u8]) -> IResult<&[u8], Option<DataElement>> + 'a {
+    move |input: &[u8]| {
+        let name = peek_variable_name(endianness, input);
+        let selected = match &name {
+            Some(name) => select(name),
+            None => true,
+        };
+        if selected {
+            return map(parse_next_data_element(endianness), Some)(input);
+        }
+        match skip_data_element(endianness)(input) {
+            Ok((i, ())) => Ok((i, None)),
+            // Report the error the same way as for selected variables
+            Err(_) => parse_next_data_element(endianness)(input).map(|(i, _)| (i, None)),
+        }
+    }
+}
+
+/// Skips the next data element without looking at its content. Fails if the
+/// data element is truncated.
+fn skip_data_element(endianness: nom::number::Endianness) -> impl Fn(&[u8]) -> IResult<&[u8], ()> {
+    move |input: &[u8]| {
+        let (i, data_element_tag) = parse_data_element_tag(endianness)(input)?;
+        if i.len() < data_element_tag.data_byte_size as usize {
+            return Err(fail(input, ParseErrorKind::Truncated));
+        }
+        let (i, _) = take(data_element_tag.data_byte_size)(i)?;
+        let num_padding_bytes = if data_element_tag.data_type == DataType::Compressed {
+            0
+        } else {
+            data_element_tag.padding_byte_size
+        };
+        let (i, _) = opt(complete(take(num_padding_bytes)))(i)?;
+        Ok((i, ()))
+    }
+}
+
+/// Parses the data elements like
+/// `many0(complete(parse_selected_data_element(..)))`, but decompresses and
+/// parses them in parallel. The file is split into data elements by their
+/// tags first.
+#[cfg(feature = "rayon")]
+fn parse_selected_data_elements_parallel<'a>(
+    endianness: nom::number::Endianness,
+    select: &(dyn Fn(&str) -> bool + Sync),
+    input: &'a [u8],
+) -> IResult<&'a [u8], Vec<Option<DataElement>>> {
+    use rayon::prelude::*;
+    let mut elements = Vec::new();
+    let mut i = input;
+    while let Ok((rest, ())) = skip_data_element(endianness)(i) {
+        if rest.len() == i.len() {
+            break;
+        }
+        elements.push(&i[..i.len() - rest.len()]);
+        i = rest;
+    }
+    let data_elements = elements
+        .par_iter()
+        .map(|element| complete(parse_selected_data_element(endianness, select))(element))
+        .collect::<Vec<_>>();
+    // The first error in file order is reported, like the sequential parser
+    // does
+    let data_elements = data_elements
+        .into_iter()
+        .map(|result| result.map(|(_, data_element)| data_element))
+        .collect::<Result<_, _>>()?;
+    Ok((i, data_elements))
+}
+
+/// Reads the name of the (possibly compressed) matrix data element at the
+/// start of `i`, as far as possible. This is used to tell which variable an
+/// error occurred in.
+pub(crate) fn peek_variable_name(endianness: nom::number::Endianness, i: &[u8]) -> Option<String> {
+    let (i, data_element_tag) = parse_data_element_tag(endianness)(i).ok()?;
+    match data_element_tag.data_type {
+        DataType::Matrix => {}
+        DataType::Compressed => {
+            // The name is found at the very beginning of the decompressed
+            // data, which is available even if the data is corrupt later on
+            let mut buf = Vec::new();
+            let _ = Decoder::new(i).ok()?.take(1024).read_to_end(&mut buf);
+            let (_, inner_tag) = parse_data_element_tag(endianness)(&buf).ok()?;
+            if inner_tag.data_type != DataType::Matrix {
+                return None;
+            }
+            return peek_variable_name(endianness, &buf);
+        }
+        _ => return None,
+    }
+    // Only the class is needed from the array flags subelement. It's read
+    // without validation, so that the name can be found even if the class is
+    // invalid.
+    let (_, flags_and_class) = u32::<_, Error<_>>(endianness)(i.get(8..)?).ok()?;
+    let i = i.get(16..)?;
+    // Opaque arrays have no dimensions
+    let i = if flags_and_class & 0xFF == ArrayType::Opaque as u32 {
+        i
+    } else {
+        parse_dimensions_array_subelement(endianness)(i).ok()?.0
+    };
+    let (_, name) = parse_array_name_subelement(endianness)(i).ok()?;
+    Some(name)
+}
+
 fn ceil_to_multiple(x: u32, multiple: u32) -> u32 {
     if x > 0 {
         (((x - 1) / multiple) + 1) * multiple
@@ -245,6 +489,8 @@ pub enum ArrayType {
     UInt32 = 13,
     Int64 = 14,
     UInt64 = 15,
+    Function = 16,
+    Opaque = 17,
 }
 
 impl ArrayType {
@@ -259,6 +505,28 @@ impl ArrayType {
     //     }
     // }
 
+    /// The name of the Matlab class of arrays of this type.
+    pub fn class_name(&self) -> &'static str {
+        match self {
+            ArrayType::Cell => "cell",
+            ArrayType::Struct => "struct",
+            ArrayType::Object => "object",
+            ArrayType::Char => "char",
+            ArrayType::Sparse | ArrayType::Double => "double",
+            ArrayType::Single => "single",
+            ArrayType::Int8 => "int8",
+            ArrayType::UInt8 => "uint8",
+            ArrayType::Int16 => "int16",
+            ArrayType::UInt16 => "uint16",
+            ArrayType::Int32 => "int32",
+            ArrayType::UInt32 => "uint32",
+            ArrayType::Int64 => "int64",
+            ArrayType::UInt64 => "uint64",
+            ArrayType::Function => "function_handle",
+            ArrayType::Opaque => "opaque",
+        }
+    }
+
     fn numeric_data_type(&self) -> Option<DataType> {
         match self {
             ArrayType::Double => Some(DataType::Double),
@@ -267,7 +535,7 @@ impl ArrayType {
             ArrayType::UInt8 => Some(DataType::UInt8),
             ArrayType::Int16 => Some(DataType::Int16),
             ArrayType::UInt16 => Some(DataType::UInt16),
-            ArrayType::Int32 => Some(DataType::UInt32),
+            ArrayType::Int32 => Some(DataType::Int32),
             ArrayType::UInt32 => Some(DataType::UInt32),
             ArrayType::Int64 => Some(DataType::Int64),
             ArrayType::UInt64 => Some(DataType::UInt64),
@@ -280,16 +548,16 @@ pub type Dimensions = Vec<i32>;
 
 #[derive(Clone, Copy, Debug)]
 pub struct DataElementTag {
-    data_type: DataType,
-    data_byte_size: u32,
-    padding_byte_size: u32,
+    pub(crate) data_type: DataType,
+    pub(crate) data_byte_size: u32,
+    pub(crate) padding_byte_size: u32,
 }
 
-fn parse_data_element_tag(
+pub(crate) fn parse_data_element_tag(
     endianness: nom::number::Endianness,
 ) -> impl Fn(&[u8]) -> IResult<&[u8], DataElementTag> {
-    move |i: &[u8]| {
-        let (i, starting_bytes) = u32(endianness)(i)?;
+    move |input: &[u8]| {
+        let (i, starting_bytes) = u32(endianness)(input)?;
         let (i, data_type, byte_size, padding_byte_size) = if starting_bytes & 0xFFFF0000 == 0 {
             // Long Data Element Format
             let data_type = starting_bytes;
@@ -302,11 +570,10 @@ fn parse_data_element_tag(
             let byte_size = (starting_bytes & 0xFFFF0000) >> 16;
             // Assert that byte_size is <= 4
             if byte_size > 4 {
-                return Err(nom::Err::Failure(error_position!(
-                    i,
-                    // TODO
-                    nom::error::ErrorKind::Tag
-                )));
+                return Err(fail(
+                    input,
+                    ParseErrorKind::Invalid("small data element with more than 4 bytes"),
+                ));
             }
             let padding_byte_size = 4 - byte_size;
             (i, data_type, byte_size, padding_byte_size)
@@ -314,15 +581,10 @@ fn parse_data_element_tag(
         Ok((
             i,
             DataElementTag {
-                data_type: DataType::from_u32(data_type).ok_or(nom::Err::Failure(
-                    nom::error::Error {
-                        input: i,
-                        // TODO
-                        code: nom::error::ErrorKind::Tag,
-                    },
-                ))?,
+                data_type: DataType::from_u32(data_type)
+                    .ok_or_else(|| fail(input, ParseErrorKind::BadTagType(data_type)))?,
                 data_byte_size: byte_size,
-                padding_byte_size: padding_byte_size,
+                padding_byte_size,
             },
         ))
     }
@@ -331,21 +593,17 @@ fn parse_data_element_tag(
 fn parse_array_name_subelement(
     endianness: nom::number::Endianness,
 ) -> impl Fn(&[u8]) -> IResult<&[u8], String> {
-    move |i: &[u8]| {
-        let (i, data_element_tag) = parse_data_element_tag(endianness)(i)?;
-        if !(data_element_tag.data_type == DataType::Int8 && data_element_tag.data_byte_size > 0) {
-            return Err(nom::Err::Failure(error_position!(
-                i,
-                // TODO
-                nom::error::ErrorKind::Tag
-            )));
+    move |input: &[u8]| {
+        let (i, data_element_tag) = parse_data_element_tag(endianness)(input)?;
+        // Arrays nested in cells (and structs) have empty names
+        if data_element_tag.data_type != DataType::Int8 {
+            return Err(fail(
+                input,
+                ParseErrorKind::BadTagType(data_element_tag.data_type as u32),
+            ));
         }
         let (i, name) = map_res(take(data_element_tag.data_byte_size), |b| {
-            std::str::from_utf8(b)
-                .map(|s| s.to_owned())
-                .map_err(|_err| {
-                    nom::Err::Failure((i, nom::error::ErrorKind::Tag)) // TODO
-                })
+            std::str::from_utf8(b).map(|s| s.to_owned())
         })(i)?;
         // Padding bytes
         let (i, _) = take(data_element_tag.padding_byte_size)(i)?;
@@ -356,17 +614,16 @@ fn parse_array_name_subelement(
 fn parse_dimensions_array_subelement(
     endianness: nom::number::Endianness,
 ) -> impl Fn(&[u8]) -> IResult<&[u8], Dimensions> {
-    move |i: &[u8]| {
-        let (i, data_element_tag) = parse_data_element_tag(endianness)(i)?;
-        if !(data_element_tag.data_type == DataType::Int32
-            && data_element_tag.data_byte_size >= 8
-            && data_element_tag.data_byte_size % 4 == 0)
-        {
-            return Err(nom::Err::Failure(error_position!(
-                i,
-                // TODO
-                nom::error::ErrorKind::Tag
-            )));
+    move |input: &[u8]| {
+        let (i, data_element_tag) = parse_data_element_tag(endianness)(input)?;
+        if data_element_tag.data_type != DataType::Int32 {
+            return Err(fail(
+                input,
+                ParseErrorKind::BadTagType(data_element_tag.data_type as u32),
+            ));
+        }
+        if !(data_element_tag.data_byte_size >= 8 && data_element_tag.data_byte_size % 4 == 0) {
+            return Err(fail(input, ParseErrorKind::Invalid("invalid dimensions")));
         }
         let (i, dimensions) = count(
             i32(endianness),
@@ -377,196 +634,389 @@ fn parse_dimensions_array_subelement(
     }
 }
 
+/// The number of elements of an array with the given dimensions.
+fn num_elements<'a>(
+    input: &'a [u8],
+    dimensions: &[i32],
+) -> Result<usize, nom::Err<Error<&'a [u8]>>> {
+    dimensions
+        .iter()
+        .try_fold(1usize, |n, &d| {
+            usize::try_from(d).ok().and_then(|d| n.checked_mul(d))
+        })
+        .ok_or_else(|| fail(input, ParseErrorKind::Invalid("invalid dimensions")))
+}
+
 fn parse_array_flags_subelement(
     endianness: nom::number::Endianness,
 ) -> impl Fn(&[u8]) -> IResult<&[u8], ArrayFlags> {
-    move |i: &[u8]| {
-        let (i, tag_data_type) = u32(endianness)(i)?;
+    move |input: &[u8]| {
+        let (i, tag_data_type) = u32(endianness)(input)?;
         let (i, tag_data_len) = u32(endianness)(i)?;
-        if !(tag_data_type == DataType::UInt32 as u32 && tag_data_len == 8) {
-            return Err(nom::Err::Failure(error_position!(
-                i,
-                // TODO
-                nom::error::ErrorKind::Tag
-            )));
+        if tag_data_type != DataType::UInt32 as u32 {
+            return Err(fail(input, ParseErrorKind::BadTagType(tag_data_type)));
+        }
+        if tag_data_len != 8 {
+            return Err(fail(input, ParseErrorKind::Invalid("invalid array flags")));
         }
         let (i, flags_and_class) = u32(endianness)(i)?;
         let (i, nzmax) = u32(endianness)(i)?;
+        let class = (flags_and_class & 0xFF) as u8;
         Ok((
             i,
             ArrayFlags {
                 complex: (flags_and_class & 0x0800) != 0,
                 global: (flags_and_class & 0x0400) != 0,
                 logical: (flags_and_class & 0x0200) != 0,
-                class: ArrayType::from_u8((flags_and_class & 0xFF) as u8).ok_or(
-                    nom::Err::Failure(nom::error::Error {
-                        input: i,
-                        code: nom::error::ErrorKind::Tag,
-                    }), // TODO
-                )?,
+                class: ArrayType::from_u8(class)
+                    .ok_or_else(|| fail(input, ParseErrorKind::UnsupportedClass(class)))?,
                 nzmax: nzmax as usize,
             },
         ))
     }
 }
 
+/// Parses the array flags like `parse_array_flags_subelement`, but returns
+/// the class number instead of failing if the class is unknown.
+fn parse_array_flags_or_class_subelement(
+    endianness: nom::number::Endianness,
+) -> impl Fn(&[u8]) -> IResult<&[u8], Result<ArrayFlags, u8>> {
+    move |input: &[u8]| match parse_array_flags_subelement(endianness)(input) {
+        Err(nom::Err::Failure(Error {
+            kind: ParseErrorKind::UnsupportedClass(class),
+            ..
+        })) => {
+            let (i, _) = take(16usize)(input)?;
+            Ok((i, Err(class)))
+        }
+        result => result.map(|(i, flags)| (i, Ok(flags))),
+    }
+}
+
+/// The class name of variables of an unknown class.
+fn unknown_class_name(class: u8) -> String {
+    format!("unknown class {}", class)
+}
+
 fn parse_matrix_data_element(
     endianness: nom::number::Endianness,
 ) -> impl Fn(&[u8]) -> IResult<&[u8], DataElement> {
     move |i: &[u8]| {
-        let (i, flags) = parse_array_flags_subelement(endianness)(i)?;
-        match flags.class {
-            ArrayType::Cell | ArrayType::Struct | ArrayType::Object | ArrayType::Char => {
-                parse_unsupported_data_element(endianness)(i)
+        // Empty arrays nested in cells may be stored as a matrix without any
+        // content, which is equivalent to an empty double matrix
+        if i.is_empty() {
+            return Ok((i, empty_matrix()));
+        }
+        let (i, flags) = match parse_array_flags_or_class_subelement(endianness)(i)? {
+            (i, Ok(flags)) => (i, flags),
+            // Arrays of unknown classes are left out like other unsupported
+            // variables
+            (i, Err(class)) => {
+                let (i, dimensions) = parse_dimensions_array_subelement(endianness)(i)?;
+                let (_, name) = parse_array_name_subelement(endianness)(i)?;
+                return Ok((
+                    &[],
+                    DataElement::UnsupportedMatrix(dimensions, name, unknown_class_name(class)),
+                ));
             }
+        };
+        match flags.class {
+            ArrayType::Struct => parse_structure_matrix_subelements(endianness, flags)(i),
+            ArrayType::Object => parse_object_matrix_subelements(endianness, flags)(i),
+            ArrayType::Cell => parse_cell_matrix_subelements(endianness, flags)(i),
+            ArrayType::Char => parse_character_matrix_subelements(endianness, flags)(i),
             ArrayType::Sparse => parse_sparse_matrix_subelements(endianness, flags)(i),
+            ArrayType::Function => parse_function_matrix_subelements(endianness, flags)(i),
+            ArrayType::Opaque => parse_opaque_matrix_subelements(endianness, flags)(i),
             _ => parse_numeric_matrix_subelements(endianness, flags)(i),
         }
     }
 }
 
+/// The name, class and dimensions of a variable, as found at the start of its
+/// matrix data element.
+#[derive(Clone, Debug)]
+pub(crate) struct MatrixHeader {
+    pub name: String,
+    pub class_name: String,
+    /// `None` for opaque arrays, which have no dimensions
+    pub dimensions: Option<Dimensions>,
+}
+
+/// Parses the start of the content of a matrix data element without parsing
+/// the (possibly large) values.
+pub(crate) fn parse_matrix_header(
+    endianness: nom::number::Endianness,
+) -> impl Fn(&[u8]) -> IResult<&[u8], MatrixHeader> {
+    move |i: &[u8]| {
+        let (i, flags) = match parse_array_flags_or_class_subelement(endianness)(i)? {
+            (i, Ok(flags)) => (i, flags),
+            (i, Err(class)) => {
+                let (i, dimensions) = parse_dimensions_array_subelement(endianness)(i)?;
+                let (i, name) = parse_array_name_subelement(endianness)(i)?;
+                return Ok((
+                    i,
+                    MatrixHeader {
+                        name,
+                        class_name: unknown_class_name(class),
+                        dimensions: Some(dimensions),
+                    },
+                ));
+            }
+        };
+        let (i, dimensions) = if flags.class == ArrayType::Opaque {
+            (i, None)
+        } else {
+            let (i, dimensions) = parse_dimensions_array_subelement(endianness)(i)?;
+            (i, Some(dimensions))
+        };
+        let (i, name) = parse_array_name_subelement(endianness)(i)?;
+        let (i, class_name) = match flags.class {
+            ArrayType::Object => parse_array_name_subelement(endianness)(i)?,
+            ArrayType::Opaque => {
+                let (i, _type_system) = parse_array_name_subelement(endianness)(i)?;
+                parse_array_name_subelement(endianness)(i)?
+            }
+            _ if flags.logical => (i, "logical".to_owned()),
+            ArrayType::Sparse => (i, "double".to_owned()),
+            class => (i, class.class_name().to_owned()),
+        };
+        Ok((
+            i,
+            MatrixHeader {
+                name,
+                class_name,
+                dimensions,
+            },
+        ))
+    }
+}
+
+/// The raw values of an uncompressed numeric matrix whose values are stored
+/// with the type of its class.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct NumericParts<'a> {
+    pub data_type: DataType,
+    pub real: &'a [u8],
+    pub imag: Option<&'a [u8]>,
+}
+
+/// Finds the values of a numeric matrix data element without parsing them.
+/// Returns `None` if the data element is no such matrix.
+pub(crate) fn numeric_parts(
+    endianness: nom::number::Endianness,
+    i: &[u8],
+) -> Option<NumericParts<'_>> {
+    let (i, tag) = parse_data_element_tag(endianness)(i).ok()?;
+    if tag.data_type != DataType::Matrix {
+        return None;
+    }
+    let (i, flags) = parse_array_flags_subelement(endianness)(i).ok()?;
+    let data_type = flags.class.numeric_data_type().filter(|_| !flags.logical)?;
+    let (i, dimensions) = parse_dimensions_array_subelement(endianness)(i).ok()?;
+    let (i, _name) = parse_array_name_subelement(endianness)(i).ok()?;
+    let len = dimensions.iter().try_fold(1usize, |len, &d| {
+        len.checked_mul(std::convert::TryFrom::try_from(d).ok()?)
+    })?;
+    let part = |i| -> Option<(&[u8], &[u8])> {
+        let (i, tag) = parse_data_element_tag(endianness)(i).ok()?;
+        if tag.data_type != data_type
+            || Some(tag.data_byte_size as usize)
+                != data_type.byte_size().and_then(|size| len.checked_mul(size))
+        {
+            return None;
+        }
+        let (i, values) = take::<_, _, Error<_>>(tag.data_byte_size)(i).ok()?;
+        let (i, _) = opt(complete(take::<_, _, Error<_>>(tag.padding_byte_size)))(i).ok()?;
+        Some((i, values))
+    };
+    let (i, real) = part(i)?;
+    let imag = if flags.complex {
+        Some(part(i)?.1)
+    } else {
+        None
+    };
+    Some(NumericParts {
+        data_type,
+        real,
+        imag,
+    })
+}
+
+fn empty_matrix() -> DataElement {
+    DataElement::NumericMatrix(
+        ArrayFlags {
+            complex: false,
+            global: false,
+            logical: false,
+            class: ArrayType::Double,
+            nzmax: 0,
+        },
+        vec![0, 0],
+        String::new(),
+        NumericData::Double(Vec::new()),
+        None,
+    )
+}
+
 fn numeric_data_types_are_compatible(array_type: DataType, subelement_type: DataType) -> bool {
     match array_type {
-        DataType::Int8 => match subelement_type {
-            DataType::Int8 => true,
-            _ => false,
-        },
-        DataType::UInt8 => match subelement_type {
-            DataType::UInt8 => true,
-            _ => false,
-        },
-        DataType::Int16 => match subelement_type {
-            DataType::UInt8 | DataType::Int16 => true,
-            _ => false,
-        },
-        DataType::UInt16 => match subelement_type {
-            DataType::UInt8 | DataType::UInt16 => true,
-            _ => false,
-        },
-        DataType::Int32 => match subelement_type {
-            DataType::UInt8 | DataType::Int16 | DataType::UInt16 | DataType::Int32 => true,
-            _ => false,
-        },
-        DataType::UInt32 => match subelement_type {
-            DataType::UInt8 | DataType::Int16 | DataType::UInt16 | DataType::UInt32 => true,
-            _ => false,
-        },
-        DataType::Int64 => match subelement_type {
+        DataType::Int8 => matches!(subelement_type, DataType::Int8),
+        DataType::UInt8 => matches!(subelement_type, DataType::UInt8),
+        DataType::Int16 => matches!(subelement_type, DataType::UInt8 | DataType::Int16),
+        DataType::UInt16 => matches!(subelement_type, DataType::UInt8 | DataType::UInt16),
+        DataType::Int32 => matches!(
+            subelement_type,
+            DataType::UInt8 | DataType::Int16 | DataType::UInt16 | DataType::Int32
+        ),
+        DataType::UInt32 => matches!(
+            subelement_type,
+            DataType::UInt8 | DataType::Int16 | DataType::UInt16 | DataType::UInt32
+        ),
+        DataType::Int64 => matches!(
+            subelement_type,
             DataType::UInt8
-            | DataType::Int16
-            | DataType::UInt16
-            | DataType::Int32
-            | DataType::Int64 => true,
-            _ => false,
-        },
-        DataType::UInt64 => match subelement_type {
+                | DataType::Int16
+                | DataType::UInt16
+                | DataType::Int32
+                | DataType::Int64
+        ),
+        DataType::UInt64 => matches!(
+            subelement_type,
             DataType::UInt8
-            | DataType::Int16
-            | DataType::UInt16
-            | DataType::Int32
-            | DataType::UInt64 => true,
-            _ => false,
-        },
-        DataType::Single => match subelement_type {
+                | DataType::Int16
+                | DataType::UInt16
+                | DataType::Int32
+                | DataType::UInt64
+        ),
+        DataType::Single => matches!(
+            subelement_type,
             DataType::UInt8
-            | DataType::Int16
-            | DataType::UInt16
-            | DataType::Int32
-            | DataType::Single => true,
-            _ => false,
-        },
-        DataType::Double => match subelement_type {
+                | DataType::Int16
+                | DataType::UInt16
+                | DataType::Int32
+                | DataType::Single
+        ),
+        DataType::Double => matches!(
+            subelement_type,
             DataType::UInt8
-            | DataType::Int16
-            | DataType::UInt16
-            | DataType::Int32
-            | DataType::Double => true,
-            _ => false,
-        },
+                | DataType::Int16
+                | DataType::UInt16
+                | DataType::Int32
+                | DataType::Double
+        ),
         _ => false,
     }
 }
 
-fn parse_numeric_subelement(
+/// The primitive number types, which are decoded by copying bytes into them.
+///
+/// # Safety
+///
+/// Every bit pattern of the size of the type must be a valid value. `numbers`
+/// copies bytes into values of the type and `MatFileView` reinterprets bytes
+/// of the file as them.
+pub(crate) unsafe trait Number: Copy {
+    fn swap_bytes(self) -> Self;
+}
+
+macro_rules! impl_number {
+    ($($t:ty),*) => {
+        // Safety: any bit pattern is a valid integer
+        $(unsafe impl Number for $t {
+            fn swap_bytes(self) -> Self {
+                <$t>::swap_bytes(self)
+            }
+        })*
+    };
+}
+
+impl_number!(i8, u8, i16, u16, i32, u32, i64, u64);
+
+// Safety: any bit pattern is a valid float, possibly a NaN
+unsafe impl Number for f32 {
+    fn swap_bytes(self) -> Self {
+        f32::from_bits(self.to_bits().swap_bytes())
+    }
+}
+
+// Safety: any bit pattern is a valid float, possibly a NaN
+unsafe impl Number for f64 {
+    fn swap_bytes(self) -> Self {
+        f64::from_bits(self.to_bits().swap_bytes())
+    }
+}
+
+/// Whether numbers stored with the given byte order can be used as they are.
+pub(crate) fn is_native(endianness: nom::number::Endianness) -> bool {
+    match endianness {
+        nom::number::Endianness::Big => cfg!(target_endian = "big"),
+        nom::number::Endianness::Little => cfg!(target_endian = "little"),
+        nom::number::Endianness::Native => true,
+    }
+}
+
+/// Decodes `len` numbers at once, like `count(f64(endianness), len)` but much
+/// faster for large arrays: the bytes are copied in bulk and swapped
+/// afterwards if they are not in native byte order, which the compiler
+/// vectorizes.
+fn numbers<T: Number>(
+    endianness: nom::number::Endianness,
+    len: usize,
+) -> impl Fn(&[u8]) -> IResult<&[u8], Vec<T>> {
+    move |input: &[u8]| {
+        let byte_size = len
+            .checked_mul(std::mem::size_of::<T>())
+            .ok_or_else(|| fail(input, ParseErrorKind::Truncated))?;
+        let (i, bytes) = take(byte_size)(input)?;
+        let mut values = Vec::<T>::with_capacity(len);
+        // Safety: the vector has room for `len` values, which are `byte_size`
+        // bytes, and any bit pattern is a valid `T` as it implements `Number`
+        unsafe {
+            std::ptr::copy_nonoverlapping(
+                bytes.as_ptr(),
+                values.as_mut_ptr() as *mut u8,
+                byte_size,
+            );
+            values.set_len(len);
+        }
+        if !is_native(endianness) {
+            for value in &mut values {
+                *value = value.swap_bytes();
+            }
+        }
+        Ok((i, values))
+    }
+}
+
+/// Parses a numeric subelement, like the real or imaginary part of a numeric
+/// matrix.
+pub fn parse_numeric_subelement(
     endianness: nom::number::Endianness,
 ) -> impl Fn(&[u8]) -> IResult<&[u8], NumericData> {
-    move |i: &[u8]| {
-        let (i, data_element_tag) = parse_data_element_tag(endianness)(i)?;
+    move |input: &[u8]| {
+        let (i, data_element_tag) = parse_data_element_tag(endianness)(input)?;
+        let size = data_element_tag.data_byte_size as usize;
         let (i, numeric_data) = match data_element_tag.data_type {
-            DataType::Int8 => map(
-                count(i8, data_element_tag.data_byte_size as usize),
-                NumericData::Int8,
-            )(i)?,
-            DataType::UInt8 => map(
-                count(u8, data_element_tag.data_byte_size as usize),
-                NumericData::UInt8,
-            )(i)?,
-            DataType::Int16 => map(
-                count(
-                    i16(endianness),
-                    data_element_tag.data_byte_size as usize / 2,
-                ),
-                NumericData::Int16,
-            )(i)?,
-            DataType::UInt16 => map(
-                count(
-                    u16(endianness),
-                    data_element_tag.data_byte_size as usize / 2,
-                ),
-                NumericData::UInt16,
-            )(i)?,
-            DataType::Int32 => map(
-                count(
-                    i32(endianness),
-                    data_element_tag.data_byte_size as usize / 4,
-                ),
-                NumericData::Int32,
-            )(i)?,
-            DataType::UInt32 => map(
-                count(
-                    u32(endianness),
-                    data_element_tag.data_byte_size as usize / 4,
-                ),
-                NumericData::UInt32,
-            )(i)?,
-            DataType::Int64 => map(
-                count(
-                    i64(endianness),
-                    data_element_tag.data_byte_size as usize / 8,
-                ),
-                NumericData::Int64,
-            )(i)?,
-            DataType::UInt64 => map(
-                count(
-                    u64(endianness),
-                    data_element_tag.data_byte_size as usize / 8,
-                ),
-                NumericData::UInt64,
-            )(i)?,
-            DataType::Single => map(
-                count(
-                    f32(endianness),
-                    data_element_tag.data_byte_size as usize / 4,
-                ),
-                NumericData::Single,
-            )(i)?,
-            DataType::Double => map(
-                count(
-                    f64(endianness),
-                    data_element_tag.data_byte_size as usize / 8,
-                ),
-                NumericData::Double,
-            )(i)?,
+            DataType::Int8 => map(numbers(endianness, size), NumericData::Int8)(i)?,
+            DataType::UInt8 => map(numbers(endianness, size), NumericData::UInt8)(i)?,
+            DataType::Int16 => map(numbers(endianness, size / 2), NumericData::Int16)(i)?,
+            DataType::UInt16 => map(numbers(endianness, size / 2), NumericData::UInt16)(i)?,
+            DataType::Int32 => map(numbers(endianness, size / 4), NumericData::Int32)(i)?,
+            DataType::UInt32 => map(numbers(endianness, size / 4), NumericData::UInt32)(i)?,
+            DataType::Int64 => map(numbers(endianness, size / 8), NumericData::Int64)(i)?,
+            DataType::UInt64 => map(numbers(endianness, size / 8), NumericData::UInt64)(i)?,
+            DataType::Single => map(numbers(endianness, size / 4), NumericData::Single)(i)?,
+            DataType::Double => map(numbers(endianness, size / 8), NumericData::Double)(i)?,
             DataType::Compressed
             | DataType::Matrix
             | DataType::Utf8
             | DataType::Utf16
             | DataType::Utf32 => {
-                return Err(nom::Err::Failure(error_position!(
-                    i,
-                    // TODO
-                    nom::error::ErrorKind::Tag
-                )));
+                return Err(fail(
+                    input,
+                    ParseErrorKind::BadTagType(data_element_tag.data_type as u32),
+                ));
             }
         };
         // Padding bytes
@@ -580,22 +1030,12 @@ fn parse_compressed_data_element(
 ) -> impl Fn(&[u8]) -> IResult<&[u8], DataElement> {
     move |i: &[u8]| {
         let mut buf = Vec::new();
+        let decompression_error =
+            |err: std::io::Error| fail(i, ParseErrorKind::Decompression(err.to_string()));
         Decoder::new(i)
-            .map_err(|err| {
-                eprintln!("{:?}", err);
-                nom::Err::Failure(nom::error::Error {
-                    input: i,
-                    code: nom::error::ErrorKind::Tag,
-                }) // TODO
-            })?
+            .map_err(decompression_error)?
             .read_to_end(&mut buf)
-            .map_err(|err| {
-                eprintln!("{:?}", err);
-                nom::Err::Failure(nom::error::Error {
-                    input: i,
-                    code: nom::error::ErrorKind::Tag,
-                }) // TODO
-            })?;
+            .map_err(decompression_error)?;
         let (_remaining, data_element) = parse_next_data_element(endianness)(buf.as_slice())
             .map_err(|err| replace_err_slice(err, i))?;
         Ok((&[], data_element))
@@ -604,6 +1044,9 @@ fn parse_compressed_data_element(
 
 pub type RowIndex = Vec<usize>;
 pub type ColumnShift = Vec<usize>;
+pub type FieldNames = Vec<String>;
+/// Characters are stored as UTF-16 code units, just like Matlab does in memory
+pub type CharacterData = Vec<u16>;
 
 fn parse_numeric_matrix_subelements(
     endianness: nom::number::Endianness,
@@ -611,33 +1054,36 @@ fn parse_numeric_matrix_subelements(
 ) -> impl Fn(&[u8]) -> IResult<&[u8], DataElement> {
     move |i: &[u8]| {
         let (i, dimensions) = parse_dimensions_array_subelement(endianness)(i)?;
+        let num_required_elements = num_elements(i, &dimensions)?;
         let (i, name) = parse_array_name_subelement(endianness)(i)?;
-        let (i, real_part) = parse_numeric_subelement(endianness)(i)?;
-        // Check that size and type of the real part are correct
-        let num_required_elements = dimensions.iter().product::<i32>();
+        // Check that size and type of the real and imaginary parts are correct
         let array_data_type = flags.class.numeric_data_type().unwrap();
-        if !(real_part.len() == num_required_elements as usize
-            && numeric_data_types_are_compatible(array_data_type, real_part.data_type()))
-        {
-            return Err(nom::Err::Failure(error_position!(
-                i,
-                // TODO
-                nom::error::ErrorKind::Tag
-            )));
-        }
-        let (i, imag_part) = cond(flags.complex, parse_numeric_subelement(endianness))(i)?;
-        // Check that size and type of imaginary part are correct if present
-        if let Some(imag_part) = &imag_part {
-            if !(imag_part.len() == num_required_elements as usize
-                && numeric_data_types_are_compatible(array_data_type, imag_part.data_type()))
-            {
-                return Err(nom::Err::Failure(error_position!(
-                    i,
-                    // TODO
-                    nom::error::ErrorKind::Tag
-                )));
+        let check = |input, part: &NumericData| {
+            if !numeric_data_types_are_compatible(array_data_type, part.data_type()) {
+                Err(fail(
+                    input,
+                    ParseErrorKind::BadTagType(part.data_type() as u32),
+                ))
+            } else if part.len() != num_required_elements {
+                Err(fail(
+                    input,
+                    ParseErrorKind::LengthMismatch {
+                        expected: num_required_elements,
+                        actual: part.len(),
+                    },
+                ))
+            } else {
+                Ok(())
             }
+        };
+        let (rest, real_part) = parse_numeric_subelement(endianness)(i)?;
+        check(i, &real_part)?;
+        let i = rest;
+        let (rest, imag_part) = cond(flags.complex, parse_numeric_subelement(endianness))(i)?;
+        if let Some(imag_part) = &imag_part {
+            check(i, imag_part)?;
         }
+        let i = rest;
         Ok((
             i,
             DataElement::NumericMatrix(flags, dimensions, name, real_part, imag_part),
@@ -645,44 +1091,271 @@ fn parse_numeric_matrix_subelements(
     }
 }
 
-fn parse_sparse_matrix_subelements(
+fn parse_cell_matrix_subelements(
+    endianness: nom::number::Endianness,
+    flags: ArrayFlags,
+) -> impl Fn(&[u8]) -> IResult<&[u8], DataElement> {
+    move |i: &[u8]| {
+        let (i, dimensions) = parse_dimensions_array_subelement(endianness)(i)?;
+        let num_cells = num_elements(i, &dimensions)?;
+        let (i, name) = parse_array_name_subelement(endianness)(i)?;
+        // Each cell is a complete matrix data element of its own, stored in
+        // column-major order
+        let (i, cells) = count(parse_next_data_element(endianness), num_cells)(i)?;
+        Ok((i, DataElement::CellMatrix(flags, dimensions, name, cells)))
+    }
+}
+
+fn parse_structure_matrix_subelements(
+    endianness: nom::number::Endianness,
+    flags: ArrayFlags,
+) -> impl Fn(&[u8]) -> IResult<&[u8], DataElement> {
+    move |i: &[u8]| {
+        let (i, dimensions) = parse_dimensions_array_subelement(endianness)(i)?;
+        let (i, name) = parse_array_name_subelement(endianness)(i)?;
+        let (i, (field_names, values)) = parse_fields_subelements(endianness, &dimensions)(i)?;
+        Ok((
+            i,
+            DataElement::StructureMatrix(flags, dimensions, name, field_names, values),
+        ))
+    }
+}
+
+fn parse_object_matrix_subelements(
+    endianness: nom::number::Endianness,
+    flags: ArrayFlags,
+) -> impl Fn(&[u8]) -> IResult<&[u8], DataElement> {
+    move |i: &[u8]| {
+        let (i, dimensions) = parse_dimensions_array_subelement(endianness)(i)?;
+        let (i, name) = parse_array_name_subelement(endianness)(i)?;
+        // Objects are stored like structs with an additional class name
+        let (i, class_name) = parse_array_name_subelement(endianness)(i)?;
+        let (i, (field_names, values)) = parse_fields_subelements(endianness, &dimensions)(i)?;
+        Ok((
+            i,
+            DataElement::ObjectMatrix(flags, dimensions, name, class_name, field_names, values),
+        ))
+    }
+}
+
+fn parse_function_matrix_subelements(
     endianness: nom::number::Endianness,
     flags: ArrayFlags,
 ) -> impl Fn(&[u8]) -> IResult<&[u8], DataElement> {
     move |i: &[u8]| {
-        // Figure out the type of array
         let (i, dimensions) = parse_dimensions_array_subelement(endianness)(i)?;
         let (i, name) = parse_array_name_subelement(endianness)(i)?;
+        // The description of the function handle is stored as a struct
+        let (i, data) = parse_next_data_element(endianness)(i)?;
+        Ok((
+            i,
+            DataElement::FunctionMatrix(flags, dimensions, name, Box::new(data)),
+        ))
+    }
+}
+
+fn parse_opaque_matrix_subelements(
+    endianness: nom::number::Endianness,
+    flags: ArrayFlags,
+) -> impl Fn(&[u8]) -> IResult<&[u8], DataElement> {
+    move |i: &[u8]| {
+        // Opaque arrays have no dimensions. The name is followed by the name
+        // of the type system (e.g. "MCOS" for classdef objects) and the class
+        // name. The actual content is a matrix data element whose meaning
+        // depends on the type system.
+        let (i, name) = parse_array_name_subelement(endianness)(i)?;
+        let (i, type_system) = parse_array_name_subelement(endianness)(i)?;
+        let (i, class_name) = parse_array_name_subelement(endianness)(i)?;
+        let (i, data) = parse_next_data_element(endianness)(i)?;
+        Ok((
+            i,
+            DataElement::OpaqueMatrix(flags, name, type_system, class_name, Box::new(data)),
+        ))
+    }
+}
+
+fn parse_fields_subelements<'a>(
+    endianness: nom::number::Endianness,
+    dimensions: &'a Dimensions,
+) -> impl Fn(&[u8]) -> IResult<&[u8], (FieldNames, Vec<DataElement>)> + 'a {
+    move |i: &[u8]| {
+        let (i, field_names) = parse_field_names_subelements(endianness)(i)?;
+        // The values of all fields of the first struct come first, followed by
+        // the values of the second struct, and so on
+        let num_values = num_elements(i, dimensions)?
+            .checked_mul(field_names.len())
+            .ok_or_else(|| fail(i, ParseErrorKind::Invalid("invalid dimensions")))?;
+        let (i, values) = count(parse_next_data_element(endianness), num_values)(i)?;
+        Ok((i, (field_names, values)))
+    }
+}
+
+fn parse_field_names_subelements(
+    endianness: nom::number::Endianness,
+) -> impl Fn(&[u8]) -> IResult<&[u8], FieldNames> {
+    move |input: &[u8]| {
+        // Field name length subelement (including the null terminator)
+        let (i, data_element_tag) = parse_data_element_tag(endianness)(input)?;
+        if data_element_tag.data_type != DataType::Int32 {
+            return Err(fail(
+                input,
+                ParseErrorKind::BadTagType(data_element_tag.data_type as u32),
+            ));
+        }
+        if data_element_tag.data_byte_size != 4 {
+            return Err(fail(
+                input,
+                ParseErrorKind::Invalid("invalid field name length"),
+            ));
+        }
+        let (i, field_name_length) = i32(endianness)(i)?;
+        let (i, _) = take(data_element_tag.padding_byte_size)(i)?;
+        // Field names subelement, each name is padded with null bytes to the
+        // field name length
+        let input = i;
+        let (i, data_element_tag) = parse_data_element_tag(endianness)(input)?;
+        if data_element_tag.data_type != DataType::Int8 {
+            return Err(fail(
+                input,
+                ParseErrorKind::BadTagType(data_element_tag.data_type as u32),
+            ));
+        }
+        if !(data_element_tag.data_byte_size == 0
+            || (field_name_length > 0
+                && data_element_tag.data_byte_size % field_name_length as u32 == 0))
+        {
+            return Err(fail(input, ParseErrorKind::Invalid("invalid field names")));
+        }
+        let (i, field_names) = map_res(take(data_element_tag.data_byte_size), |b: &[u8]| {
+            b.chunks(field_name_length.max(1) as usize)
+                .map(|chunk| {
+                    let end = chunk.iter().position(|&c| c == 0).unwrap_or(chunk.len());
+                    std::str::from_utf8(&chunk[..end]).map(|s| s.to_owned())
+                })
+                .collect::<Result<FieldNames, _>>()
+        })(i)?;
+        let (i, _) = take(data_element_tag.padding_byte_size)(i)?;
+        Ok((i, field_names))
+    }
+}
+
+fn parse_character_matrix_subelements(
+    endianness: nom::number::Endianness,
+    flags: ArrayFlags,
+) -> impl Fn(&[u8]) -> IResult<&[u8], DataElement> {
+    move |i: &[u8]| {
+        let (i, dimensions) = parse_dimensions_array_subelement(endianness)(i)?;
+        let num_required_elements = num_elements(i, &dimensions)?;
+        let (input, name) = parse_array_name_subelement(endianness)(i)?;
+        let (i, data) = parse_character_subelement(endianness)(input)?;
+        // Check that the number of characters matches the dimensions
+        if data.len() != num_required_elements {
+            return Err(fail(
+                input,
+                ParseErrorKind::LengthMismatch {
+                    expected: num_required_elements,
+                    actual: data.len(),
+                },
+            ));
+        }
+        Ok((
+            i,
+            DataElement::CharacterMatrix(flags, dimensions, name, data),
+        ))
+    }
+}
+
+fn parse_character_subelement(
+    endianness: nom::number::Endianness,
+) -> impl Fn(&[u8]) -> IResult<&[u8], CharacterData> {
+    move |input: &[u8]| {
+        let (i, data_element_tag) = parse_data_element_tag(endianness)(input)?;
+        let byte_size = data_element_tag.data_byte_size as usize;
+        let (i, data) = match data_element_tag.data_type {
+            DataType::Utf8 => map_res(take(byte_size), |b| {
+                std::str::from_utf8(b).map(|s| s.encode_utf16().collect())
+            })(i)?,
+            DataType::Utf16 | DataType::UInt16 => numbers(endianness, byte_size / 2)(i)?,
+            DataType::Utf32 => map_res(count(u32(endianness), byte_size / 4), |code_points| {
+                code_points
+                    .into_iter()
+                    .map(|c| char::from_u32(c).ok_or(()))
+                    .collect::<Result<String, ()>>()
+                    .map(|s| s.encode_utf16().collect())
+            })(i)?,
+            // Some writers store plain 8 bit characters
+            DataType::UInt8 | DataType::Int8 => map(take(byte_size), |b: &[u8]| {
+                b.iter().map(|&c| c as u16).collect()
+            })(i)?,
+            _ => {
+                return Err(fail(
+                    input,
+                    ParseErrorKind::BadTagType(data_element_tag.data_type as u32),
+                ));
+            }
+        };
+        // Padding bytes
+        let (i, _) = take(data_element_tag.padding_byte_size)(i)?;
+        Ok((i, data))
+    }
+}
+
+fn parse_sparse_matrix_subelements(
+    endianness: nom::number::Endianness,
+    flags: ArrayFlags,
+) -> impl Fn(&[u8]) -> IResult<&[u8], DataElement> {
+    move |input: &[u8]| {
+        // Figure out the type of array
+        let (i, dimensions) = parse_dimensions_array_subelement(endianness)(input)?;
+        let (i, name) = parse_array_name_subelement(endianness)(i)?;
         let (i, row_index) = parse_row_index_array_subelement(endianness)(i)?;
         let (i, column_index) = parse_column_index_array_subelement(endianness)(i)?;
-        let (i, real_part) = parse_numeric_subelement(endianness)(i)?;
-        // Check that size of the real part is correct (can't check for type in sparse matrices)
-        if !(real_part.len() == flags.nzmax) {
-            return Err(nom::Err::Failure(error_position!(
-                i,
-                // TODO
-                nom::error::ErrorKind::Tag
-            )));
-        }
-        let (i, imag_part) = cond(flags.complex, parse_numeric_subelement(endianness))(i)?;
-        // Check that size of the imaginary part is correct if present (can't check for type in sparse matrices)
-        if let Some(imag_part) = &imag_part {
-            if !(imag_part.len() == flags.nzmax as usize) {
-                return Err(nom::Err::Failure(error_position!(
-                    i,
-                    // TODO
-                    nom::error::ErrorKind::Tag
-                )));
+        let size = match dimensions[..] {
+            [rows, columns] => usize::try_from(rows)
+                .ok()
+                .zip(usize::try_from(columns).ok()),
+            _ => None,
+        };
+        match size {
+            Some((rows, columns))
+                if valid_sparse_indices(rows, columns, &row_index, &column_index) => {}
+            _ => {
+                return Err(fail(
+                    input,
+                    ParseErrorKind::Invalid("invalid sparse matrix"),
+                ))
+            }
+        }
+        // Check that the sizes of the real and imaginary parts are correct
+        // (can't check for type in sparse matrices)
+        let check = |input, part: &NumericData| {
+            if part.len() != flags.nzmax {
+                return Err(fail(
+                    input,
+                    ParseErrorKind::LengthMismatch {
+                        expected: flags.nzmax,
+                        actual: part.len(),
+                    },
+                ));
             }
+            Ok(())
+        };
+        let (rest, real_part) = parse_numeric_subelement(endianness)(i)?;
+        check(i, &real_part)?;
+        let i = rest;
+        let (rest, imag_part) = cond(flags.complex, parse_numeric_subelement(endianness))(i)?;
+        if let Some(imag_part) = &imag_part {
+            check(i, imag_part)?;
         }
+        let i = rest;
         Ok((
             i,
             DataElement::SparseMatrix(
                 flags,
                 dimensions,
                 name,
-                row_index.iter().map(|&i| i as usize).collect(),
-                column_index.iter().map(|&i| i as usize).collect(),
+                row_index.to_vec(),
+                column_index.to_vec(),
                 real_part,
                 imag_part,
             ),
@@ -693,87 +1366,489 @@ fn parse_sparse_matrix_subelements(
 fn parse_row_index_array_subelement(
     endianness: nom::number::Endianness,
 ) -> impl Fn(&[u8]) -> IResult<&[u8], RowIndex> {
-    move |i: &[u8]| {
-        let (i, data_element_tag) = parse_data_element_tag(endianness)(i)?;
-        if !(data_element_tag.data_type == DataType::Int32 && data_element_tag.data_byte_size > 0) {
-            return Err(nom::Err::Failure(error_position!(
-                i,
-                // TODO
-                nom::error::ErrorKind::Tag
-            )));
-        }
-        let (i, row_index) = count(
-            i32(endianness),
-            (data_element_tag.data_byte_size / 4) as usize,
-        )(i)?;
+    move |input: &[u8]| {
+        let (i, data_element_tag) = parse_data_element_tag(endianness)(input)?;
+        if data_element_tag.data_type != DataType::Int32 {
+            return Err(fail(
+                input,
+                ParseErrorKind::BadTagType(data_element_tag.data_type as u32),
+            ));
+        }
+        if data_element_tag.data_byte_size == 0 {
+            return Err(fail(input, ParseErrorKind::Invalid("empty sparse index")));
+        }
+        let (i, row_index): (_, Vec<i32>) =
+            numbers(endianness, (data_element_tag.data_byte_size / 4) as usize)(i)?;
         let (i, _) = take(data_element_tag.padding_byte_size)(i)?;
-        Ok((i, row_index.iter().map(|&i| i as usize).collect()))
+        let row_index = row_index
+            .iter()
+            .map(|&i| usize::try_from(i))
+            .collect::<Result<_, _>>()
+            .map_err(|_| fail(input, ParseErrorKind::Invalid("negative sparse index")))?;
+        Ok((i, row_index))
     }
 }
 
 fn parse_column_index_array_subelement(
     endianness: nom::number::Endianness,
 ) -> impl Fn(&[u8]) -> IResult<&[u8], ColumnShift> {
-    move |i: &[u8]| {
-        let (i, data_element_tag) = parse_data_element_tag(endianness)(i)?;
-        if !(data_element_tag.data_type == DataType::Int32 && data_element_tag.data_byte_size > 0) {
-            return Err(nom::Err::Failure(error_position!(
-                i,
-                // TODO
-                nom::error::ErrorKind::Tag
-            )));
-        }
-        let (i, column_index) = count(
-            i32(endianness),
-            (data_element_tag.data_byte_size / 4) as usize,
-        )(i)?;
+    move |input: &[u8]| {
+        let (i, data_element_tag) = parse_data_element_tag(endianness)(input)?;
+        if data_element_tag.data_type != DataType::Int32 {
+            return Err(fail(
+                input,
+                ParseErrorKind::BadTagType(data_element_tag.data_type as u32),
+            ));
+        }
+        if data_element_tag.data_byte_size == 0 {
+            return Err(fail(input, ParseErrorKind::Invalid("empty sparse index")));
+        }
+        let (i, column_index): (_, Vec<i32>) =
+            numbers(endianness, (data_element_tag.data_byte_size / 4) as usize)(i)?;
         let (i, _) = take(data_element_tag.padding_byte_size)(i)?;
-        Ok((i, column_index.iter().map(|&i| i as usize).collect()))
+        let column_index = column_index
+            .iter()
+            .map(|&i| usize::try_from(i))
+            .collect::<Result<_, _>>()
+            .map_err(|_| fail(input, ParseErrorKind::Invalid("negative sparse index")))?;
+        Ok((i, column_index))
     }
 }
 
-pub fn replace_err_slice<'old, 'new>(
-    err: nom::Err<nom::error::Error<&'old [u8]>>,
-    new_slice: &'new [u8],
-) -> nom::Err<nom::error::Error<&'new [u8]>> {
-    match err {
-        nom::Err::Error(nom::error::Error { code, .. }) => nom::Err::Error(nom::error::Error {
-            code,
-            input: new_slice,
-        }),
-        nom::Err::Failure(nom::error::Error { code, .. }) => nom::Err::Failure(nom::error::Error {
-            code,
-            input: new_slice,
-        }),
-        nom::Err::Incomplete(needed) => nom::Err::Incomplete(needed),
+/// Whether the row indices and column pointers of a sparse matrix in
+/// compressed column form fit its size.
+pub(crate) fn valid_sparse_indices(
+    rows: usize,
+    columns: usize,
+    row_index: &[usize],
+    column_shift: &[usize],
+) -> bool {
+    if Some(column_shift.len()) != columns.checked_add(1)
+        || column_shift.windows(2).any(|shift| shift[0] > shift[1])
+    {
+        return false;
     }
+    match column_shift.last() {
+        Some(&nnz) if nnz <= row_index.len() => row_index[..nnz].iter().all(|&row| row < rows),
+        _ => false,
+    }
+}
+
+pub fn replace_err_slice<'new>(
+    err: nom::Err<Error<&[u8]>>,
+    new_slice: &'new [u8],
+) -> nom::Err<Error<&'new [u8]>> {
+    err.map(|err| Error {
+        input: new_slice,
+        kind: err.kind,
+        variable: err.variable,
+        unsupported: err.unsupported,
+    })
 }
 
 fn parse_unsupported_data_element(
-    _endianness: nom::number::Endianness,
+    data_type: DataType,
 ) -> impl Fn(&[u8]) -> IResult<&[u8], DataElement> {
-    |_i: &[u8]| Ok((&[], DataElement::Unsupported))
+    move |_i: &[u8]| Ok((&[], DataElement::Unsupported(data_type)))
+}
+
+/// Parses the content of the subsystem data element, which is a uint8 array
+/// holding a small MAT file of its own: an 8 byte header consisting of the
+/// version and the endian indicator, followed by a single matrix data element.
+fn parse_subsystem_data(i: &[u8]) -> IResult<&[u8], DataElement> {
+    let (i, _version) = take(2usize)(i)?;
+    let (i, endianness) = alt((
+        value(nom::number::Endianness::Little, tag("IM")),
+        value(nom::number::Endianness::Big, tag("MI")),
+    ))(i)?;
+    let (i, _padding) = take(4usize)(i)?;
+    parse_next_data_element(endianness)(i)
+}
+
+/// Parses the data element holding the subsystem data. Returns `None` if it
+/// is not a uint8 array.
+pub(crate) fn parse_subsystem_element(
+    endianness: nom::number::Endianness,
+    input: &[u8],
+) -> IResult<&[u8], Option<DataElement>> {
+    let (i, subsystem_data) = parse_next_data_element(endianness)(input)?;
+    let subsystem_data = match subsystem_data {
+        DataElement::NumericMatrix(_, _, _, NumericData::UInt8(bytes), None) => Some(
+            parse_subsystem_data(&bytes)
+                .map(|(_, subsystem_data)| subsystem_data)
+                .map_err(|err| replace_err_slice(err, input))?,
+        ),
+        _ => None,
+    };
+    Ok((i, subsystem_data))
+}
+
+/// The error for input that is valid, but uses a format or feature that is
+/// not supported. It is told apart from invalid input by its error kind.
+pub(crate) fn unsupported(i: &[u8]) -> nom::Err<Error<&[u8]>> {
+    nom::Err::Failure(Error {
+        unsupported: true,
+        ..Error::new(i, ParseErrorKind::Invalid("unsupported format"))
+    })
+}
+
+/// Detects the version of a MAT file from its first bytes.
+///
+/// Level 5 files start with a text header, whereas each variable of a Level 4
+/// file starts with its type code, a small integer. So if any of the first
+/// four bytes is zero, the file is a Level 4 file. Level 5 files are told
+/// apart by the version field of the header and by whether the first
+/// variable is compressed.
+pub fn detect_version(input: &[u8]) -> FileVersion {
+    if input.len() >= 4 && input[..4].contains(&0) {
+        return match parse_v4_header(input) {
+            Ok(_) => FileVersion::V4,
+            Err(_) => FileVersion::NotMatFile,
+        };
+    }
+    let header = match parse_header(input) {
+        Ok((_, header)) => header,
+        Err(_) => return FileVersion::NotMatFile,
+    };
+    if header.version == 0x0200 {
+        return FileVersion::V73;
+    }
+    let endianness = if header.is_little_endian {
+        nom::number::Endianness::Little
+    } else {
+        nom::number::Endianness::Big
+    };
+    match parse_data_element_tag(endianness)(&input[128..]) {
+        Ok((_, tag)) if tag.data_type == DataType::Compressed => FileVersion::V7,
+        _ => FileVersion::V6,
+    }
+}
+
+/// Parses the 20 byte header of a Level 4 variable: the type code, the number
+/// of rows and columns, the imaginary flag and the length of the name.
+///
+/// The type code is `MOPT` in decimal, where `M` is the machine format (0 for
+/// little endian IEEE, 1 for big endian IEEE, 2 and 3 for VAX and 4 for Cray),
+/// `O` is always 0, `P` the precision of the data and `T` the matrix type. The
+/// byte order of the header is the one of the machine that wrote the file, so
+/// it is detected from the type code: in the right byte order it's small.
+fn parse_v4_header(input: &[u8]) -> IResult<&[u8], V4Header> {
+    let (_, type_code) = peek(u32(nom::number::Endianness::Little))(input)?;
+    let endianness = if type_code < 5000 {
+        nom::number::Endianness::Little
+    } else {
+        nom::number::Endianness::Big
+    };
+    let (i, type_code) = u32(endianness)(input)?;
+    let (i, rows) = u32(endianness)(i)?;
+    let (i, columns) = u32(endianness)(i)?;
+    let (i, imaginary_flag) = u32(endianness)(i)?;
+    let (i, name_length) = u32(endianness)(i)?;
+    let machine = type_code / 1000;
+    // VAX machines are little endian, Cray machines big endian
+    let machine_matches = match endianness {
+        nom::number::Endianness::Little => machine == 0 || machine == 2 || machine == 3,
+        _ => machine == 1 || machine == 4,
+    };
+    if !machine_matches
+        || (type_code / 100) % 10 != 0
+        || (type_code / 10) % 10 > 5
+        || type_code % 10 > 2
+        || imaginary_flag > 1
+    {
+        return Err(fail(input, ParseErrorKind::InvalidHeader));
+    }
+    Ok((
+        i,
+        V4Header {
+            endianness,
+            machine,
+            precision: (type_code / 10) % 10,
+            matrix_type: type_code % 10,
+            rows: rows as usize,
+            columns: columns as usize,
+            complex: imaginary_flag == 1,
+            name_length: name_length as usize,
+        },
+    ))
+}
+
+#[derive(Clone, Copy, Debug)]
+struct V4Header {
+    endianness: nom::number::Endianness,
+    machine: u32,
+    precision: u32,
+    matrix_type: u32,
+    rows: usize,
+    columns: usize,
+    complex: bool,
+    name_length: usize,
+}
+
+fn parse_v4_numeric_data(
+    endianness: nom::number::Endianness,
+    precision: u32,
+    len: usize,
+) -> impl Fn(&[u8]) -> IResult<&[u8], NumericData> {
+    move |i: &[u8]| match precision {
+        0 => map(numbers(endianness, len), NumericData::Double)(i),
+        1 => map(numbers(endianness, len), NumericData::Single)(i),
+        2 => map(numbers(endianness, len), NumericData::Int32)(i),
+        3 => map(numbers(endianness, len), NumericData::Int16)(i),
+        4 => map(numbers(endianness, len), NumericData::UInt16)(i),
+        5 => map(numbers(endianness, len), NumericData::UInt8)(i),
+        _ => Err(fail(i, ParseErrorKind::InvalidHeader)),
+    }
+}
+
+/// Parses a single variable of a Level 4 file into the same kind of data
+/// element a Level 5 file would contain.
+fn parse_v4_data_element(i: &[u8]) -> IResult<&[u8], DataElement> {
+    let (i, header) = parse_v4_header(i)?;
+    // VAX and Cray floating point formats are not supported
+    if header.machine >= 2 {
+        return Err(unsupported(i));
+    }
+    let (i, name) = take(header.name_length)(i)?;
+    let name = name.split(|&b| b == 0).next().unwrap_or(&[]);
+    let name = String::from_utf8_lossy(name).into_owned();
+    parse_v4_matrix(header, name.clone(), i)
+        .map_err(|err| err.map(|err| err.in_variable(|| Some(name))))
+}
+
+fn parse_v4_matrix(header: V4Header, name: String, i: &[u8]) -> IResult<&[u8], DataElement> {
+    let len = header
+        .rows
+        .checked_mul(header.columns)
+        .ok_or_else(|| fail(i, ParseErrorKind::Invalid("matrix too large")))?;
+    let input = i;
+    let (i, real) = parse_v4_numeric_data(header.endianness, header.precision, len)(i)?;
+    let (i, imag) = cond(
+        header.complex,
+        parse_v4_numeric_data(header.endianness, header.precision, len),
+    )(i)?;
+    let dimensions = match (i32::try_from(header.rows), i32::try_from(header.columns)) {
+        (Ok(rows), Ok(columns)) => vec![rows, columns],
+        _ => return Err(fail(input, ParseErrorKind::Invalid("matrix too large"))),
+    };
+    let mut flags = ArrayFlags {
+        complex: header.complex,
+        global: false,
+        logical: false,
+        // Level 4 files only know double matrices, the precision merely
+        // describes how the data is stored
+        class: ArrayType::Double,
+        nzmax: 0,
+    };
+    let data_element = match header.matrix_type {
+        0 => DataElement::NumericMatrix(flags, dimensions, name, real, imag),
+        1 => {
+            flags.class = ArrayType::Char;
+            let chars = v4_values(&real).into_iter().map(|c| c as u16).collect();
+            DataElement::CharacterMatrix(flags, dimensions, name, chars)
+        }
+        _ => match v4_sparse_matrix(flags, name, header.rows, header.columns, &real, input.len()) {
+            Some(data_element) => data_element,
+            None => {
+                return Err(fail(
+                    input,
+                    ParseErrorKind::Invalid("invalid sparse matrix"),
+                ));
+            }
+        },
+    };
+    Ok((i, data_element))
+}
+
+fn v4_values(data: &NumericData) -> Vec<f64> {
+    match data {
+        NumericData::Double(vec) => vec.clone(),
+        NumericData::Single(vec) => vec.iter().map(|&v| v as f64).collect(),
+        NumericData::Int32(vec) => vec.iter().map(|&v| v as f64).collect(),
+        NumericData::Int16(vec) => vec.iter().map(|&v| v as f64).collect(),
+        NumericData::UInt16(vec) => vec.iter().map(|&v| v as f64).collect(),
+        NumericData::UInt8(vec) => vec.iter().map(|&v| v as f64).collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Converts a Level 4 sparse matrix into compressed column form. Level 4
+/// files store sparse matrices as a table with one row per nonzero entry,
+/// holding the one-based row and column indices, the real part and optionally
+/// the imaginary part. An additional last row holds the size of the matrix.
+/// `input_len` is the length of the input starting at the data.
+fn v4_sparse_matrix(
+    mut flags: ArrayFlags,
+    name: String,
+    rows: usize,
+    columns: usize,
+    data: &NumericData,
+    input_len: usize,
+) -> Option<DataElement> {
+    if rows == 0 || (columns != 3 && columns != 4) {
+        return None;
+    }
+    let values = v4_values(data);
+    let column = |index: usize| &values[index * rows..(index + 1) * rows];
+    let nnz = rows - 1;
+    let (num_rows, num_columns) = (column(0)[nnz], column(1)[nnz]);
+    let max = i32::MAX as f64;
+    if !((0.0..=max).contains(&num_rows) && (0.0..=max).contains(&num_columns)) {
+        return None;
+    }
+    let (num_rows, num_columns) = (num_rows as usize, num_columns as usize);
+    // Unlike in Level 5 files, the size is not backed by any data. Limit it to
+    // what the input could describe, since the column pointers take memory
+    // in proportion to the number of columns.
+    let max_size = nnz.saturating_add(input_len);
+    if num_rows > max_size || num_columns > max_size {
+        return None;
+    }
+    let mut entries = Vec::with_capacity(nnz);
+    for entry in 0..nnz {
+        let (row, col) = (column(0)[entry], column(1)[entry]);
+        if !(row >= 1.0 && row <= num_rows as f64 && col >= 1.0 && col <= num_columns as f64) {
+            return None;
+        }
+        entries.push((col as usize - 1, row as usize - 1, entry));
+    }
+    entries.sort_unstable();
+    let mut column_shift = vec![0; num_columns + 1];
+    for &(col, _, _) in &entries {
+        column_shift[col + 1] += 1;
+    }
+    for col in 0..num_columns {
+        column_shift[col + 1] += column_shift[col];
+    }
+    let row_index = entries.iter().map(|&(_, row, _)| row).collect();
+    let real = entries
+        .iter()
+        .map(|&(_, _, entry)| column(2)[entry])
+        .collect();
+    let imag = if columns == 4 {
+        Some(NumericData::Double(
+            entries
+                .iter()
+                .map(|&(_, _, entry)| column(3)[entry])
+                .collect(),
+        ))
+    } else {
+        None
+    };
+    flags.complex = imag.is_some();
+    flags.class = ArrayType::Sparse;
+    flags.nzmax = nnz;
+    Some(DataElement::SparseMatrix(
+        flags,
+        vec![num_rows as i32, num_columns as i32],
+        name,
+        row_index,
+        column_shift,
+        NumericData::Double(real),
+        imag,
+    ))
+}
+
+/// Parses a Level 4 MAT file, which is just a sequence of variables without
+/// any file header.
+fn parse_v4_all(input: &[u8]) -> IResult<&[u8], ParseResult> {
+    let (_, first_header) = parse_v4_header(input)?;
+    let (i, data_elements) = many0(complete(parse_v4_data_element))(input)?;
+    if !i.is_empty() {
+        // Make sure that trailing garbage is reported rather than ignored
+        parse_v4_data_element(i)?;
+    }
+    Ok((
+        i,
+        ParseResult {
+            header: Header {
+                text: String::new(),
+                subsystem_data_offset: None,
+                is_little_endian: first_header.endianness == nom::number::Endianness::Little,
+                // Level 4 files have no header and thus no version field
+                version: 0,
+            },
+            data_elements,
+            subsystem_data: None,
+        },
+    ))
 }
 
 #[derive(Debug)]
 pub struct ParseResult {
     pub header: Header,
     pub data_elements: Vec<DataElement>,
+    /// The content of the subsystem data, which is where Matlab stores the
+    /// properties of `classdef` objects
+    pub subsystem_data: Option<DataElement>,
+}
+
+pub fn parse_all(input: &[u8]) -> IResult<&[u8], ParseResult> {
+    parse_selected(input, &|_| true)
 }
 
-pub fn parse_all(i: &[u8]) -> IResult<&[u8], ParseResult> {
-    let (i, header) = parse_header(i)?;
+/// Parses a MAT file, but only the variables whose name is accepted by
+/// `select`. The other variables are skipped without decoding them and, if
+/// the name can be found at the start of the compressed data, without
+/// decompressing them.
+pub fn parse_selected<'a>(
+    input: &'a [u8],
+    select: &(dyn Fn(&str) -> bool + Sync),
+) -> IResult<&'a [u8], ParseResult> {
+    if detect_version(input) == FileVersion::V4 {
+        let (i, mut parse_result) = parse_v4_all(input)?;
+        parse_result
+            .data_elements
+            .retain(|data_element| select(data_element.name()));
+        return Ok((i, parse_result));
+    }
+    let (i, header) = parse_header(input)?;
+    if header.version == 0x0200 {
+        // Version 7.3 files are HDF5 files with the header in the user block
+        let data_elements = crate::hdf5::parse_all(input, select)?;
+        return Ok((
+            &[],
+            ParseResult {
+                header,
+                data_elements,
+                subsystem_data: None,
+            },
+        ));
+    }
     let endianness = if header.is_little_endian {
         nom::number::Endianness::Little
     } else {
         nom::number::Endianness::Big
     };
-    let (i, data_elements) = many0(complete(parse_next_data_element(endianness)))(i)?;
+    // The subsystem data element is stored after all variables. It's not a
+    // variable itself, so it's parsed separately.
+    let subsystem_data_offset = header
+        .subsystem_data_offset
+        .map(|offset| offset as usize)
+        .filter(|&offset| offset >= input.len() - i.len() && offset < input.len());
+    let (i, subsystem_data) = match subsystem_data_offset {
+        Some(offset) => {
+            let variables = &input[input.len() - i.len()..offset];
+            let (_, subsystem_data) = parse_subsystem_element(endianness, &input[offset..])?;
+            (variables, subsystem_data)
+        }
+        None => (i, None),
+    };
+    #[cfg(not(feature = "rayon"))]
+    let (i, data_elements) = many0(complete(parse_selected_data_element(endianness, select)))(i)?;
+    #[cfg(feature = "rayon")]
+    let (i, data_elements) = parse_selected_data_elements_parallel(endianness, select, i)?;
+    let data_elements = data_elements.into_iter().flatten().collect();
+    if !i.is_empty() {
+        // Make sure that truncated variables are reported rather than ignored
+        parse_next_data_element(endianness)(i)?;
+    }
     Ok((
         i,
         ParseResult {
-            header: header,
-            data_elements: data_elements,
+            header,
+            data_elements,
+            subsystem_data,
         },
     ))
 }
@@ -830,4 +1905,159 @@ mod test {
             panic!("Error extracting DataElement::SparseMatrix");
         }
     }
+
+    #[test]
+    fn numbers() {
+        use nom::number::Endianness;
+        let values = [1.5f64, -2.0, 1e300];
+        let le: Vec<u8> = values.iter().flat_map(|v| v.to_le_bytes()).collect();
+        let be: Vec<u8> = values.iter().flat_map(|v| v.to_be_bytes()).collect();
+        let (rest, parsed) = super::numbers::<f64>(Endianness::Little, 2)(&le).unwrap();
+        assert_eq!(parsed, values[..2]);
+        assert_eq!(rest, &le[16..]);
+        let (_, parsed) = super::numbers::<f64>(Endianness::Big, 3)(&be).unwrap();
+        assert_eq!(parsed, values);
+        let (_, parsed) = super::numbers::<i16>(Endianness::Big, 2)(&[1, 2, 0xff, 0xfe]).unwrap();
+        assert_eq!(parsed, [0x0102, -2]);
+        let err = super::numbers::<f64>(Endianness::Little, 4)(&le).unwrap_err();
+        assert_eq!(
+            err,
+            nom::Err::Error(Error::new(&le[..], ParseErrorKind::Truncated))
+        );
+        assert!(super::numbers::<u32>(Endianness::Little, usize::MAX)(&le).is_err());
+    }
+
+    #[test]
+    fn invalid_dimensions() {
+        let input: &[u8] = &[];
+        assert_eq!(num_elements(input, &[2, 3]), Ok(6));
+        assert_eq!(num_elements(input, &[]), Ok(1));
+        let invalid = Err(fail(input, ParseErrorKind::Invalid("invalid dimensions")));
+        assert_eq!(num_elements(input, &[-1, 2]), invalid);
+        assert_eq!(num_elements(input, &[i32::MAX; 3]), invalid);
+    }
+
+    #[test]
+    fn sparse_matrix_indices() {
+        let subelement = |data_type: DataType, bytes: Vec<u8>| {
+            let mut element = Vec::new();
+            element.extend_from_slice(&(data_type as u32).to_le_bytes());
+            element.extend_from_slice(&(bytes.len() as u32).to_le_bytes());
+            element.extend_from_slice(&bytes);
+            element.resize(element.len() + (8 - bytes.len() % 8) % 8, 0);
+            element
+        };
+        let int32 = |values: &[i32]| {
+            subelement(
+                DataType::Int32,
+                values.iter().flat_map(|v| v.to_le_bytes()).collect(),
+            )
+        };
+        // The subelements of a 2x2 sparse matrix with one nonzero value
+        let sparse_matrix = |row_index: &[i32], column_shift: &[i32]| {
+            let mut data = int32(&[2, 2]);
+            data.extend(subelement(DataType::Int8, b"s".to_vec()));
+            data.extend(int32(row_index));
+            data.extend(int32(column_shift));
+            data.extend(subelement(DataType::Double, 1.0f64.to_le_bytes().to_vec()));
+            data
+        };
+        let flags = ArrayFlags {
+            complex: false,
+            global: false,
+            logical: false,
+            class: ArrayType::Sparse,
+            nzmax: 1,
+        };
+        let parse = |row_index: &[i32], column_shift: &[i32]| {
+            let data = sparse_matrix(row_index, column_shift);
+            parse_sparse_matrix_subelements(nom::number::Endianness::Little, flags)(&data)
+                .map(|(_, parsed)| parsed)
+                .map_err(|err| match err {
+                    nom::Err::Failure(err) => err.kind,
+                    _ => panic!("Expected a failure"),
+                })
+        };
+        match parse(&[1], &[0, 0, 1]) {
+            Ok(DataElement::SparseMatrix(_, _, _, irows, icols, _, _)) => {
+                assert_eq!(irows, vec![1]);
+                assert_eq!(icols, vec![0, 0, 1]);
+            }
+            _ => panic!("Expected a sparse matrix"),
+        }
+        assert_eq!(
+            parse(&[-1], &[0, 0, 1]).unwrap_err(),
+            ParseErrorKind::Invalid("negative sparse index")
+        );
+        assert_eq!(
+            parse(&[0], &[0, -1, 1]).unwrap_err(),
+            ParseErrorKind::Invalid("negative sparse index")
+        );
+        for (row_index, column_shift) in [
+            // Row out of range
+            (&[2][..], &[0, 0, 1][..]),
+            // Too few or too many column pointers
+            (&[1], &[0, 1]),
+            (&[1], &[0, 0, 0, 1]),
+            // Decreasing column pointers
+            (&[1], &[0, 1, 0]),
+            // More nonzero values than row indices
+            (&[1], &[0, 1, 2]),
+        ] {
+            assert_eq!(
+                parse(row_index, column_shift).unwrap_err(),
+                ParseErrorKind::Invalid("invalid sparse matrix")
+            );
+        }
+    }
+
+    #[test]
+    fn v4_sparse_matrix_size() {
+        // A sparse matrix without entries, whose size is given by the last
+        // (and only) row
+        let v4_file = |num_rows: f64, num_columns: f64| {
+            let mut data = Vec::new();
+            for field in &[2u32, 1, 3, 0, 2] {
+                data.extend_from_slice(&field.to_le_bytes());
+            }
+            data.extend_from_slice(b"s\0");
+            for value in &[num_rows, num_columns, 0.0] {
+                data.extend_from_slice(&value.to_le_bytes());
+            }
+            data
+        };
+        let data = v4_file(3.0, 4.0);
+        let (_, parsed) = parse_v4_data_element(&data).unwrap();
+        match parsed {
+            DataElement::SparseMatrix(_, dims, _, _, icols, _, _) => {
+                assert_eq!(dims, vec![3, 4]);
+                assert_eq!(icols, vec![0; 5]);
+            }
+            _ => panic!("Expected a sparse matrix"),
+        }
+        for (num_rows, num_columns) in [(1.0, 2147483647.0), (2147483647.0, 1.0)] {
+            let data = v4_file(num_rows, num_columns);
+            let err = parse_v4_data_element(&data).unwrap_err();
+            assert!(matches!(
+                err,
+                nom::Err::Failure(Error {
+                    kind: ParseErrorKind::Invalid("invalid sparse matrix"),
+                    ..
+                })
+            ));
+        }
+        // An empty matrix with more rows than Level 5 files support
+        let mut data = Vec::new();
+        for field in &[0u32, 3_000_000_000, 0, 0, 2] {
+            data.extend_from_slice(&field.to_le_bytes());
+        }
+        data.extend_from_slice(b"e\0");
+        assert!(matches!(
+            parse_v4_data_element(&data).unwrap_err(),
+            nom::Err::Failure(Error {
+                kind: ParseErrorKind::Invalid("matrix too large"),
+                ..
+            })
+        ));
+    }
 }
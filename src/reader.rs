@@ -0,0 +1,409 @@
+use crate::parse::{self, DataType};
+use crate::{
+    mcos, warning, Array, Error, FileHeader, FileVersion, ParseError, ParseErrorKind, ParseOptions,
+    SkippedVariable, Warning,
+};
+use libflate::zlib::Decoder;
+use std::io::{Read, Seek, SeekFrom};
+
+/// How many bytes from the start of a variable are read to index it. This is
+/// enough for the name, class and dimensions of all but the most unusual
+/// variables, otherwise the whole variable is read.
+const PREFIX_SIZE: usize = 4096;
+
+/// Reads the variables of a .mat file one at a time, as they are requested.
+///
+/// When it's created, the reader only scans the file for the names, classes
+/// and sizes of the variables, see [`variables`](MatFileReader::variables).
+/// A variable is read, decompressed and decoded by
+/// [`load`](MatFileReader::load), so loading a few variables of a large file
+/// is fast and needs little memory.
+///
+/// Only Level 5 files (versions 6 and 7) can be read this way. Use
+/// [`MatFile::parse`](crate::MatFile::parse) for Level 4 and version 7.3
+/// files.
+///
+/// ```rust
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let file = std::fs::File::open("tests/two_arrays.mat")?;
+/// let mut reader = matfile::MatFileReader::new(file)?;
+/// for variable in reader.variables() {
+///     println!("{}: {} {:?}", variable.name(), variable.class(), variable.size());
+/// }
+/// if let Some(array) = reader.load("A")? {
+///     println!("{:#?}", array.data());
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct MatFileReader<R> {
+    reader: R,
+    header: FileHeader,
+    endianness: nom::number::Endianness,
+    variables: Vec<VariableInfo>,
+    subsystem: mcos::Subsystem,
+}
+
+/// A variable found by a [`MatFileReader`], which may not have been loaded
+/// yet.
+#[derive(Clone, Debug, PartialEq)]
+pub struct VariableInfo {
+    name: String,
+    class: String,
+    size: Vec<usize>,
+    offset: u64,
+    stored_size: u64,
+    uncompressed_size: u64,
+    compressed: bool,
+}
+
+impl VariableInfo {
+    /// The name of the variable.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The Matlab class of the variable, such as "double", "cell" or the name
+    /// of a `classdef` class.
+    pub fn class(&self) -> &str {
+        &self.class
+    }
+
+    /// The size of the variable, see `Array::size`. Objects of `classdef`
+    /// classes are only known as an array of objects, so a `datetime` array
+    /// or a `table` has the size of a single object here.
+    pub fn size(&self) -> &Vec<usize> {
+        &self.size
+    }
+
+    /// The byte offset of the variable from the start of the file.
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// The number of bytes the variable takes up in the file.
+    pub fn stored_size(&self) -> u64 {
+        self.stored_size
+    }
+
+    /// The number of bytes of the variable after decompression. The same as
+    /// the stored size for uncompressed variables.
+    pub fn uncompressed_size(&self) -> u64 {
+        self.uncompressed_size
+    }
+
+    /// Whether the variable is stored compressed.
+    pub fn is_compressed(&self) -> bool {
+        self.compressed
+    }
+}
+
+impl<R: Read + Seek> MatFileReader<R> {
+    /// Scans a .mat file for its variables.
+    pub fn new(reader: R) -> Result<Self, Error> {
+        Self::new_with(reader, &ParseOptions::default())
+    }
+
+    /// Scans a .mat file for its variables, using the given options. Only the
+    /// variables selected by the options are listed.
+    pub fn new_with(mut reader: R, options: &ParseOptions) -> Result<Self, Error> {
+        let len = reader.seek(SeekFrom::End(0)).map_err(Error::IOError)?;
+        let start = read_at(&mut reader, 0, 136)?;
+        let version = parse::detect_version(&start);
+        if version != FileVersion::V6 && version != FileVersion::V7 {
+            return Err(Error::UnsupportedFormat(version));
+        }
+        let (_, header) = parse::parse_header(&start)
+            .map_err(|err| Error::from_parse_error(err, &start, 0, version))?;
+        let endianness = if header.is_little_endian {
+            nom::number::Endianness::Little
+        } else {
+            nom::number::Endianness::Big
+        };
+        let subsystem_data_offset = header
+            .subsystem_data_offset
+            .filter(|&offset| offset >= 128 && offset < len);
+        let subsystem_data = match subsystem_data_offset {
+            Some(offset) => {
+                let element = read_element(&mut reader, offset, len, endianness, version)?;
+                parse::parse_subsystem_element(endianness, &element)
+                    .map_err(|err| {
+                        Error::from_parse_error(err, &element, offset as usize, version)
+                    })?
+                    .1
+            }
+            None => None,
+        };
+        if let (Some(offset), None) = (header.subsystem_data_offset, &subsystem_data) {
+            options.warn(Warning::IgnoredSubsystemData { offset });
+        }
+        let mut mat_file_reader = MatFileReader {
+            reader,
+            header: FileHeader::new(&header, version),
+            endianness,
+            variables: Vec::new(),
+            subsystem: mcos::Subsystem::new(subsystem_data, header.is_little_endian),
+        };
+        let end = subsystem_data_offset.unwrap_or(len);
+        let mut offset = 128;
+        while offset < end {
+            offset = mat_file_reader.index_element(offset, len, options)?;
+        }
+        Ok(mat_file_reader)
+    }
+
+    /// The header of the file.
+    pub fn header(&self) -> &FileHeader {
+        &self.header
+    }
+
+    pub(crate) fn get_ref(&self) -> &R {
+        &self.reader
+    }
+
+    /// List of all variables in the file, in the order they are stored.
+    pub fn variables(&self) -> &[VariableInfo] {
+        &self.variables
+    }
+
+    /// Reads and decodes the variable with the given name. Returns `None` if
+    /// there is no such variable.
+    ///
+    /// Fails with `Error::UnsupportedVariable` if the variable can't be
+    /// loaded.
+    pub fn load(&mut self, name: &str) -> Result<Option<Array>, Error> {
+        let (offset, stored_size) = match self.find(name) {
+            Some(variable) => (variable.offset, variable.stored_size),
+            None => return Ok(None),
+        };
+        let data = read_at(&mut self.reader, offset, stored_size as usize)?;
+        self.decode(&data, offset).map(Some)
+    }
+
+    pub(crate) fn find(&self, name: &str) -> Option<&VariableInfo> {
+        self.variables.iter().find(|variable| variable.name == name)
+    }
+
+    pub(crate) fn endianness(&self) -> nom::number::Endianness {
+        self.endianness
+    }
+
+    /// Decodes the data element of a variable, which starts at `offset` in
+    /// the file.
+    pub(crate) fn decode(&self, data: &[u8], offset: u64) -> Result<Array, Error> {
+        let version = self.header.version();
+        let (_, data_element) = parse::parse_next_data_element(self.endianness)(data)
+            .map_err(|err| Error::from_parse_error(err, data, offset as usize, version))?;
+        let skipped = SkippedVariable::from_data_element(&data_element);
+        Array::from_data_element(data_element, mcos::Context::new(&self.subsystem))?
+            .ok_or(Error::UnsupportedVariable(skipped))
+    }
+
+    /// Adds the data element at `offset` to the index if it's a variable.
+    /// Returns the offset of the next data element.
+    fn index_element(
+        &mut self,
+        offset: u64,
+        len: u64,
+        options: &ParseOptions,
+    ) -> Result<u64, Error> {
+        let version = self.header.version();
+        let tag_bytes = read_at(&mut self.reader, offset, 8)?;
+        let (rest, tag) = parse::parse_data_element_tag(self.endianness)(&tag_bytes)
+            .map_err(|err| Error::from_parse_error(err, &tag_bytes, offset as usize, version))?;
+        let data_offset = offset + (tag_bytes.len() - rest.len()) as u64;
+        let data_size = tag.data_byte_size as u64;
+        let compressed = match tag.data_type {
+            DataType::Matrix => false,
+            DataType::Compressed => true,
+            data_type => {
+                options.warn(Warning::IgnoredDataElement {
+                    data_type: data_type as u32,
+                });
+                return Ok(data_offset + data_size + tag.padding_byte_size as u64);
+            }
+        };
+        let truncated = data_offset + data_size > len;
+        let variable =
+            self.read_matrix_header(&tag_bytes, data_offset, data_size, compressed, truncated);
+        if truncated {
+            let name = variable
+                .ok()
+                .and_then(|(_, _, header)| header)
+                .map(|h| h.name);
+            return Err(Error::ParseError(ParseError {
+                offset: offset as usize,
+                variable: name.filter(|name| !name.is_empty()),
+                kind: ParseErrorKind::Truncated,
+            }));
+        }
+        let (prefix, inner_tag, header) = variable?;
+        let header = match header {
+            Some(header) => header,
+            None => {
+                options.warn(Warning::IgnoredDataElement {
+                    data_type: inner_tag.data_type as u32,
+                });
+                return Ok(data_offset + data_size);
+            }
+        };
+        let padding = if compressed {
+            0
+        } else {
+            tag.padding_byte_size as u64
+        };
+        let next_offset = data_offset + data_size + padding;
+        if !options.selects(&header.name) {
+            return Ok(next_offset);
+        }
+        let size = match header.dimensions {
+            Some(dimensions) => dimensions.iter().map(|&d| d as usize).collect(),
+            // Opaque arrays are small, so the whole array is in the prefix
+            None => parse::parse_next_data_element(self.endianness)(&prefix)
+                .map(|(_, data_element)| warning::variable_size(&data_element))
+                .unwrap_or_else(|_| vec![1, 1]),
+        };
+        self.variables.push(VariableInfo {
+            name: header.name,
+            class: header.class_name,
+            size,
+            offset,
+            stored_size: data_offset - offset + data_size,
+            uncompressed_size: 8 + inner_tag.data_byte_size as u64,
+            compressed,
+        });
+        Ok(next_offset)
+    }
+
+    /// Reads the start of a variable, starting with its matrix tag, and
+    /// parses the header of the matrix. Compressed variables are only
+    /// decompressed as far as needed. The header is `None` if the compressed
+    /// data element is not a matrix.
+    ///
+    /// If the variable is truncated, whatever can be decompressed is used, so
+    /// that the name of the variable can be reported.
+    fn read_matrix_header(
+        &mut self,
+        tag_bytes: &[u8],
+        data_offset: u64,
+        data_size: u64,
+        compressed: bool,
+        truncated: bool,
+    ) -> Result<(Vec<u8>, parse::DataElementTag, Option<parse::MatrixHeader>), Error> {
+        let endianness = self.endianness;
+        let version = self.header.version();
+        let read_prefix = |reader: &mut R, limit: usize| -> Result<Vec<u8>, Error> {
+            if !compressed {
+                let mut prefix = tag_bytes.to_vec();
+                prefix.extend(read_at(reader, data_offset, limit.min(data_size as usize))?);
+                return Ok(prefix);
+            }
+            reader
+                .seek(SeekFrom::Start(data_offset))
+                .map_err(Error::IOError)?;
+            let decompression_error = |err: std::io::Error| {
+                Error::ParseError(ParseError {
+                    offset: data_offset as usize,
+                    variable: None,
+                    kind: ParseErrorKind::Decompression(err.to_string()),
+                })
+            };
+            let mut prefix = Vec::new();
+            let result = Decoder::new(reader.by_ref().take(data_size))
+                .and_then(|decoder| decoder.take(limit as u64).read_to_end(&mut prefix));
+            match result {
+                Err(err) if !truncated => Err(decompression_error(err)),
+                _ => Ok(prefix),
+            }
+        };
+        // Errors in compressed variables are reported at the start of the
+        // compressed data, like `MatFile::parse` does
+        let error_offset = if compressed {
+            data_offset as usize
+        } else {
+            data_offset as usize - tag_bytes.len()
+        };
+        let mut prefix = read_prefix(&mut self.reader, PREFIX_SIZE)?;
+        let (content, tag) = parse::parse_data_element_tag(endianness)(&prefix)
+            .map_err(|err| Error::from_parse_error(err, &prefix, error_offset, version))?;
+        let tag_size = prefix.len() - content.len();
+        if tag.data_type != DataType::Matrix {
+            return Ok((prefix, tag, None));
+        }
+        let is_truncated = |prefix: &[u8]| match matrix_header(endianness, &prefix[tag_size..]) {
+            Err(nom::Err::Error(err)) | Err(nom::Err::Failure(err)) => {
+                err.kind == ParseErrorKind::Truncated
+            }
+            _ => false,
+        };
+        // If the header doesn't fit into the prefix, read more of the
+        // variable step by step rather than all of it
+        let mut limit = PREFIX_SIZE;
+        while prefix.len() - tag_size < tag.data_byte_size as usize && is_truncated(&prefix) {
+            let len = prefix.len();
+            limit = limit.saturating_mul(2);
+            prefix = read_prefix(&mut self.reader, limit)?;
+            if prefix.len() <= len {
+                break;
+            }
+        }
+        let (_, header) = matrix_header(endianness, &prefix[tag_size..])
+            .map_err(|err| Error::from_parse_error(err, &prefix, error_offset, version))?;
+        Ok((prefix, tag, Some(header)))
+    }
+}
+
+/// Parses the header of a matrix from the start of its content.
+fn matrix_header(
+    endianness: nom::number::Endianness,
+    content: &[u8],
+) -> parse::IResult<&[u8], parse::MatrixHeader> {
+    // Empty arrays may be stored without any content
+    if content.is_empty() {
+        let header = parse::MatrixHeader {
+            name: String::new(),
+            class_name: "double".to_owned(),
+            dimensions: Some(vec![0, 0]),
+        };
+        return Ok((content, header));
+    }
+    parse::parse_matrix_header(endianness)(content)
+}
+
+/// Reads up to `len` bytes at `offset`. Fewer bytes are returned at the end
+/// of the file.
+fn read_at<R: Read + Seek>(reader: &mut R, offset: u64, len: usize) -> Result<Vec<u8>, Error> {
+    reader
+        .seek(SeekFrom::Start(offset))
+        .map_err(Error::IOError)?;
+    let mut buf = Vec::new();
+    reader
+        .by_ref()
+        .take(len as u64)
+        .read_to_end(&mut buf)
+        .map_err(Error::IOError)?;
+    Ok(buf)
+}
+
+/// Reads the whole data element at `offset`.
+fn read_element<R: Read + Seek>(
+    reader: &mut R,
+    offset: u64,
+    len: u64,
+    endianness: nom::number::Endianness,
+    version: FileVersion,
+) -> Result<Vec<u8>, Error> {
+    let tag_bytes = read_at(reader, offset, 8)?;
+    let (rest, tag) = parse::parse_data_element_tag(endianness)(&tag_bytes)
+        .map_err(|err| Error::from_parse_error(err, &tag_bytes, offset as usize, version))?;
+    let element_size = (tag_bytes.len() - rest.len()) as u64 + tag.data_byte_size as u64;
+    if offset + element_size > len {
+        return Err(Error::ParseError(ParseError {
+            offset: offset as usize,
+            variable: None,
+            kind: ParseErrorKind::Truncated,
+        }));
+    }
+    read_at(reader, offset, element_size as usize)
+}
@@ -0,0 +1,179 @@
+use crate::{ArrayData, NumericData};
+
+/// A sparse matrix in compressed sparse column (CSC) format.
+///
+/// The non-zero values are stored column by column. The values of column `c`
+/// are found at the positions `column_pointers()[c]..column_pointers()[c + 1]`
+/// of the value vectors, and `row_indices()` holds the row of each of them.
+///
+/// ```rust
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let file = std::fs::File::open("tests/sparse1.mat")?;
+/// let mat_file = matfile::MatFile::parse(file)?;
+/// let array = &mat_file.arrays()[0];
+/// if let matfile::ArrayData::Sparse(sparse) = array.data() {
+///     if let matfile::SparseData::Numeric(matfile::NumericData::Double { real, imag: _ }) = sparse.data() {
+///         for (row, column, value) in sparse.triplets(real) {
+///             println!("({}, {}) = {}", row, column, value);
+///         }
+///     }
+///     let dense = sparse.to_dense();
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct SparseArray {
+    size: Vec<usize>,
+    nzmax: usize,
+    row_indices: Vec<usize>,
+    column_pointers: Vec<usize>,
+    data: SparseData,
+}
+
+/// The non-zero values of a sparse matrix.
+#[derive(Clone, Debug)]
+pub enum SparseData {
+    /// The values of a numeric sparse matrix
+    Numeric(NumericData),
+    /// The values of a logical sparse matrix
+    Logical(Vec<bool>),
+}
+
+impl SparseArray {
+    pub(crate) fn new(
+        size: Vec<usize>,
+        nzmax: usize,
+        row_indices: Vec<usize>,
+        column_pointers: Vec<usize>,
+        data: SparseData,
+    ) -> Self {
+        SparseArray {
+            size,
+            nzmax,
+            row_indices,
+            column_pointers,
+            data,
+        }
+    }
+
+    /// The size of this sparse matrix, see `Array::size`. Sparse matrices
+    /// are always two-dimensional.
+    pub fn size(&self) -> &Vec<usize> {
+        &self.size
+    }
+
+    /// The number of rows.
+    pub fn num_rows(&self) -> usize {
+        self.size.first().copied().unwrap_or(0)
+    }
+
+    /// The number of columns.
+    pub fn num_columns(&self) -> usize {
+        self.size.get(1).copied().unwrap_or(0)
+    }
+
+    /// The maximum number of non-zero elements the matrix has storage
+    /// allocated for.
+    pub fn nzmax(&self) -> usize {
+        self.nzmax
+    }
+
+    /// The actual number of non-zero elements.
+    pub fn nnz(&self) -> usize {
+        self.column_pointers.last().copied().unwrap_or(0)
+    }
+
+    /// The row index of each stored value.
+    pub fn row_indices(&self) -> &[usize] {
+        &self.row_indices
+    }
+
+    /// For each column the position of its first value, followed by the
+    /// total number of non-zero elements.
+    pub fn column_pointers(&self) -> &[usize] {
+        &self.column_pointers
+    }
+
+    /// The non-zero values.
+    pub fn data(&self) -> &SparseData {
+        &self.data
+    }
+
+    /// Iterates over the (row, column) positions of the non-zero elements in
+    /// column-major order.
+    pub fn positions(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.column_pointers
+            .windows(2)
+            .enumerate()
+            .flat_map(move |(column, range)| {
+                self.row_indices
+                    .get(range[0]..range[1])
+                    .unwrap_or(&[])
+                    .iter()
+                    .map(move |&row| (row, column))
+            })
+    }
+
+    /// Iterates over the non-zero elements as (row, column, value) triplets
+    /// in column-major order. `values` is the real or imaginary part of the
+    /// numeric data or the logical data of this sparse matrix.
+    pub fn triplets<'a, T: Copy>(
+        &'a self,
+        values: &'a [T],
+    ) -> impl Iterator<Item = (usize, usize, T)> + 'a {
+        self.positions()
+            .zip(values.iter())
+            .map(|((row, column), &value)| (row, column, value))
+    }
+
+    /// Converts the sparse matrix into a dense matrix in column-major order.
+    ///
+    /// Depending on the data of the sparse matrix the result is either
+    /// `ArrayData::Numeric` or `ArrayData::Logical`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the number of elements of the dense matrix overflows
+    /// `usize`.
+    pub fn to_dense(&self) -> ArrayData {
+        macro_rules! densify {
+            ( $variant:ident, $real:expr, $imag:expr ) => {
+                NumericData::$variant {
+                    real: self.densify($real),
+                    imag: $imag.as_ref().map(|imag| self.densify(imag)),
+                }
+            };
+        }
+        let data = match &self.data {
+            SparseData::Numeric(data) => data,
+            SparseData::Logical(data) => return ArrayData::Logical(self.densify(data)),
+        };
+        ArrayData::Numeric(match data {
+            NumericData::Int8 { real, imag } => densify!(Int8, real, imag),
+            NumericData::UInt8 { real, imag } => densify!(UInt8, real, imag),
+            NumericData::Int16 { real, imag } => densify!(Int16, real, imag),
+            NumericData::UInt16 { real, imag } => densify!(UInt16, real, imag),
+            NumericData::Int32 { real, imag } => densify!(Int32, real, imag),
+            NumericData::UInt32 { real, imag } => densify!(UInt32, real, imag),
+            NumericData::Int64 { real, imag } => densify!(Int64, real, imag),
+            NumericData::UInt64 { real, imag } => densify!(UInt64, real, imag),
+            NumericData::Single { real, imag } => densify!(Single, real, imag),
+            NumericData::Double { real, imag } => densify!(Double, real, imag),
+        })
+    }
+
+    fn densify<T: Copy + Default>(&self, values: &[T]) -> Vec<T> {
+        let num_rows = self.num_rows();
+        let len = num_rows
+            .checked_mul(self.num_columns())
+            .expect("sparse matrix too large to densify");
+        let mut dense = vec![T::default(); len];
+        for (row, column, value) in self.triplets(values) {
+            if let Some(element) = dense.get_mut(row + column * num_rows) {
+                *element = value;
+            }
+        }
+        dense
+    }
+}
@@ -0,0 +1,326 @@
+use crate::parse::{self, DataType};
+use crate::{
+    mcos, Array, Error, FileHeader, FileVersion, ParseError, ParseErrorKind, ParseOptions,
+    SkippedVariable, Warning,
+};
+use libflate::zlib::Decoder;
+use std::collections::VecDeque;
+use std::io::{Chain, Cursor, Read};
+
+/// How many bytes from the start of a variable are read to tell its name
+/// before the rest of it is read or skipped.
+const PREFIX_SIZE: u64 = 1024;
+
+/// An iterator over the variables of a .mat file that reads the file as it
+/// goes, see [`MatFile::stream`](crate::MatFile::stream).
+///
+/// The iterator ends after the first error.
+#[derive(Debug)]
+pub struct MatFileStream<R> {
+    /// The reader, after the bytes that were read to detect the version
+    reader: Chain<Cursor<Vec<u8>>, R>,
+    options: ParseOptions,
+    header: FileHeader,
+    endianness: nom::number::Endianness,
+    /// Offset of the next data element from the start of the file
+    position: u64,
+    subsystem_data_offset: Option<u64>,
+    /// The decoded subsystem data, once it has been read
+    subsystem: Option<mcos::Subsystem>,
+    /// Variables holding `classdef` objects, which can only be converted once
+    /// the subsystem data at the end of the file has been read
+    deferred: VecDeque<parse::DataElement>,
+    /// Whether all data elements have been read
+    at_end: bool,
+    /// Whether the iterator is exhausted
+    done: bool,
+}
+
+impl<R: Read> MatFileStream<R> {
+    pub(crate) fn new(mut reader: R, options: &ParseOptions) -> Result<Self, Error> {
+        let mut start = Vec::new();
+        reader
+            .by_ref()
+            .take(136)
+            .read_to_end(&mut start)
+            .map_err(Error::IOError)?;
+        let version = parse::detect_version(&start);
+        if version != FileVersion::V6 && version != FileVersion::V7 {
+            return Err(Error::UnsupportedFormat(version));
+        }
+        let (_, header) = parse::parse_header(&start)
+            .map_err(|err| Error::from_parse_error(err, &start, 0, version))?;
+        let endianness = if header.is_little_endian {
+            nom::number::Endianness::Little
+        } else {
+            nom::number::Endianness::Big
+        };
+        let subsystem_data_offset = header.subsystem_data_offset.filter(|&offset| offset >= 128);
+        if let (Some(offset), None) = (header.subsystem_data_offset, subsystem_data_offset) {
+            options.warn(Warning::IgnoredSubsystemData { offset });
+        }
+        let after_header = start.split_off(128);
+        Ok(MatFileStream {
+            reader: Cursor::new(after_header).chain(reader),
+            options: options.clone(),
+            header: FileHeader::new(&header, version),
+            endianness,
+            position: 128,
+            subsystem_data_offset,
+            subsystem: match subsystem_data_offset {
+                Some(_) => None,
+                None => Some(mcos::Subsystem::default()),
+            },
+            deferred: VecDeque::new(),
+            at_end: false,
+            done: false,
+        })
+    }
+
+    /// The header of the file.
+    pub fn header(&self) -> &FileHeader {
+        &self.header
+    }
+
+    fn next_array(&mut self) -> Result<Option<Array>, Error> {
+        loop {
+            if self.subsystem.is_some() {
+                if let Some(data_element) = self.deferred.pop_front() {
+                    match self.convert(data_element)? {
+                        Some(array) => return Ok(Some(array)),
+                        None => continue,
+                    }
+                }
+            }
+            let data_element = match self.next_data_element()? {
+                Some(data_element) => data_element,
+                None => {
+                    if self.subsystem.is_none() {
+                        // The file ended before the subsystem data
+                        if let Some(offset) = self.subsystem_data_offset {
+                            self.options.warn(Warning::IgnoredSubsystemData { offset });
+                        }
+                        self.subsystem = Some(mcos::Subsystem::default());
+                    }
+                    if self.deferred.is_empty() {
+                        return Ok(None);
+                    }
+                    continue;
+                }
+            };
+            if self.subsystem.is_none() && mcos::needs_subsystem(&data_element) {
+                self.deferred.push_back(data_element);
+                continue;
+            }
+            if let Some(array) = self.convert(data_element)? {
+                return Ok(Some(array));
+            }
+        }
+    }
+
+    fn convert(&self, data_element: parse::DataElement) -> Result<Option<Array>, Error> {
+        if let parse::DataElement::Unsupported(data_type) = data_element {
+            self.options.warn(Warning::IgnoredDataElement {
+                data_type: data_type as u32,
+            });
+            return Ok(None);
+        }
+        let empty = mcos::Subsystem::default();
+        let subsystem = self.subsystem.as_ref().unwrap_or(&empty);
+        let skipped_variable = SkippedVariable::from_data_element(&data_element);
+        match Array::from_data_element(data_element, mcos::Context::new(subsystem))? {
+            Some(array) => Ok(Some(array)),
+            None if self.options.is_strict() => Err(Error::UnsupportedVariable(skipped_variable)),
+            None => {
+                self.options
+                    .warn(Warning::SkippedVariable(skipped_variable));
+                Ok(None)
+            }
+        }
+    }
+
+    /// Reads the next data element that is a selected variable or not a
+    /// variable at all. Returns `None` at the end of the file.
+    fn next_data_element(&mut self) -> Result<Option<parse::DataElement>, Error> {
+        let version = self.header.version();
+        let endianness = self.endianness;
+        while !self.at_end {
+            let offset = self.position;
+            let tag_bytes = self.read(8)?;
+            if tag_bytes.is_empty() {
+                self.at_end = true;
+                break;
+            }
+            let (rest, tag) =
+                parse::parse_data_element_tag(endianness)(&tag_bytes).map_err(|err| {
+                    Error::from_parse_error(err, &tag_bytes, offset as usize, version)
+                })?;
+            if !rest.is_empty() {
+                // A small data element, which holds its data within the tag
+                let (_, data_element) = parse::parse_next_data_element(endianness)(&tag_bytes)
+                    .map_err(|err| {
+                        Error::from_parse_error(err, &tag_bytes, offset as usize, version)
+                    })?;
+                return Ok(Some(data_element));
+            }
+            let data_size = tag.data_byte_size as u64;
+            let padding = tag.padding_byte_size as u64;
+            if Some(offset) == self.subsystem_data_offset {
+                // Everything after the subsystem data is ignored
+                self.at_end = true;
+                let mut element = tag_bytes;
+                element.extend(self.read(data_size)?);
+                let (_, subsystem_data) = parse::parse_subsystem_element(endianness, &element)
+                    .map_err(|err| {
+                        Error::from_parse_error(err, &element, offset as usize, version)
+                    })?;
+                if subsystem_data.is_none() {
+                    self.options.warn(Warning::IgnoredSubsystemData { offset });
+                }
+                self.subsystem = Some(mcos::Subsystem::new(
+                    subsystem_data,
+                    self.header.is_little_endian(),
+                ));
+                break;
+            }
+            let data_element = match tag.data_type {
+                DataType::Matrix => self.read_matrix(offset, tag_bytes, data_size, padding)?,
+                DataType::Compressed => self.read_compressed(offset, data_size)?,
+                data_type => {
+                    self.skip(data_size + padding)?;
+                    Some(parse::DataElement::Unsupported(data_type))
+                }
+            };
+            if data_element.is_some() {
+                return Ok(data_element);
+            }
+        }
+        Ok(None)
+    }
+
+    /// Reads an uncompressed variable, or skips it if it's not selected.
+    fn read_matrix(
+        &mut self,
+        offset: u64,
+        tag_bytes: Vec<u8>,
+        data_size: u64,
+        padding: u64,
+    ) -> Result<Option<parse::DataElement>, Error> {
+        let mut element = tag_bytes;
+        element.extend(self.read(data_size.min(PREFIX_SIZE))?);
+        let prefix_size = element.len() as u64 - 8;
+        if let Some(name) = parse::peek_variable_name(self.endianness, &element) {
+            let remaining = data_size - prefix_size;
+            if !self.options.selects(&name) && self.skip(remaining)? == remaining {
+                self.skip(padding)?;
+                return Ok(None);
+            }
+        }
+        element.extend(self.read(data_size - prefix_size)?);
+        self.skip(padding)?;
+        let (_, data_element) =
+            parse::parse_next_data_element(self.endianness)(&element).map_err(|err| {
+                Error::from_parse_error(err, &element, offset as usize, self.header.version())
+            })?;
+        Ok(Some(data_element))
+    }
+
+    /// Reads and decompresses a compressed variable, or skips it without
+    /// decompressing it further than its name if it's not selected.
+    fn read_compressed(
+        &mut self,
+        offset: u64,
+        data_size: u64,
+    ) -> Result<Option<parse::DataElement>, Error> {
+        let data_offset = offset + 8;
+        let endianness = self.endianness;
+        let options = &self.options;
+        let mut compressed = self.reader.by_ref().take(data_size);
+        let mut element = Vec::new();
+        let mut selected = true;
+        let result = Decoder::new(&mut compressed).and_then(|mut decoder| {
+            decoder
+                .by_ref()
+                .take(PREFIX_SIZE)
+                .read_to_end(&mut element)?;
+            if let Some(name) = parse::peek_variable_name(endianness, &element) {
+                selected = options.selects(&name);
+            }
+            if selected {
+                decoder.read_to_end(&mut element)?;
+            }
+            Ok(())
+        });
+        // The checksum and anything else after the compressed data
+        std::io::copy(&mut compressed, &mut std::io::sink()).map_err(Error::IOError)?;
+        let missing = compressed.limit();
+        self.position += data_size - missing;
+        if missing > 0 {
+            return Err(Error::ParseError(ParseError {
+                offset: offset as usize,
+                variable: parse::peek_variable_name(self.endianness, &element),
+                kind: ParseErrorKind::Truncated,
+            }));
+        }
+        if let Err(err) = result {
+            return Err(Error::ParseError(ParseError {
+                offset: data_offset as usize,
+                variable: parse::peek_variable_name(self.endianness, &element),
+                kind: ParseErrorKind::Decompression(err.to_string()),
+            }));
+        }
+        if !selected {
+            return Ok(None);
+        }
+        // Errors in compressed variables are reported at the start of the
+        // compressed data, like `MatFile::parse` does
+        let (_, data_element) =
+            parse::parse_next_data_element(self.endianness)(&element).map_err(|err| {
+                let err = parse::replace_err_slice(err, &element);
+                Error::from_parse_error(err, &element, data_offset as usize, self.header.version())
+            })?;
+        Ok(Some(data_element))
+    }
+
+    /// Reads up to `len` bytes, fewer at the end of the file.
+    fn read(&mut self, len: u64) -> Result<Vec<u8>, Error> {
+        let mut buf = Vec::new();
+        self.reader
+            .by_ref()
+            .take(len)
+            .read_to_end(&mut buf)
+            .map_err(Error::IOError)?;
+        self.position += buf.len() as u64;
+        Ok(buf)
+    }
+
+    /// Skips up to `len` bytes. Returns the number of bytes skipped, fewer
+    /// at the end of the file.
+    fn skip(&mut self, len: u64) -> Result<u64, Error> {
+        let skipped = std::io::copy(&mut self.reader.by_ref().take(len), &mut std::io::sink())
+            .map_err(Error::IOError)?;
+        self.position += skipped;
+        Ok(skipped)
+    }
+}
+
+impl<R: Read> Iterator for MatFileStream<R> {
+    type Item = Result<Array, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        match self.next_array() {
+            Ok(Some(array)) => Some(Ok(array)),
+            Ok(None) => {
+                self.done = true;
+                None
+            }
+            Err(err) => {
+                self.done = true;
+                Some(Err(err))
+            }
+        }
+    }
+}
@@ -0,0 +1,117 @@
+use crate::cell::linear_index;
+
+use std::convert::TryFrom;
+
+/// A `string` array.
+///
+/// Unlike character arrays, each element of a string array is a complete
+/// string. An element can also be `<missing>`, which is represented by
+/// `None`. Like numeric data, the strings are stored in column-major order.
+///
+/// ```rust
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let file = std::fs::File::open("tests/string.mat")?;
+/// let mat_file = matfile::MatFile::parse(file)?;
+/// if let Some(matfile::ArrayData::String(strings)) = mat_file.find_by_name("s").map(|a| a.data()) {
+///     assert_eq!(strings.get(0), Some("hello"));
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct StringArray {
+    size: Vec<usize>,
+    strings: Vec<Option<String>>,
+}
+
+impl StringArray {
+    pub(crate) fn new(size: Vec<usize>, strings: Vec<Option<String>>) -> Self {
+        StringArray { size, strings }
+    }
+
+    /// Decodes the content of a string object. It is stored as a uint64
+    /// array consisting of a version number, the number of dimensions, the
+    /// dimensions, the length of each string in UTF-16 code units (all bits
+    /// set for missing strings) and finally the characters of all strings,
+    /// four UTF-16 code units per uint64.
+    pub(crate) fn from_encoded(data: &[u64], is_little_endian: bool) -> Option<Self> {
+        let lengths_start = 2usize.checked_add(usize::try_from(*data.get(1)?).ok()?)?;
+        let size = data
+            .get(2..lengths_start)?
+            .iter()
+            .map(|&d| usize::try_from(d).ok())
+            .collect::<Option<Vec<_>>>()?;
+        let num_strings = size.iter().try_fold(1usize, |n, &d| n.checked_mul(d))?;
+        let units_start = lengths_start.checked_add(num_strings)?;
+        let lengths = data.get(lengths_start..units_start)?;
+        let units: Vec<u16> = data[units_start..]
+            .iter()
+            .flat_map(|&word| {
+                let units = [
+                    word as u16,
+                    (word >> 16) as u16,
+                    (word >> 32) as u16,
+                    (word >> 48) as u16,
+                ];
+                // The code units are stored in the byte order of the file
+                if is_little_endian {
+                    units
+                } else {
+                    [units[3], units[2], units[1], units[0]]
+                }
+            })
+            .collect();
+        let mut position = 0usize;
+        let strings = lengths
+            .iter()
+            .map(|&length| {
+                if length == u64::MAX {
+                    return Some(None);
+                }
+                let end = position.checked_add(usize::try_from(length).ok()?)?;
+                let string = units.get(position..end)?;
+                position = end;
+                Some(Some(String::from_utf16_lossy(string)))
+            })
+            .collect::<Option<Vec<_>>>()?;
+        Some(StringArray::new(size, strings))
+    }
+
+    /// The size of this string array, see `Array::size`.
+    pub fn size(&self) -> &Vec<usize> {
+        &self.size
+    }
+
+    /// The number of strings.
+    pub fn len(&self) -> usize {
+        self.strings.len()
+    }
+
+    /// Whether this string array has no strings at all.
+    pub fn is_empty(&self) -> bool {
+        self.strings.is_empty()
+    }
+
+    /// All strings in column-major order, with `None` for missing strings.
+    pub fn strings(&self) -> &[Option<String>] {
+        &self.strings
+    }
+
+    /// Returns the string at the given linear (column-major) index. Returns
+    /// `None` if the index is out of bounds or the string is missing.
+    pub fn get(&self, index: usize) -> Option<&str> {
+        self.strings.get(index)?.as_deref()
+    }
+
+    /// Returns the string at the given zero-based subscripts, one per
+    /// dimension. See `CellArray::get_by_subscripts`.
+    pub fn get_by_subscripts(&self, subscripts: &[usize]) -> Option<&str> {
+        self.get(linear_index(&self.size, subscripts)?)
+    }
+
+    /// Iterates over all strings in column-major order, with `None` for
+    /// missing strings.
+    pub fn iter(&self) -> impl Iterator<Item = Option<&str>> {
+        self.strings.iter().map(|string| string.as_deref())
+    }
+}
@@ -0,0 +1,169 @@
+use crate::cell::linear_index;
+use crate::{mcos, parse, Array, Error};
+
+/// A structure array.
+///
+/// All structs of a structure array share the same field names. Most of the
+/// time a structure array consists of a single struct, in which case its
+/// fields can be accessed directly using `get_field`. The individual structs
+/// of larger structure arrays are stored in column-major order and can be
+/// accessed using `get` or `iter`.
+///
+/// The arrays stored in the fields are named after the field.
+///
+/// ```rust
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let file = std::fs::File::open("tests/struct.mat")?;
+/// let mat_file = matfile::MatFile::parse(file)?;
+/// if let Some(matfile::ArrayData::Struct(config)) = mat_file.find_by_name("config").map(|a| a.data()) {
+///     println!("Fields: {:?}", config.field_names());
+///     let gain = config.get_field("gain");
+/// }
+/// if let Some(matfile::ArrayData::Struct(trials)) = mat_file.find_by_name("trials").map(|a| a.data()) {
+///     for trial in trials {
+///         println!("Trial {:?}", trial.get_field("id"));
+///     }
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct StructArray {
+    size: Vec<usize>,
+    field_names: Vec<String>,
+    values: Vec<Array>,
+}
+
+/// A single struct of a structure array.
+#[derive(Clone, Copy, Debug)]
+pub struct Struct<'a> {
+    field_names: &'a [String],
+    values: &'a [Array],
+}
+
+impl StructArray {
+    pub(crate) fn new(size: Vec<usize>, field_names: Vec<String>, values: Vec<Array>) -> Self {
+        StructArray {
+            size,
+            field_names,
+            values,
+        }
+    }
+
+    /// Converts the parsed field values. Returns `None` if any of the values
+    /// is of unsupported type.
+    pub(crate) fn from_data_elements(
+        size: Vec<usize>,
+        field_names: Vec<String>,
+        values: Vec<parse::DataElement>,
+        context: mcos::Context,
+    ) -> Result<Option<Self>, Error> {
+        let values: Option<Vec<Array>> = values
+            .into_iter()
+            .zip(field_names.iter().cycle())
+            .map(|(value, field_name)| {
+                Ok(Array::from_data_element(value, context)?
+                    .map(|array| array.with_name(field_name)))
+            })
+            .collect::<Result<_, Error>>()?;
+        Ok(values.map(|values| StructArray::new(size, field_names, values)))
+    }
+
+    /// The size of this structure array, see `Array::size`.
+    pub fn size(&self) -> &Vec<usize> {
+        &self.size
+    }
+
+    /// The number of structs in this structure array.
+    pub fn len(&self) -> usize {
+        self.size.iter().product()
+    }
+
+    /// Whether this structure array contains no structs at all.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The names of the fields, in the order they are stored in the file.
+    pub fn field_names(&self) -> &[String] {
+        &self.field_names
+    }
+
+    /// Returns the value of a field of a 1x1 structure array. Case
+    /// sensitive.
+    ///
+    /// Returns `None` if there is no such field or if this structure array
+    /// does not consist of exactly one struct. Use `get` to access the fields
+    /// of the individual structs of larger structure arrays.
+    pub fn get_field(&self, name: &str) -> Option<&Array> {
+        if self.len() != 1 {
+            return None;
+        }
+        self.get(0)?.get_field(name)
+    }
+
+    /// Removes a field of a 1x1 structure array and returns its value.
+    pub(crate) fn remove_field(&mut self, name: &str) -> Option<Array> {
+        if self.len() != 1 {
+            return None;
+        }
+        let index = self.field_names.iter().position(|field| field == name)?;
+        self.field_names.remove(index);
+        Some(self.values.remove(index))
+    }
+
+    /// Returns the struct at the given linear (column-major) index.
+    pub fn get(&self, index: usize) -> Option<Struct<'_>> {
+        if index >= self.len() {
+            return None;
+        }
+        let num_fields = self.field_names.len();
+        Some(Struct {
+            field_names: &self.field_names,
+            values: &self.values[index * num_fields..(index + 1) * num_fields],
+        })
+    }
+
+    /// Returns the struct at the given zero-based subscripts, one per
+    /// dimension. See `CellArray::get_by_subscripts`.
+    pub fn get_by_subscripts(&self, subscripts: &[usize]) -> Option<Struct<'_>> {
+        self.get(linear_index(&self.size, subscripts)?)
+    }
+
+    /// Iterates over all structs in column-major order.
+    pub fn iter(&self) -> impl Iterator<Item = Struct<'_>> {
+        (0..self.len()).filter_map(move |index| self.get(index))
+    }
+}
+
+impl<'a> IntoIterator for &'a StructArray {
+    type Item = Struct<'a>;
+    type IntoIter = Box<dyn Iterator<Item = Struct<'a>> + 'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        Box::new(self.iter())
+    }
+}
+
+impl<'a> Struct<'a> {
+    /// The names of the fields, in the order they are stored in the file.
+    pub fn field_names(&self) -> &'a [String] {
+        self.field_names
+    }
+
+    /// Returns the value of the field with the given name. Case sensitive.
+    pub fn get_field(&self, name: &str) -> Option<&'a Array> {
+        self.field_names
+            .iter()
+            .position(|field_name| field_name == name)
+            .map(|index| &self.values[index])
+    }
+
+    /// Iterates over all fields as pairs of field name and value.
+    pub fn fields(&self) -> impl Iterator<Item = (&'a str, &'a Array)> {
+        self.field_names
+            .iter()
+            .map(|name| name.as_str())
+            .zip(self.values.iter())
+    }
+}
@@ -0,0 +1,138 @@
+use crate::mcos::text_cells;
+use crate::{Array, ArrayData};
+
+/// A `table` or `timetable`.
+///
+/// Each column (variable) of a table is an array of its own, named after the
+/// variable. All columns have the same number of rows. The rows of a table
+/// can optionally be named, the rows of a timetable have times instead.
+///
+/// ```rust
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let file = std::fs::File::open("tests/table.mat")?;
+/// let mat_file = matfile::MatFile::parse(file)?;
+/// if let Some(matfile::ArrayData::Table(table)) = mat_file.find_by_name("T").map(|a| a.data()) {
+///     println!("{} rows, columns {:?}", table.num_rows(), table.column_names());
+///     let age = table.column("Age");
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct Table {
+    is_timetable: bool,
+    num_rows: usize,
+    column_names: Vec<String>,
+    columns: Vec<Array>,
+    row_names: Vec<String>,
+    row_times: Option<Box<Array>>,
+}
+
+impl Table {
+    pub(crate) fn new(
+        is_timetable: bool,
+        num_rows: usize,
+        column_names: Vec<String>,
+        columns: Vec<Array>,
+        row_names: Vec<String>,
+        row_times: Option<Array>,
+    ) -> Self {
+        Table {
+            is_timetable,
+            num_rows,
+            column_names,
+            columns,
+            row_names,
+            row_times: row_times.map(Box::new),
+        }
+    }
+
+    /// Assembles a table from the properties of a `table` or `timetable`
+    /// object. The column data is a cell array with one cell per column and
+    /// the variable (and row) names are cell arrays of character arrays.
+    pub(crate) fn from_properties(
+        is_timetable: bool,
+        data: Array,
+        variable_names: Array,
+        row_names: Option<Array>,
+        row_times: Option<Array>,
+        num_rows: Option<Array>,
+    ) -> Option<Self> {
+        let cells = match data.data {
+            ArrayData::Cell(cells) => cells,
+            _ => return None,
+        };
+        let column_names = text_cells(&variable_names)?;
+        if column_names.len() != cells.len() {
+            return None;
+        }
+        let columns: Vec<Array> = cells
+            .into_cells()
+            .into_iter()
+            .zip(&column_names)
+            .map(|(column, name)| column.with_name(name))
+            .collect();
+        let row_names = row_names
+            .and_then(|names| text_cells(&names))
+            .unwrap_or_default();
+        let num_rows = match num_rows.as_ref().and_then(Array::numeric_data) {
+            Some(crate::NumericData::Double { real, .. }) if real.len() == 1 => real[0] as usize,
+            _ => columns
+                .first()
+                .and_then(|column| column.size().first().copied())
+                .unwrap_or(0),
+        };
+        Some(Table::new(
+            is_timetable,
+            num_rows,
+            column_names,
+            columns,
+            row_names,
+            row_times,
+        ))
+    }
+
+    /// Whether this is a `timetable` rather than a `table`.
+    pub fn is_timetable(&self) -> bool {
+        self.is_timetable
+    }
+
+    /// The number of rows.
+    pub fn num_rows(&self) -> usize {
+        self.num_rows
+    }
+
+    /// The number of columns (variables).
+    pub fn num_columns(&self) -> usize {
+        self.columns.len()
+    }
+
+    /// The names of the columns (variables).
+    pub fn column_names(&self) -> &[String] {
+        &self.column_names
+    }
+
+    /// Returns the column with the given name. Case sensitive.
+    pub fn column(&self, name: &str) -> Option<&Array> {
+        self.column_names
+            .iter()
+            .position(|column_name| column_name == name)
+            .map(|index| &self.columns[index])
+    }
+
+    /// All columns in order.
+    pub fn columns(&self) -> &[Array] {
+        &self.columns
+    }
+
+    /// The names of the rows of a table, empty if the rows are not named.
+    pub fn row_names(&self) -> &[String] {
+        &self.row_names
+    }
+
+    /// The row times of a timetable, usually a `datetime` or `duration`
+    /// array.
+    pub fn row_times(&self) -> Option<&Array> {
+        self.row_times.as_deref()
+    }
+}
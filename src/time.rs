@@ -0,0 +1,160 @@
+/// A `datetime` array.
+///
+/// The points in time are stored as milliseconds since 1970-01-01 00:00:00
+/// in column-major order. Missing values (`NaT`) are stored as NaN. For
+/// datetimes with a time zone the timestamps are in UTC, datetimes without a
+/// time zone hold the displayed date and time as if it was UTC.
+///
+/// ```rust
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let file = std::fs::File::open("tests/datetime.mat")?;
+/// let mat_file = matfile::MatFile::parse(file)?;
+/// if let Some(matfile::ArrayData::Datetime(t)) = mat_file.find_by_name("t").map(|a| a.data()) {
+///     println!("Time zone: {:?}, format: {:?}", t.time_zone(), t.format());
+///     for millis in t.millis() {
+///         println!("{} ms since the epoch", millis);
+///     }
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct DatetimeArray {
+    size: Vec<usize>,
+    millis: Vec<f64>,
+    time_zone: Option<String>,
+    format: Option<String>,
+}
+
+/// A `duration` array.
+///
+/// The durations are stored as (possibly fractional or negative)
+/// milliseconds in column-major order.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DurationArray {
+    size: Vec<usize>,
+    millis: Vec<f64>,
+    format: Option<String>,
+}
+
+impl DatetimeArray {
+    pub(crate) fn new(
+        size: Vec<usize>,
+        millis: Vec<f64>,
+        time_zone: Option<String>,
+        format: Option<String>,
+    ) -> Self {
+        DatetimeArray {
+            size,
+            millis,
+            time_zone,
+            format,
+        }
+    }
+
+    /// The size of this datetime array, see `Array::size`.
+    pub fn size(&self) -> &Vec<usize> {
+        &self.size
+    }
+
+    /// The number of points in time.
+    pub fn len(&self) -> usize {
+        self.millis.len()
+    }
+
+    /// Whether this datetime array is empty.
+    pub fn is_empty(&self) -> bool {
+        self.millis.is_empty()
+    }
+
+    /// The points in time as milliseconds since 1970-01-01 00:00:00, NaN for
+    /// `NaT`.
+    pub fn millis(&self) -> &[f64] {
+        &self.millis
+    }
+
+    /// The time zone (e.g. "Europe/Berlin" or "UTC"), if any.
+    pub fn time_zone(&self) -> Option<&str> {
+        self.time_zone.as_deref()
+    }
+
+    /// The display format (e.g. "yyyy-MM-dd HH:mm:ss"), if one was set.
+    pub fn format(&self) -> Option<&str> {
+        self.format.as_deref()
+    }
+
+    /// Converts the points in time into `chrono` date times, `None` for
+    /// `NaT` and values out of range. For datetimes with a time zone the
+    /// results are in UTC.
+    #[cfg(feature = "chrono")]
+    pub fn to_chrono(&self) -> Vec<Option<chrono::NaiveDateTime>> {
+        self.millis
+            .iter()
+            .map(|&millis| {
+                if !millis.is_finite() {
+                    return None;
+                }
+                let seconds = (millis / 1000.0).floor();
+                let nanos = ((millis - seconds * 1000.0) * 1e6)
+                    .round()
+                    .min(999_999_999.0);
+                chrono::DateTime::from_timestamp(seconds as i64, nanos as u32)
+                    .map(|date_time| date_time.naive_utc())
+            })
+            .collect()
+    }
+}
+
+impl DurationArray {
+    pub(crate) fn new(size: Vec<usize>, millis: Vec<f64>, format: Option<String>) -> Self {
+        DurationArray {
+            size,
+            millis,
+            format,
+        }
+    }
+
+    /// The size of this duration array, see `Array::size`.
+    pub fn size(&self) -> &Vec<usize> {
+        &self.size
+    }
+
+    /// The number of durations.
+    pub fn len(&self) -> usize {
+        self.millis.len()
+    }
+
+    /// Whether this duration array is empty.
+    pub fn is_empty(&self) -> bool {
+        self.millis.is_empty()
+    }
+
+    /// The durations in milliseconds, NaN for missing values.
+    pub fn millis(&self) -> &[f64] {
+        &self.millis
+    }
+
+    /// The display format (e.g. "hh:mm:ss"), if one was set.
+    pub fn format(&self) -> Option<&str> {
+        self.format.as_deref()
+    }
+
+    /// Converts the durations into `chrono` durations, `None` for missing
+    /// values and values out of range.
+    #[cfg(feature = "chrono")]
+    pub fn to_chrono(&self) -> Vec<Option<chrono::Duration>> {
+        self.millis
+            .iter()
+            .map(|&millis| {
+                let nanos = millis * 1e6;
+                if !nanos.is_finite() {
+                    None
+                } else if nanos.abs() < i64::MAX as f64 {
+                    Some(chrono::Duration::nanoseconds(nanos.round() as i64))
+                } else {
+                    chrono::Duration::try_milliseconds(millis as i64)
+                }
+            })
+            .collect()
+    }
+}
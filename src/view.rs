@@ -0,0 +1,214 @@
+use crate::parse::{self, DataType};
+use crate::{Array, ArrayData, Error, FileHeader, MatFileReader, NumericData, VariableInfo};
+use std::borrow::Cow;
+use std::io::Cursor;
+
+/// A .mat file in memory, such as a memory-mapped file, whose numeric arrays
+/// can be accessed without copying them.
+///
+/// Like a [`MatFileReader`], the view indexes the variables of the file first
+/// and decodes them when they are requested. The values of a numeric array
+/// are borrowed from the file if the array is not compressed, stored with
+/// the type of its class and in the byte order of this machine. Otherwise
+/// they are copied.
+///
+/// The view either borrows the file content (e.g. as `&[u8]`) or owns it,
+/// like the `Mmap` of [`MatFileView::map`] with the `mmap` feature.
+///
+/// Only Level 5 files (versions 6 and 7) can be viewed. Files written with
+/// the `-v6` option are not compressed.
+///
+/// ```rust
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let file = std::fs::File::open("tests/small_matrix.mat")?;
+/// // The file must not be modified while it is mapped
+/// let mmap = unsafe { memmap2::Mmap::map(&file)? };
+/// let view = matfile::MatFileView::new(&mmap)?;
+/// if let Some(matfile::NumericDataView::Double { real, .. }) = view.numeric_data("abcde")? {
+///     let values: &[f64] = &real;
+///     println!("{:?}", values);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct MatFileView<D> {
+    reader: MatFileReader<Cursor<D>>,
+}
+
+impl<D: AsRef<[u8]>> MatFileView<D> {
+    /// Indexes the variables of a .mat file.
+    pub fn new(data: D) -> Result<Self, Error> {
+        Ok(MatFileView {
+            reader: MatFileReader::new(Cursor::new(data))?,
+        })
+    }
+
+    /// The header of the file.
+    pub fn header(&self) -> &FileHeader {
+        self.reader.header()
+    }
+
+    /// List of all variables in the file, in the order they are stored.
+    pub fn variables(&self) -> &[VariableInfo] {
+        self.reader.variables()
+    }
+
+    /// Decodes the variable with the given name into an (owned) array.
+    /// Returns `None` if there is no such variable.
+    pub fn load(&self, name: &str) -> Result<Option<Array>, Error> {
+        match self.reader.find(name) {
+            Some(variable) => self
+                .reader
+                .decode(self.element(variable), variable.offset())
+                .map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// The values of the numeric array with the given name, borrowed from the
+    /// file if possible. Returns `None` if there is no numeric array with
+    /// this name.
+    pub fn numeric_data(&self, name: &str) -> Result<Option<NumericDataView<'_>>, Error> {
+        let variable = match self.reader.find(name) {
+            Some(variable) => variable,
+            None => return Ok(None),
+        };
+        if !variable.is_compressed() {
+            let endianness = self.reader.endianness();
+            let borrowed = parse::numeric_parts(endianness, self.element(variable))
+                .and_then(|parts| NumericDataView::borrow(parts, endianness));
+            if borrowed.is_some() {
+                return Ok(borrowed);
+            }
+        }
+        let array = self
+            .reader
+            .decode(self.element(variable), variable.offset())?;
+        match array.data {
+            ArrayData::Numeric(data) => Ok(Some(NumericDataView::from(data))),
+            _ => Ok(None),
+        }
+    }
+
+    fn element(&self, variable: &VariableInfo) -> &[u8] {
+        let start = variable.offset() as usize;
+        &self.reader.get_ref().get_ref().as_ref()[start..start + variable.stored_size() as usize]
+    }
+}
+
+#[cfg(feature = "mmap")]
+impl MatFileView<memmap2::Mmap> {
+    /// Maps a file into memory and indexes its variables.
+    ///
+    /// ```rust
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let file = std::fs::File::open("tests/small_matrix.mat")?;
+    /// let view = unsafe { matfile::MatFileView::map(&file)? };
+    /// println!("{:?}", view.numeric_data("abcde")?);
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Safety
+    ///
+    /// The file must not be modified while it is mapped, see
+    /// [`memmap2::Mmap::map`].
+    pub unsafe fn map(file: &std::fs::File) -> Result<Self, Error> {
+        Self::new(memmap2::Mmap::map(file).map_err(Error::IOError)?)
+    }
+}
+
+/// Reinterprets bytes as numbers if they are aligned and in the byte order
+/// of this machine.
+fn cast<T: parse::Number>(bytes: &[u8], endianness: nom::number::Endianness) -> Option<&[T]> {
+    if !parse::is_native(endianness)
+        || bytes.as_ptr() as usize % std::mem::align_of::<T>() != 0
+        || bytes.len() % std::mem::size_of::<T>() != 0
+    {
+        return None;
+    }
+    // Safety: the pointer is aligned for `T` and the bytes hold a whole number
+    // of values. Any bit pattern is a valid `T` as it implements `Number`.
+    Some(unsafe {
+        std::slice::from_raw_parts(
+            bytes.as_ptr() as *const T,
+            bytes.len() / std::mem::size_of::<T>(),
+        )
+    })
+}
+
+macro_rules! numeric_data_view {
+    ($($variant:ident($t:ty)),*) => {
+        /// The values of a numeric array of a [`MatFileView`], borrowed from
+        /// the file where possible. Like `NumericData`, the values are stored
+        /// in column-major order with the imaginary part being optional.
+        #[derive(Clone, Debug, PartialEq)]
+        pub enum NumericDataView<'a> {
+            $($variant {
+                real: Cow<'a, [$t]>,
+                imag: Option<Cow<'a, [$t]>>,
+            },)*
+        }
+
+        impl<'a> NumericDataView<'a> {
+            fn borrow(
+                parts: parse::NumericParts<'a>,
+                endianness: nom::number::Endianness,
+            ) -> Option<Self> {
+                match parts.data_type {
+                    $(DataType::$variant => Some(NumericDataView::$variant {
+                        real: Cow::Borrowed(cast(parts.real, endianness)?),
+                        imag: match parts.imag {
+                            Some(imag) => Some(Cow::Borrowed(cast(imag, endianness)?)),
+                            None => None,
+                        },
+                    }),)*
+                    _ => None,
+                }
+            }
+
+            /// Whether the values are borrowed from the file rather than
+            /// copied.
+            pub fn is_borrowed(&self) -> bool {
+                match self {
+                    $(NumericDataView::$variant { real, .. } => matches!(real, Cow::Borrowed(_)),)*
+                }
+            }
+
+            /// Copies the values if they are borrowed.
+            pub fn into_owned(self) -> NumericData {
+                match self {
+                    $(NumericDataView::$variant { real, imag } => NumericData::$variant {
+                        real: real.into_owned(),
+                        imag: imag.map(Cow::into_owned),
+                    },)*
+                }
+            }
+        }
+
+        impl From<NumericData> for NumericDataView<'_> {
+            fn from(data: NumericData) -> Self {
+                match data {
+                    $(NumericData::$variant { real, imag } => NumericDataView::$variant {
+                        real: Cow::Owned(real),
+                        imag: imag.map(Cow::Owned),
+                    },)*
+                }
+            }
+        }
+    };
+}
+
+numeric_data_view!(
+    Int8(i8),
+    UInt8(u8),
+    Int16(i16),
+    UInt16(u16),
+    Int32(i32),
+    UInt32(u32),
+    Int64(i64),
+    UInt64(u64),
+    Single(f32),
+    Double(f64)
+);
@@ -0,0 +1,130 @@
+use crate::{mcos, parse};
+
+/// A recoverable problem found while parsing a .mat file.
+///
+/// The file is still loaded, but the affected content is left out. Warnings
+/// can be received with [`ParseOptions::on_warning`](crate::ParseOptions::on_warning)
+/// and are logged with the `log` crate if the `log` feature is enabled.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Warning {
+    /// A variable was left out because it can't be loaded
+    SkippedVariable(SkippedVariable),
+    /// A data element that is not a variable was ignored. Holds the data
+    /// type of the element.
+    IgnoredDataElement { data_type: u32 },
+    /// The header points to subsystem data that can't be read, so objects of
+    /// `classdef` classes (such as strings) can't be loaded
+    IgnoredSubsystemData { offset: u64 },
+}
+
+impl std::fmt::Display for Warning {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Warning::SkippedVariable(skipped) => write!(f, "Skipped {}", skipped),
+            Warning::IgnoredDataElement { data_type } => {
+                write!(f, "Ignored data element of data type {}", data_type)
+            }
+            Warning::IgnoredSubsystemData { offset } => {
+                write!(f, "Ignored invalid subsystem data at offset {}", offset)
+            }
+        }
+    }
+}
+
+/// A variable of a .mat file that could not be loaded.
+///
+/// These variables are listed by [`MatFile::skipped`](crate::MatFile::skipped).
+#[derive(Clone, Debug, PartialEq)]
+pub struct SkippedVariable {
+    name: String,
+    class: String,
+    size: Vec<usize>,
+    reason: SkipReason,
+}
+
+/// The reason why a variable could not be loaded.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SkipReason {
+    /// Arrays of this class are not supported, like objects of most
+    /// `classdef` classes
+    UnsupportedClass,
+    /// The array holds values that are not supported, like a cell array
+    /// containing an object of an unsupported class
+    UnsupportedContent,
+}
+
+impl SkippedVariable {
+    pub(crate) fn from_data_element(data_element: &parse::DataElement) -> Self {
+        use parse::DataElement;
+        let reason = match data_element {
+            DataElement::OpaqueMatrix(..)
+            | DataElement::UnsupportedMatrix(..)
+            | DataElement::Unsupported(_) => SkipReason::UnsupportedClass,
+            _ => SkipReason::UnsupportedContent,
+        };
+        SkippedVariable {
+            name: data_element.name().to_owned(),
+            class: data_element.class_name(),
+            size: variable_size(data_element),
+            reason,
+        }
+    }
+
+    /// The name of the variable.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The Matlab class of the variable, such as "cell" or the name of a
+    /// `classdef` class.
+    pub fn class(&self) -> &str {
+        &self.class
+    }
+
+    /// The size of the variable, if it is known. Empty otherwise.
+    pub fn size(&self) -> &Vec<usize> {
+        &self.size
+    }
+
+    /// Why the variable could not be loaded.
+    pub fn reason(&self) -> SkipReason {
+        self.reason
+    }
+}
+
+impl std::fmt::Display for SkippedVariable {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let size: Vec<String> = self.size.iter().map(|d| d.to_string()).collect();
+        write!(
+            f,
+            "variable \"{}\" ({} {}): ",
+            self.name,
+            size.join("x"),
+            self.class
+        )?;
+        match self.reason {
+            SkipReason::UnsupportedClass => write!(f, "class not supported"),
+            SkipReason::UnsupportedContent => write!(f, "content not supported"),
+        }
+    }
+}
+
+/// The size of a variable. Opaque arrays have no dimensions, so the size of
+/// `classdef` objects is taken from the reference to them, if possible.
+pub(crate) fn variable_size(data_element: &parse::DataElement) -> Vec<usize> {
+    use parse::DataElement;
+    match data_element {
+        DataElement::NumericMatrix(_, dims, ..)
+        | DataElement::SparseMatrix(_, dims, ..)
+        | DataElement::CharacterMatrix(_, dims, ..)
+        | DataElement::CellMatrix(_, dims, ..)
+        | DataElement::StructureMatrix(_, dims, ..)
+        | DataElement::ObjectMatrix(_, dims, ..)
+        | DataElement::FunctionMatrix(_, dims, ..)
+        | DataElement::UnsupportedMatrix(dims, ..) => dims.iter().map(|&d| d as usize).collect(),
+        DataElement::OpaqueMatrix(_, _, type_system, _, data) => {
+            mcos::opaque_size(type_system, data).unwrap_or_else(|| vec![1, 1])
+        }
+        DataElement::Unsupported(_) => Vec::new(),
+    }
+}
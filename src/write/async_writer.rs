@@ -0,0 +1,92 @@
+use super::{writers, MatFileWriter};
+
+use crate::parse::NumericData;
+
+use std::io::{Error, Result};
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+
+/// Writes .mat files to an asynchronous writer, like [`MatFileWriter`] does
+/// to a synchronous one.
+///
+/// The variables are encoded on the blocking thread pool of the `tokio`
+/// runtime, so the writer must be used within one.
+///
+/// ```rust
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// # let runtime = tokio::runtime::Builder::new_current_thread().build()?;
+/// # runtime.block_on(async {
+/// use matfile::{parse::NumericData, write::AsyncMatFileWriter};
+///
+/// let mut buf = Vec::new();
+/// let mut writer = AsyncMatFileWriter::new(&mut buf).await?;
+/// writer
+///     .write_array("x", NumericData::Double(vec![1.0, 2.0, 3.0]), None)
+///     .await?;
+/// # Ok::<(), std::io::Error>(())
+/// # })?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct AsyncMatFileWriter<'a, W: AsyncWrite + Unpin>(&'a mut W);
+
+impl<'a, W: AsyncWrite + Unpin> AsyncMatFileWriter<'a, W> {
+    pub async fn new(w: &'a mut W) -> Result<Self> {
+        Self::new_with_description(w, "MATLAB 5.0 MAT-file, Platform: matfile-rs").await
+    }
+
+    pub async fn new_with_description(w: &'a mut W, description: &str) -> Result<Self> {
+        let mut header = Vec::with_capacity(128);
+        writers::write_header(&mut header, description)?;
+        w.write_all(&header).await?;
+
+        Ok(AsyncMatFileWriter(w))
+    }
+
+    pub async fn write_array(
+        &mut self,
+        array_name: &str,
+        real: NumericData,
+        imag: Option<NumericData>,
+    ) -> Result<()> {
+        let array_name = array_name.to_owned();
+        self.write_encoded(move |w| w.write_array(&array_name, real, imag))
+            .await
+    }
+
+    /// Writes a logical array, see [`MatFileWriter::write_logical_array`].
+    pub async fn write_logical_array(
+        &mut self,
+        array_name: &str,
+        dims: &[usize],
+        data: &[bool],
+    ) -> Result<()> {
+        let array_name = array_name.to_owned();
+        let dims = dims.to_vec();
+        let data = data.to_vec();
+        self.write_encoded(move |w| w.write_logical_array(&array_name, &dims, &data))
+            .await
+    }
+
+    /// Encodes a variable with a synchronous writer on the blocking thread
+    /// pool and writes the result.
+    async fn write_encoded<F>(&mut self, encode: F) -> Result<()>
+    where
+        F: FnOnce(&mut MatFileWriter<Vec<u8>>) -> Result<()> + Send + 'static,
+    {
+        let encoded = tokio::task::spawn_blocking(move || -> Result<Vec<u8>> {
+            let mut buf = Vec::new();
+            encode(&mut MatFileWriter(&mut buf))?;
+            Ok(buf)
+        });
+        let buf = match encoded.await {
+            Ok(result) => result?,
+            Err(err) if err.is_panic() => std::panic::resume_unwind(err.into_panic()),
+            Err(err) => return Err(Error::other(err)),
+        };
+
+        self.0.write_all(&buf).await?;
+        self.0.flush().await?;
+
+        Ok(())
+    }
+}
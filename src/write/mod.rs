@@ -3,11 +3,16 @@ mod tests;
 
 mod writers;
 
-use num_traits::ToBytes;
+#[cfg(feature = "tokio")]
+mod async_writer;
 
-use crate::parse::{ArrayFlags, ArrayType, DataType, NumericData};
+#[cfg(feature = "tokio")]
+pub use async_writer::AsyncMatFileWriter;
 
-use std::io::{Result, Write};
+use crate::parse::{ArrayFlags, ArrayType, DataElement, NumericData};
+
+use std::convert::TryFrom;
+use std::io::{Error, ErrorKind, Result, Write};
 
 pub struct MatFileWriter<'a, W: Write>(&'a mut W);
 
@@ -36,49 +41,73 @@ impl<'a, W: Write> MatFileWriter<'a, W> {
             assert_eq!(real.len(), imag.len())
         }
 
-        let dim = vec![1i32, real.len() as i32];
-
-        // This should avoid expensive reallocations for big arrays
-        let estimated_size = {
-            use writers::sizes::*;
-            dimensions(dim.len())
-                + name(array_name.bytes().len())
-                + numeric_subelement(real.data_type(), real.len())
-                + match imag {
-                    Some(ref imag) => numeric_subelement(imag.data_type(), imag.len()),
-                    None => 0,
-                }
-        };
-
-        let mut buf = Vec::with_capacity(estimated_size);
-        {
-            writers::write_sub_element_array_flags(
-                &mut buf,
-                ArrayFlags {
-                    complex,
-                    global: false,
-                    logical: false,
-                    class: ArrayType::Double,
-                    nzmax: 0,
-                },
-            )?;
-
-            writers::write_sub_element_dimensions(&mut buf, &dim)?;
-            writers::write_sub_element_array_name(&mut buf, &array_name)?;
-
-            writers::write_sub_element_real_part(&mut buf, real.data_type(), &real.to_ne_bytes())?;
-
-            if let Some(imag) = imag {
-                writers::write_sub_element_imaginary_part(
-                    &mut buf,
-                    imag.data_type(),
-                    &imag.to_ne_bytes(),
-                )?;
-            }
+        self.write_matrix(
+            array_name,
+            ArrayFlags {
+                complex,
+                global: false,
+                logical: false,
+                class: ArrayType::Double,
+                nzmax: 0,
+            },
+            &[1, real.len()],
+            real,
+            imag,
+        )
+    }
+
+    /// Writes a logical array with the given dimensions, whose product must
+    /// be the number of values. Matlab stores logical values as 8 bit
+    /// unsigned integers with the logical flag set.
+    pub fn write_logical_array(
+        &mut self,
+        array_name: &str,
+        dims: &[usize],
+        data: &[bool],
+    ) -> Result<()> {
+        self.write_matrix(
+            array_name,
+            ArrayFlags {
+                complex: false,
+                global: false,
+                logical: true,
+                class: ArrayType::UInt8,
+                nzmax: 0,
+            },
+            dims,
+            NumericData::UInt8(data.iter().map(|&x| x as u8).collect()),
+            None,
+        )
+    }
+
+    fn write_matrix(
+        &mut self,
+        array_name: &str,
+        array_flags: ArrayFlags,
+        dims: &[usize],
+        real: NumericData,
+        imag: Option<NumericData>,
+    ) -> Result<()> {
+        if dims.iter().try_fold(1usize, |n, &d| n.checked_mul(d)) != Some(real.len()) {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "The dimensions don't match the number of values",
+            ));
         }
+        let dims = dims
+            .iter()
+            .map(|&d| i32::try_from(d))
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .map_err(|_| Error::new(ErrorKind::InvalidInput, "The dimensions are too large"))?;
+
+        let element =
+            DataElement::NumericMatrix(array_flags, dims, array_name.to_owned(), real, imag);
 
-        writers::write_data_element(self.0, DataType::Matrix, &buf)?;
+        // Encode the whole element first to write it at once
+        let mut buf = Vec::with_capacity(8 + writers::sizes::data_element(&element));
+        writers::write_matrix(&mut buf, element)?;
 
+        self.0.write_all(&buf)?;
         self.0.flush()?;
 
         Ok(())
@@ -193,4 +193,33 @@ mod test {
         }
         // assert_eq!(buf, REFERENCE);
     }
+
+    #[test]
+    fn write_logical_array_round_trip() {
+        let mut buf = Vec::new();
+        {
+            let mut writer = super::super::MatFileWriter::new(&mut buf)
+                .expect("Writing into a buffer should not fail");
+            writer
+                .write_logical_array("mask", &[2, 2], &[true, false, true, true])
+                .expect("Writing into a buffer should not fail");
+            writer
+                .write_logical_array("cube", &[1, 2, 1, 2], &[false, true, true, false])
+                .expect("Writing into a buffer should not fail");
+            assert!(writer
+                .write_logical_array("invalid", &[2, 2], &[true, false, true])
+                .is_err());
+        }
+
+        let mat_file = crate::MatFile::parse(buf.as_slice()).unwrap();
+        let array = mat_file.find_by_name("mask").unwrap();
+        assert_eq!(array.size(), &vec![2, 2]);
+        match array.data() {
+            crate::ArrayData::Logical(data) => assert_eq!(data, &vec![true, false, true, true]),
+            _ => panic!("Expected a logical array"),
+        }
+        let array = mat_file.find_by_name("cube").unwrap();
+        assert_eq!(array.size(), &vec![1, 2, 1, 2]);
+        assert!(mat_file.find_by_name("invalid").is_none());
+    }
 }
@@ -22,7 +22,7 @@ pub fn write_header<W: Write>(w: &mut W, text: &str) -> Result<()> {
     w.write_all(&vec![32; 116 - text_bytes.len()])?;
 
     // Indicate no subsystem specific data
-    w.write_all(&vec![0; 8])?;
+    w.write_all(&[0; 8])?;
 
     // Set version to 0x0100
     w.write_all(&[0b00000000, 0b00000001])?;
@@ -85,6 +85,8 @@ pub fn write_matrix<W: Write>(w: &mut W, data_element: DataElement) -> Result<()
     Ok(())
 }
 
+// Only used to check the encoding in the tests
+#[cfg(test)]
 pub fn write_data_element<W: Write>(
     w: &mut W,
     data_type: DataType,
@@ -117,7 +119,7 @@ pub fn write_sub_element_array_flags<W: Write>(w: &mut W, array_flags: ArrayFlag
     // Sub element data type
     w.write_all(&(DataType::UInt32 as u32).to_ne_bytes())?;
     // Sub element number of bytes
-    w.write_all(&(8 as u32).to_ne_bytes())?;
+    w.write_all(&8_u32.to_ne_bytes())?;
 
     let class = array_flags.class as u8;
     let flags = ((array_flags.complex as u8) << 3)
@@ -136,8 +138,6 @@ pub fn write_sub_element_array_flags<W: Write>(w: &mut W, array_flags: ArrayFlag
 }
 
 pub fn write_sub_element_dimensions<W: Write>(w: &mut W, dimensions: &[i32]) -> Result<()> {
-    assert!(dimensions.len() <= 3);
-
     // Sub element data type
     w.write_all(&(DataType::Int32 as u32).to_ne_bytes())?;
 
@@ -216,48 +216,48 @@ impl ToBytes for NumericData {
     fn to_ne_bytes(&self) -> Vec<u8> {
         // !TODO check soundness of align_to for potential big speed improvement
         match self {
-            NumericData::Single(vec) => vec.into_iter().flat_map(|&n| n.to_ne_bytes()).collect(),
-            NumericData::Double(vec) => vec.into_iter().flat_map(|&n| n.to_ne_bytes()).collect(),
-            NumericData::Int8(vec) => vec.into_iter().flat_map(|&n| n.to_ne_bytes()).collect(),
-            NumericData::UInt8(vec) => vec.into_iter().flat_map(|&n| n.to_ne_bytes()).collect(),
-            NumericData::Int16(vec) => vec.into_iter().flat_map(|&n| n.to_ne_bytes()).collect(),
-            NumericData::UInt16(vec) => vec.into_iter().flat_map(|&n| n.to_ne_bytes()).collect(),
-            NumericData::Int32(vec) => vec.into_iter().flat_map(|&n| n.to_ne_bytes()).collect(),
-            NumericData::UInt32(vec) => vec.into_iter().flat_map(|&n| n.to_ne_bytes()).collect(),
-            NumericData::Int64(vec) => vec.into_iter().flat_map(|&n| n.to_ne_bytes()).collect(),
-            NumericData::UInt64(vec) => vec.into_iter().flat_map(|&n| n.to_ne_bytes()).collect(),
+            NumericData::Single(vec) => vec.iter().flat_map(|&n| n.to_ne_bytes()).collect(),
+            NumericData::Double(vec) => vec.iter().flat_map(|&n| n.to_ne_bytes()).collect(),
+            NumericData::Int8(vec) => vec.iter().flat_map(|&n| n.to_ne_bytes()).collect(),
+            NumericData::UInt8(vec) => vec.iter().flat_map(|&n| n.to_ne_bytes()).collect(),
+            NumericData::Int16(vec) => vec.iter().flat_map(|&n| n.to_ne_bytes()).collect(),
+            NumericData::UInt16(vec) => vec.iter().flat_map(|&n| n.to_ne_bytes()).collect(),
+            NumericData::Int32(vec) => vec.iter().flat_map(|&n| n.to_ne_bytes()).collect(),
+            NumericData::UInt32(vec) => vec.iter().flat_map(|&n| n.to_ne_bytes()).collect(),
+            NumericData::Int64(vec) => vec.iter().flat_map(|&n| n.to_ne_bytes()).collect(),
+            NumericData::UInt64(vec) => vec.iter().flat_map(|&n| n.to_ne_bytes()).collect(),
         }
     }
 
     fn to_be_bytes(&self) -> Vec<u8> {
         // !TODO check soundness of align_to for potential big speed improvement
         match self {
-            NumericData::Single(vec) => vec.into_iter().flat_map(|&n| n.to_ne_bytes()).collect(),
-            NumericData::Double(vec) => vec.into_iter().flat_map(|&n| n.to_ne_bytes()).collect(),
-            NumericData::Int8(vec) => vec.into_iter().flat_map(|&n| n.to_ne_bytes()).collect(),
-            NumericData::UInt8(vec) => vec.into_iter().flat_map(|&n| n.to_ne_bytes()).collect(),
-            NumericData::Int16(vec) => vec.into_iter().flat_map(|&n| n.to_ne_bytes()).collect(),
-            NumericData::UInt16(vec) => vec.into_iter().flat_map(|&n| n.to_ne_bytes()).collect(),
-            NumericData::Int32(vec) => vec.into_iter().flat_map(|&n| n.to_ne_bytes()).collect(),
-            NumericData::UInt32(vec) => vec.into_iter().flat_map(|&n| n.to_ne_bytes()).collect(),
-            NumericData::Int64(vec) => vec.into_iter().flat_map(|&n| n.to_ne_bytes()).collect(),
-            NumericData::UInt64(vec) => vec.into_iter().flat_map(|&n| n.to_ne_bytes()).collect(),
+            NumericData::Single(vec) => vec.iter().flat_map(|&n| n.to_ne_bytes()).collect(),
+            NumericData::Double(vec) => vec.iter().flat_map(|&n| n.to_ne_bytes()).collect(),
+            NumericData::Int8(vec) => vec.iter().flat_map(|&n| n.to_ne_bytes()).collect(),
+            NumericData::UInt8(vec) => vec.iter().flat_map(|&n| n.to_ne_bytes()).collect(),
+            NumericData::Int16(vec) => vec.iter().flat_map(|&n| n.to_ne_bytes()).collect(),
+            NumericData::UInt16(vec) => vec.iter().flat_map(|&n| n.to_ne_bytes()).collect(),
+            NumericData::Int32(vec) => vec.iter().flat_map(|&n| n.to_ne_bytes()).collect(),
+            NumericData::UInt32(vec) => vec.iter().flat_map(|&n| n.to_ne_bytes()).collect(),
+            NumericData::Int64(vec) => vec.iter().flat_map(|&n| n.to_ne_bytes()).collect(),
+            NumericData::UInt64(vec) => vec.iter().flat_map(|&n| n.to_ne_bytes()).collect(),
         }
     }
 
     fn to_le_bytes(&self) -> Vec<u8> {
         // !TODO check soundness of align_to for potential big speed improvement
         match self {
-            NumericData::Single(vec) => vec.into_iter().flat_map(|&n| n.to_ne_bytes()).collect(),
-            NumericData::Double(vec) => vec.into_iter().flat_map(|&n| n.to_ne_bytes()).collect(),
-            NumericData::Int8(vec) => vec.into_iter().flat_map(|&n| n.to_ne_bytes()).collect(),
-            NumericData::UInt8(vec) => vec.into_iter().flat_map(|&n| n.to_ne_bytes()).collect(),
-            NumericData::Int16(vec) => vec.into_iter().flat_map(|&n| n.to_ne_bytes()).collect(),
-            NumericData::UInt16(vec) => vec.into_iter().flat_map(|&n| n.to_ne_bytes()).collect(),
-            NumericData::Int32(vec) => vec.into_iter().flat_map(|&n| n.to_ne_bytes()).collect(),
-            NumericData::UInt32(vec) => vec.into_iter().flat_map(|&n| n.to_ne_bytes()).collect(),
-            NumericData::Int64(vec) => vec.into_iter().flat_map(|&n| n.to_ne_bytes()).collect(),
-            NumericData::UInt64(vec) => vec.into_iter().flat_map(|&n| n.to_ne_bytes()).collect(),
+            NumericData::Single(vec) => vec.iter().flat_map(|&n| n.to_ne_bytes()).collect(),
+            NumericData::Double(vec) => vec.iter().flat_map(|&n| n.to_ne_bytes()).collect(),
+            NumericData::Int8(vec) => vec.iter().flat_map(|&n| n.to_ne_bytes()).collect(),
+            NumericData::UInt8(vec) => vec.iter().flat_map(|&n| n.to_ne_bytes()).collect(),
+            NumericData::Int16(vec) => vec.iter().flat_map(|&n| n.to_ne_bytes()).collect(),
+            NumericData::UInt16(vec) => vec.iter().flat_map(|&n| n.to_ne_bytes()).collect(),
+            NumericData::Int32(vec) => vec.iter().flat_map(|&n| n.to_ne_bytes()).collect(),
+            NumericData::UInt32(vec) => vec.iter().flat_map(|&n| n.to_ne_bytes()).collect(),
+            NumericData::Int64(vec) => vec.iter().flat_map(|&n| n.to_ne_bytes()).collect(),
+            NumericData::UInt64(vec) => vec.iter().flat_map(|&n| n.to_ne_bytes()).collect(),
         }
     }
 }
@@ -281,7 +281,7 @@ pub mod sizes {
 
         let array_flags_size = 8 + 8;
         let dimensions_size = self::dimensions(dimensions.len());
-        let matrix_name_size = name(matrix_name.bytes().len());
+        let matrix_name_size = name(matrix_name.len());
         let real_part_size = numeric_subelement(real_part.data_type(), real_part.len());
         let imaginary_part_size = match imaginary_part {
             Some(part) => numeric_subelement(part.data_type(), part.len()),